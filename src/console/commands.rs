@@ -0,0 +1,366 @@
+use bevy::prelude::*;
+use anyhow::{Result, anyhow, bail};
+use std::collections::BTreeMap;
+use crate::physics::rigid_body::{PhysicsEngine, RigidBodyHandle};
+use crate::player::EYE_HEIGHT;
+use crate::player::player::PlayerMovement;
+use crate::player::survival::GameMode;
+use crate::world::chunk::ChunkManager;
+use crate::world::block_types::{StaticBlocksRes, block_by_name};
+use crate::world::coordinates::BlockPosition;
+use crate::world::block_inner::BlockInner;
+use crate::world::time::{WorldTime, DAY_LENGTH, parse_time_of_day};
+use crate::world::weather::{WeatherState, parse_weather};
+
+/// the maximal number of blocks changed by one `/fill`
+pub const MAX_FILL_BLOCKS: i64 = 32768;
+
+/// A command gets the arguments after its name and returns the message for the console
+pub type CommandHandler = fn(&[&str], &mut World, &mut Resources) -> Result<String>;
+
+#[derive(Clone)]
+struct RegisteredCommand {
+    /// the arguments, shown if the command fails
+    usage: &'static str,
+    handler: CommandHandler,
+}
+
+/// The slash commands of the console.
+///
+/// Commands run with exclusive access to the world, so they can be tested on an app without rendering.
+#[derive(Clone, Default)]
+pub struct CommandRegistry {
+    commands: BTreeMap<&'static str, RegisteredCommand>,
+}
+
+impl CommandRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn with_builtin_commands() -> Self {
+        let mut registry = Self::new();
+        registry.register("tp", "<x> <y> <z>", teleport);
+        registry.register("seed", "", seed);
+        registry.register("setblock", "<x> <y> <z> <block>", set_block);
+        registry.register("fill", "<x1> <y1> <z1> <x2> <y2> <z2> <block>", fill);
+        registry.register("time", "set <day|noon|night|midnight|ticks>", time);
+        registry.register("weather", "<clear|rain|thunder>", weather);
+        registry.register("gamemode", "<survival|creative>", game_mode);
+        registry
+    }
+    pub fn register(&mut self, name: &'static str, usage: &'static str, handler: CommandHandler) {
+        self.commands.insert(name, RegisteredCommand {
+            usage,
+            handler,
+        });
+    }
+    pub fn names(&self) -> impl Iterator<Item=&'static str> + '_ {
+        self.commands.keys().cloned()
+    }
+    /// runs a command line, the leading slash is optional
+    pub fn execute(&self, line: &str, world: &mut World, resources: &mut Resources) -> Result<String> {
+        let mut parts = line.trim().trim_start_matches('/').split_whitespace();
+        let name = parts.next().ok_or_else(||anyhow!("empty command"))?;
+        let arguments: Vec<&str> = parts.collect();
+        if name == "help" {
+            return Ok(self.commands.iter()
+                .map(|(name, command)|format!("/{} {}", name, command.usage))
+                .collect::<Vec<_>>()
+                .join("\n"));
+        }
+        let command = self.commands.get(name).ok_or_else(||anyhow!("unknown command /{}, try /help", name))?;
+        (command.handler)(&arguments, world, resources)
+            .map_err(|error|anyhow!("{}\nusage: /{} {}", error, name, command.usage))
+    }
+}
+
+fn not_loaded() -> anyhow::Error {
+    anyhow!("no world is loaded")
+}
+
+fn expect_arguments(arguments: &[&str], count: usize) -> Result<()> {
+    if arguments.len() != count {
+        bail!("expected {} arguments, got {}", count, arguments.len());
+    }
+    Ok(())
+}
+
+fn player(world: &World) -> Result<Entity> {
+    world.query_filtered::<Entity, With<PlayerMovement>>()
+        .next()
+        .ok_or_else(||anyhow!("there is no player"))
+}
+
+/// the position of the feet of the player
+fn player_position(world: &World, resources: &Resources) -> Result<Vec3> {
+    let handle = *world.get::<RigidBodyHandle>(player(world)?)?;
+    let physics = resources.get::<PhysicsEngine>().ok_or_else(not_loaded)?;
+    let body = physics.get(handle).ok_or_else(||anyhow!("the player has no body"))?;
+    Ok(body.position() - Vec3::unit_y() * EYE_HEIGHT)
+}
+
+/// a number or a tilde for the coordinate of the player, optionally followed by an offset
+pub fn parse_coordinate(argument: &str, origin: f32) -> Result<f32> {
+    let parse = |number: &str|number.parse::<f32>().map_err(|_|anyhow!("invalid coordinate: {}", argument));
+    match argument.strip_prefix('~') {
+        Some("") => Ok(origin),
+        Some(offset) => Ok(origin + parse(offset)?),
+        None => parse(argument),
+    }
+}
+
+fn parse_position(arguments: &[&str], origin: Vec3) -> Result<Vec3> {
+    Ok(Vec3::new(
+        parse_coordinate(arguments[0], origin.x)?,
+        parse_coordinate(arguments[1], origin.y)?,
+        parse_coordinate(arguments[2], origin.z)?,
+    ))
+}
+
+fn teleport(arguments: &[&str], world: &mut World, resources: &mut Resources) -> Result<String> {
+    expect_arguments(arguments, 3)?;
+    let position = parse_position(arguments, player_position(world, resources)?)?;
+    let handle = *world.get::<RigidBodyHandle>(player(world)?)?;
+    let mut physics = resources.get_mut::<PhysicsEngine>().ok_or_else(not_loaded)?;
+    let body = physics.get_mut(handle).ok_or_else(||anyhow!("the player has no body"))?;
+    body.teleport(position + Vec3::unit_y() * EYE_HEIGHT);
+    Ok(format!("teleported to {:.1} {:.1} {:.1}", position.x, position.y, position.z))
+}
+
+fn seed(arguments: &[&str], _world: &mut World, resources: &mut Resources) -> Result<String> {
+    expect_arguments(arguments, 0)?;
+    let chunk_manager = resources.get::<ChunkManager>().ok_or_else(not_loaded)?;
+    Ok(format!("seed: {}", chunk_manager.provider().seed()))
+}
+
+fn block(resources: &Resources, name: &str) -> Result<BlockInner> {
+    let static_blocks = resources.get::<StaticBlocksRes>().ok_or_else(not_loaded)?;
+    block_by_name(&static_blocks, name).ok_or_else(||anyhow!("unknown block: {}", name))
+}
+
+fn set_block(arguments: &[&str], world: &mut World, resources: &mut Resources) -> Result<String> {
+    expect_arguments(arguments, 4)?;
+    let position = BlockPosition::from_vector(parse_position(arguments, player_position(world, resources)?)?);
+    let block = block(resources, arguments[3])?;
+    let mut chunk_manager = resources.get_mut::<ChunkManager>().ok_or_else(not_loaded)?;
+    if !chunk_manager.set_in_world(position, block, world) {
+        bail!("{} isnt loaded", position);
+    }
+    Ok(format!("placed {} at {}", arguments[3], position))
+}
+
+fn fill(arguments: &[&str], world: &mut World, resources: &mut Resources) -> Result<String> {
+    expect_arguments(arguments, 7)?;
+    let origin = player_position(world, resources)?;
+    let from = BlockPosition::from_vector(parse_position(&arguments[0..3], origin)?);
+    let to = BlockPosition::from_vector(parse_position(&arguments[3..6], origin)?);
+    let (min_x, max_x) = (from.x().min(to.x()), from.x().max(to.x()));
+    let (min_y, max_y) = (from.y().min(to.y()), from.y().max(to.y()));
+    let (min_z, max_z) = (from.z().min(to.z()), from.z().max(to.z()));
+    //far apart coordinates overflow the volume
+    let length = |min: i64, max: i64|max.checked_sub(min).and_then(|length|length.checked_add(1));
+    let volume = length(min_x, max_x)
+        .zip(length(min_y, max_y))
+        .zip(length(min_z, max_z))
+        .and_then(|((x, y), z)|x.checked_mul(y)?.checked_mul(z))
+        .filter(|volume|*volume <= MAX_FILL_BLOCKS)
+        .ok_or_else(||anyhow!("too many blocks, at most {} can be filled", MAX_FILL_BLOCKS))?;
+    let block = block(resources, arguments[6])?;
+    let mut chunk_manager = resources.get_mut::<ChunkManager>().ok_or_else(not_loaded)?;
+    let mut changed = 0;
+    for x in min_x..=max_x {
+        for y in min_y..=max_y {
+            for z in min_z..=max_z {
+                if chunk_manager.set_in_world(BlockPosition::new(x, y, z), block, world) {
+                    changed += 1;
+                }
+            }
+        }
+    }
+    Ok(format!("filled {} of {} blocks with {}", changed, volume, arguments[6]))
+}
+
+fn time(arguments: &[&str], _world: &mut World, resources: &mut Resources) -> Result<String> {
+    let mut world_time = resources.get_mut::<WorldTime>().ok_or_else(not_loaded)?;
+    match arguments {
+        [] => Ok(format!("time: {} (day {})", world_time.time_of_day(), world_time.ticks() / DAY_LENGTH)),
+        ["set", time] => {
+            let time = parse_time_of_day(time).ok_or_else(||anyhow!("invalid time: {}", time))?;
+            world_time.set_time_of_day(time);
+            Ok(format!("set the time to {}", world_time.time_of_day()))
+        }
+        _ => bail!("expected set and a time"),
+    }
+}
+
+fn weather(arguments: &[&str], _world: &mut World, resources: &mut Resources) -> Result<String> {
+    let mut state = resources.get_mut::<WeatherState>().ok_or_else(not_loaded)?;
+    match arguments {
+        [] => Ok(format!("weather: {:?}", state.weather())),
+        [weather] => {
+            let weather = parse_weather(weather).ok_or_else(||anyhow!("unknown weather: {}", weather))?;
+            state.set(weather, &mut rand::thread_rng());
+            resources.get_mut::<ChunkManager>().ok_or_else(not_loaded)?.provider_mut().save_weather(&state);
+            Ok(format!("set the weather to {:?}", weather))
+        }
+        _ => bail!("expected a weather"),
+    }
+}
+
+fn game_mode(arguments: &[&str], world: &mut World, resources: &mut Resources) -> Result<String> {
+    expect_arguments(arguments, 1)?;
+    let new_mode = match arguments[0] {
+        "survival" | "0" => GameMode::Survival,
+        "creative" | "1" => GameMode::Creative,
+        other => bail!("unknown game mode: {}", other),
+    };
+    //apply_game_mode reacts to the change
+    *world.get_mut::<GameMode>(player(world)?)? = new_mode;
+    Ok(format!("game mode set to {:?}", new_mode))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use crate::content::in_memory::InMemory;
+    use crate::player::PLAYER_COLLIDER;
+    use crate::settings::PhysicsSettings;
+    use crate::world::block_types::get_block_types;
+    use crate::world::chunk::Chunk;
+    use crate::world::coordinates::ChunkPosition;
+    use crate::world::block_inner::{STONE, DIRT, AIR};
+    use crate::world::weather::Weather;
+
+    /// an app with a player and an empty world, but without rendering
+    fn headless_app() -> (App, RigidBodyHandle) {
+        let mut builder = App::build();
+        let (mut physics, _, _) = PhysicsEngine::new(PhysicsSettings::default());
+        let handle = physics.create_rigid_body(Vec3::new(0.5, 10.0 + EYE_HEIGHT, 0.5), 1.0, false, PLAYER_COLLIDER);
+        builder.add_resource(physics)
+            .add_resource(get_block_types())
+            .add_resource(WorldTime::default())
+            .add_resource(WeatherState::default())
+            .add_resource(ChunkManager::new(Box::new(InMemory::new()), ChunkPosition::new(0, 0, 0), 2.0, 4.0, PathBuf::new()));
+        let mut app = builder.app;
+        app.world.spawn((PlayerMovement::new(), handle, GameMode::Survival));
+        (app, handle)
+    }
+
+    fn execute(app: &mut App, line: &str) -> Result<String> {
+        CommandRegistry::with_builtin_commands().execute(line, &mut app.world, &mut app.resources)
+    }
+
+    fn feet(app: &App, handle: RigidBodyHandle) -> Vec3 {
+        app.resources.get::<PhysicsEngine>().unwrap().get(handle).unwrap().position() - Vec3::unit_y() * EYE_HEIGHT
+    }
+
+    #[test]
+    fn teleport_absolute_and_relative() {
+        let (mut app, handle) = headless_app();
+        execute(&mut app, "/tp 3 20 -4").unwrap();
+        assert_eq!(feet(&app, handle), Vec3::new(3.0, 20.0, -4.0));
+
+        execute(&mut app, "tp ~ ~2 ~-1.5").unwrap();
+        assert_eq!(feet(&app, handle), Vec3::new(3.0, 22.0, -5.5));
+
+        assert!(execute(&mut app, "/tp 1 2").is_err());
+        assert!(execute(&mut app, "/tp a b c").is_err());
+    }
+
+    #[test]
+    fn seed_and_time() {
+        let (mut app, _) = headless_app();
+        let seed = app.resources.get::<ChunkManager>().unwrap().provider().seed();
+        assert_eq!(execute(&mut app, "/seed").unwrap(), format!("seed: {}", seed));
+
+        execute(&mut app, "/time set night").unwrap();
+        assert_eq!(app.resources.get::<WorldTime>().unwrap().time_of_day(), 13000);
+        execute(&mut app, "/time set 500").unwrap();
+        assert_eq!(app.resources.get::<WorldTime>().unwrap().time_of_day(), 500);
+        assert!(execute(&mut app, "/time set never").is_err());
+    }
+
+    #[test]
+    fn weather_is_set_and_saved() {
+        let (mut app, _) = headless_app();
+        assert_eq!(execute(&mut app, "/weather").unwrap(), "weather: Clear");
+        execute(&mut app, "/weather thunder").unwrap();
+        assert_eq!(app.resources.get::<WeatherState>().unwrap().weather(), Weather::Thunderstorm);
+        let saved = app.resources.get_mut::<ChunkManager>().unwrap().provider_mut().load_weather();
+        assert_eq!(saved.map(|state|state.weather()), Some(Weather::Thunderstorm));
+        assert!(execute(&mut app, "/weather sunny").is_err());
+    }
+
+    #[test]
+    fn game_mode_changes_the_player() {
+        let (mut app, _) = headless_app();
+        execute(&mut app, "/gamemode creative").unwrap();
+        let player = player(&app.world).unwrap();
+        assert_eq!(*app.world.get::<GameMode>(player).unwrap(), GameMode::Creative);
+        assert!(execute(&mut app, "/gamemode spectator").is_err());
+    }
+
+    #[test]
+    fn blocks_need_loaded_chunks_and_known_names() {
+        let (mut app, _) = headless_app();
+        assert!(execute(&mut app, "/setblock 0 0 0 stone").unwrap_err().to_string().contains("isnt loaded"));
+        assert!(execute(&mut app, "/setblock 0 0 0 cheese").unwrap_err().to_string().contains("unknown block"));
+        assert!(execute(&mut app, "/fill 0 0 0 100 100 100 stone").unwrap_err().to_string().contains("too many"));
+        assert_eq!(execute(&mut app, "/fill 0 0 0 1 1 1 dirt").unwrap(), "filled 0 of 8 blocks with dirt");
+    }
+
+    #[test]
+    fn fill_rejects_overflowing_volumes() {
+        let (mut app, _) = headless_app();
+        let error = execute(&mut app, "/fill -9000000000000000000 0 0 9000000000000000000 0 0 stone").unwrap_err();
+        assert!(error.to_string().contains("too many"));
+        let error = execute(&mut app, "/fill 0 0 0 3000000 3000000 3000000 stone").unwrap_err();
+        assert!(error.to_string().contains("too many"));
+    }
+
+    fn block_at(app: &mut App, position: BlockPosition) -> BlockInner {
+        let (local, chunk) = position.local();
+        let entity = {
+            let mut chunk_manager = app.resources.get_mut::<ChunkManager>().unwrap();
+            if chunk_manager.is_loaded(chunk) {
+                None
+            } else {
+                Some(chunk_manager.load_chunk_in_world(&mut app.world, chunk))
+            }
+        };
+        let entity = entity.unwrap_or_else(||app.world.query_filtered::<Entity, With<Chunk>>()
+            .find(|entity|app.world.get::<Chunk>(*entity).unwrap().position == chunk)
+            .unwrap());
+        app.world.get::<Chunk>(entity).unwrap().data[local]
+    }
+
+    #[test]
+    fn blocks_are_placed_in_loaded_chunks() {
+        let (mut app, _) = headless_app();
+        //loads the chunk before the command changes it
+        block_at(&mut app, BlockPosition::new(0, 20, 0));
+        execute(&mut app, "/setblock 1 20 2 stone").unwrap();
+        assert_eq!(block_at(&mut app, BlockPosition::new(1, 20, 2)), STONE);
+
+        assert_eq!(execute(&mut app, "/fill 2 20 2 3 21 3 dirt").unwrap(), "filled 8 of 8 blocks with dirt");
+        for x in 2..=3 {
+            for y in 20..=21 {
+                for z in 2..=3 {
+                    assert_eq!(block_at(&mut app, BlockPosition::new(x, y, z)), DIRT);
+                }
+            }
+        }
+        //the fill stays in its box
+        assert_eq!(block_at(&mut app, BlockPosition::new(4, 20, 2)), AIR);
+        assert_eq!(block_at(&mut app, BlockPosition::new(1, 20, 2)), STONE);
+    }
+
+    #[test]
+    fn unknown_commands_fail() {
+        let (mut app, _) = headless_app();
+        assert!(execute(&mut app, "/dance").is_err());
+        assert!(execute(&mut app, "/").is_err());
+        assert!(execute(&mut app, "/help").unwrap().contains("/tp"));
+    }
+}
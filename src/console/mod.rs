@@ -0,0 +1,215 @@
+use bevy::prelude::*;
+use bevy::window::ReceivedCharacter;
+use std::collections::VecDeque;
+use crate::settings::Settings;
+use crate::state::{AppState, APP_STATE_STAGE, GAME_UPDATE, GAME_POST_UPDATE};
+use crate::input::actions::{Actions, OPEN_CHAT, OPEN_COMMAND};
+use crate::menu::{MenuAssets, text};
+use crate::world::chunk::ChunkManager;
+use crate::console::commands::CommandRegistry;
+
+pub mod commands;
+
+/// the number of lines shown above the input
+const VISIBLE_LINES: usize = 10;
+/// the number of lines kept in the console
+const MAX_LINES: usize = 100;
+/// new messages are shown this long (in seconds) while the console is closed
+const MESSAGE_TIME: f64 = 10.0;
+const CONSOLE_FONT_SIZE: f32 = 16.0;
+/// the name of the local player in chat messages
+const PLAYER_NAME: &str = "Player";
+
+struct ConsoleLine {
+    text: String,
+    /// the time since startup when the line was printed
+    time: f64,
+}
+
+/// The chat messages and command results.
+/// While the console is open the actions are blocked, so typing doesnt move the player.
+#[derive(Default)]
+pub struct Console {
+    open: bool,
+    input: String,
+    lines: VecDeque<ConsoleLine>,
+    /// the entered lines, which werent executed yet
+    submitted: Vec<String>,
+}
+
+impl Console {
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+    /// adds the message, every line of it is a line of the console
+    pub fn print(&mut self, message: &str, time: f64) {
+        for line in message.lines() {
+            self.lines.push_back(ConsoleLine {
+                text: line.to_string(),
+                time,
+            });
+        }
+        while self.lines.len() > MAX_LINES {
+            self.lines.pop_front();
+        }
+    }
+    /// the lines to show, the open console also shows older lines
+    fn visible_lines(&self, now: f64) -> Vec<&str> {
+        let mut lines: Vec<&str> = self.lines.iter()
+            .rev()
+            .take(VISIBLE_LINES)
+            .filter(|line|self.open || now - line.time < MESSAGE_TIME)
+            .map(|line|line.text.as_str())
+            .collect();
+        lines.reverse();
+        lines
+    }
+}
+
+/// A line of the console, the last line shows the input
+struct ConsoleText(usize);
+
+pub fn init_console(builder: &mut AppBuilder, _settings: &Settings) {
+    builder.add_resource(Console::default())
+        .add_resource(CommandRegistry::with_builtin_commands())
+        .on_state_enter(APP_STATE_STAGE, AppState::Loading, reset_console.system())
+        .add_system_to_stage(GAME_UPDATE, console_input.system())
+        .add_system_to_stage(GAME_UPDATE, run_console.system())
+        .add_system_to_stage(GAME_POST_UPDATE, update_console_text.system());
+}
+
+/// the console lines, spawned with the HUD
+pub fn spawn_console(parent: &mut ChildBuilder, assets: &MenuAssets) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                //the children of a column are placed bottom up
+                flex_direction: FlexDirection::ColumnReverse,
+                align_self: AlignSelf::FlexStart,
+                ..Default::default()
+            },
+            material: assets.transparent.clone(),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            for line in 0..=VISIBLE_LINES {
+                parent.spawn(text(assets, "", CONSOLE_FONT_SIZE))
+                    .with(ConsoleText(line));
+            }
+        });
+}
+
+/// every world starts with an empty console
+fn reset_console(mut console: ResMut<Console>, mut actions: ResMut<Actions>) {
+    *console = Console::default();
+    actions.set_blocked(false);
+}
+
+/// opens the console and handles the typing, enter submits the line and escape closes the console
+fn console_input(
+    mut console: ResMut<Console>,
+    mut actions: ResMut<Actions>,
+    keys: Res<Input<KeyCode>>,
+    characters: Res<Events<ReceivedCharacter>>,
+    mut reader: Local<EventReader<ReceivedCharacter>>,
+) {
+    //the characters are read while closed too, so the key opening the console isnt typed
+    let typed: Vec<char> = reader.iter(&characters).map(|event|event.char).collect();
+    if !console.open {
+        if actions.just_pressed(OPEN_CHAT) {
+            console.open = true;
+        } else if actions.just_pressed(OPEN_COMMAND) {
+            console.open = true;
+            console.input.push('/');
+        }
+        actions.set_blocked(console.open);
+        return;
+    }
+
+    console.input.extend(typed.into_iter().filter(|character|!character.is_control()));
+    if keys.just_pressed(KeyCode::Back) {
+        console.input.pop();
+    }
+    if keys.just_pressed(KeyCode::Return) {
+        let line = std::mem::take(&mut console.input);
+        if !line.trim().is_empty() {
+            console.submitted.push(line);
+        }
+        console.open = false;
+    }
+    if keys.just_pressed(KeyCode::Escape) {
+        console.input.clear();
+        console.open = false;
+    }
+    actions.set_blocked(console.open);
+}
+
+/// executes the submitted commands, sends chat messages over the provider and prints received messages
+fn run_console(world: &mut World, resources: &mut Resources) {
+    let submitted = std::mem::take(&mut resources.get_mut::<Console>().unwrap().submitted);
+    let mut output = Vec::new();
+    if let Some(mut chunk_manager) = resources.get_mut::<ChunkManager>() {
+        while let Some(message) = chunk_manager.provider_mut().get_chat_message() {
+            output.push(message);
+        }
+    }
+    if !submitted.is_empty() {
+        let registry = resources.get::<CommandRegistry>().unwrap().clone();
+        for line in submitted {
+            if line.starts_with('/') {
+                output.push(line.clone());
+                match registry.execute(&line, world, resources) {
+                    Ok(message) => output.push(message),
+                    Err(error) => output.push(error.to_string()),
+                }
+            } else {
+                //singleplayer providers have no one to send the message to, so it is only shown
+                if let Some(mut chunk_manager) = resources.get_mut::<ChunkManager>() {
+                    chunk_manager.provider_mut().send_chat(&line);
+                }
+                output.push(format!("<{}> {}", PLAYER_NAME, line));
+            }
+        }
+    }
+    if output.is_empty() {
+        return;
+    }
+    let time = resources.get::<Time>().unwrap().seconds_since_startup();
+    let mut console = resources.get_mut::<Console>().unwrap();
+    for message in output {
+        console.print(&message, time);
+    }
+}
+
+fn update_console_text(time: Res<Time>, console: Res<Console>, mut texts: Query<(&ConsoleText, &mut Text)>) {
+    let lines = console.visible_lines(time.seconds_since_startup());
+    //the lines are aligned to the input line at the bottom
+    let offset = VISIBLE_LINES - lines.len();
+    for (ConsoleText(line), mut text) in texts.iter_mut() {
+        let value = if *line == VISIBLE_LINES {
+            if console.open { format!("> {}_", console.input) } else { String::new() }
+        } else if *line >= offset {
+            lines[*line - offset].to_string()
+        } else {
+            String::new()
+        };
+        if text.value != value {
+            text.value = value;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn closed_console_only_shows_new_lines() {
+        let mut console = Console::default();
+        console.print("old", 0.0);
+        console.print("new\nmessage", 20.0);
+        assert_eq!(console.visible_lines(25.0), vec!["new", "message"]);
+        console.open = true;
+        assert_eq!(console.visible_lines(25.0), vec!["old", "new", "message"]);
+    }
+}
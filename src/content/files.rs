@@ -0,0 +1,382 @@
+use crate::content::provider::{Provider, ChunkUpdate};
+use crate::content::world_generation::generate_chunk;
+use crate::world::chunk::ChunkData;
+use crate::world::coordinates::{ChunkPosition, BlockVector};
+use crate::world::block_inner::BlockInner;
+use crate::player::inventory::Inventory;
+use crate::entities::entity::SavedEntity;
+use crate::world::weather::WeatherState;
+use serde::{Serialize, Deserialize};
+use serde::de::DeserializeOwned;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::io::{BufReader, BufWriter};
+use std::fs::{self, File};
+use anyhow::{Result, bail};
+
+const WORLD_FILE: &str = "world.json";
+const INVENTORY_FILE: &str = "inventory.json";
+const TIME_FILE: &str = "time.json";
+const WEATHER_FILE: &str = "weather.json";
+const CHUNK_FOLDER: &str = "chunks";
+const ENTITY_FOLDER: &str = "entities";
+
+/// The description of a saved world
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct WorldInfo {
+    pub name: String,
+    pub seed: u64,
+}
+
+/// A world in the save folder.
+/// The folder is named after the world when it is created, but it can be renamed outside the game.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SavedWorld {
+    /// the name of the folder of the world in the save folder
+    pub folder: String,
+    pub info: WorldInfo,
+}
+
+/// names are used as folder names, so only a safe set of characters is allowed
+pub fn is_valid_world_name(name: &str) -> bool {
+    !name.trim().is_empty() &&
+        name.len() <= 32 &&
+        name.chars().all(|c|c.is_ascii_alphanumeric() || c == ' ' || c == '-' || c == '_')
+}
+
+fn world_folder(save_folder: &Path, name: &str) -> PathBuf {
+    save_folder.join(name)
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    Ok(serde_json::from_reader(BufReader::new(File::open(path)?))?)
+}
+
+fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    serde_json::to_writer(BufWriter::new(File::create(path)?), value)?;
+    Ok(())
+}
+
+fn chunk_file_name(position: ChunkPosition) -> String {
+    format!("{}_{}_{}.json", position.x, position.y, position.z)
+}
+
+/// the worlds in the save folder sorted by name, folders without a valid world file are skipped
+pub fn list_worlds(save_folder: &Path) -> Vec<SavedWorld> {
+    let mut worlds: Vec<SavedWorld> = match fs::read_dir(save_folder) {
+        Ok(entries) => entries
+            .filter_map(|entry|entry.ok())
+            .filter_map(|entry|Some(SavedWorld {
+                folder: entry.file_name().into_string().ok()?,
+                info: read_json(&entry.path().join(WORLD_FILE)).ok()?,
+            }))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    worlds.sort_by(|a, b|a.info.name.cmp(&b.info.name));
+    worlds
+}
+
+/// renames the world in the folder, the folder is renamed to the new name
+pub fn rename_world(save_folder: &Path, folder: &str, new_name: &str) -> Result<()> {
+    if !is_valid_world_name(new_name) {
+        bail!("invalid world name: {}", new_name);
+    }
+    let new_folder = world_folder(save_folder, new_name);
+    if new_folder.exists() {
+        bail!("a world named {} already exists", new_name);
+    }
+    let mut info: WorldInfo = read_json(&world_folder(save_folder, folder).join(WORLD_FILE))?;
+    fs::rename(world_folder(save_folder, folder), &new_folder)?;
+    info.name = new_name.to_string();
+    write_json(&new_folder.join(WORLD_FILE), &info)
+}
+
+pub fn delete_world(save_folder: &Path, folder: &str) -> Result<()> {
+    Ok(fs::remove_dir_all(world_folder(save_folder, folder))?)
+}
+
+/// Files is a singleplayer provider saving the world in a folder.
+///
+/// Chunks are generated from the seed, only the changed blocks are saved.
+/// The changes are written when the chunk is unloaded or the world is flushed, not on every block update.
+pub struct Files {
+    folder: PathBuf,
+    info: WorldInfo,
+    /// the changed blocks of the chunks read since they were loaded
+    changes: HashMap<ChunkPosition, HashMap<BlockVector, BlockInner>>,
+    /// the chunks with changes that arent written yet
+    unsaved: HashSet<ChunkPosition>,
+}
+
+impl Files {
+    pub fn create(save_folder: &Path, info: WorldInfo) -> Result<Self> {
+        if !is_valid_world_name(&info.name) {
+            bail!("invalid world name: {}", info.name);
+        }
+        let folder = world_folder(save_folder, &info.name);
+        if folder.exists() {
+            bail!("a world named {} already exists", info.name);
+        }
+        write_json(&folder.join(WORLD_FILE), &info)?;
+        Ok(Files {
+            folder,
+            info,
+            changes: HashMap::new(),
+            unsaved: HashSet::new(),
+        })
+    }
+    /// opens the world in the folder of the save folder, see `SavedWorld::folder`
+    pub fn open(save_folder: &Path, folder: &str) -> Result<Self> {
+        let folder = world_folder(save_folder, folder);
+        let info = read_json(&folder.join(WORLD_FILE))?;
+        Ok(Files {
+            folder,
+            info,
+            changes: HashMap::new(),
+            unsaved: HashSet::new(),
+        })
+    }
+    pub fn info(&self) -> &WorldInfo {
+        &self.info
+    }
+    fn chunk_path(&self, position: ChunkPosition) -> PathBuf {
+        self.folder.join(CHUNK_FOLDER).join(chunk_file_name(position))
+    }
+    fn entity_path(&self, position: ChunkPosition) -> PathBuf {
+        self.folder.join(ENTITY_FOLDER).join(chunk_file_name(position))
+    }
+    fn changes(&mut self, position: ChunkPosition) -> &mut HashMap<BlockVector, BlockInner> {
+        let path = self.chunk_path(position);
+        self.changes.entry(position).or_insert_with(||{
+            read_json::<Vec<([i64; 3], BlockInner)>>(&path)
+                .map(|changes|changes.into_iter()
+                    .map(|([x, y, z], block)|(BlockVector::new(x, y, z), block))
+                    .collect())
+                .unwrap_or_default()
+        })
+    }
+    fn write_changes(&mut self, position: ChunkPosition) {
+        if !self.unsaved.remove(&position) {
+            return;
+        }
+        let changes = match self.changes.get(&position) {
+            Some(changes) => changes.iter()
+                .map(|(local, block)|([local.x, local.y, local.z], *block))
+                .collect::<Vec<_>>(),
+            None => return,
+        };
+        if let Err(error) = write_json(&self.chunk_path(position), &changes) {
+            eprintln!("couldnt save chunk {}: {}", position, error);
+        }
+    }
+}
+
+impl Provider for Files {
+    fn load_chunk(&mut self, position: ChunkPosition) -> ChunkData {
+        let mut chunk = generate_chunk(self.info.seed, position);
+        for (block_position, block) in self.changes(position).iter() {
+            chunk[*block_position] = *block;
+        }
+        chunk
+    }
+
+    fn get_chunk_update(&mut self) -> Option<ChunkUpdate> {
+        //No one else can update the world!
+        None
+    }
+
+    fn apply_chunk_update(&mut self, update: ChunkUpdate) {
+        let position = update.position();
+        match update {
+            ChunkUpdate::BlockUpdate(block_position, block) => {
+                self.changes(position).insert(block_position - position, block);
+            }
+        }
+        self.unsaved.insert(position);
+    }
+
+    fn unload_chunk(&mut self, position: ChunkPosition) {
+        self.write_changes(position);
+        self.changes.remove(&position);
+    }
+
+    fn flush(&mut self) {
+        for position in self.unsaved.clone() {
+            self.write_changes(position);
+        }
+    }
+
+    fn seed(&self) -> u64 {
+        self.info.seed
+    }
+
+    fn load_inventory(&mut self) -> Option<Inventory> {
+        read_json(&self.folder.join(INVENTORY_FILE)).ok().map(Inventory::validated)
+    }
+
+    fn save_inventory(&mut self, inventory: &Inventory) {
+        if let Err(error) = write_json(&self.folder.join(INVENTORY_FILE), inventory) {
+            eprintln!("couldnt save the inventory: {}", error);
+        }
+    }
+
+    fn load_entities(&mut self, position: ChunkPosition) -> Option<Vec<SavedEntity>> {
+        let path = self.entity_path(position);
+        let entities = read_json(&path).ok();
+        //the entities are owned by the loaded chunk now
+        let _ = fs::remove_file(path);
+        entities
+    }
+
+    fn save_entities(&mut self, position: ChunkPosition, entities: Vec<SavedEntity>) {
+        if let Err(error) = write_json(&self.entity_path(position), &entities) {
+            eprintln!("couldnt save the entities of chunk {}: {}", position, error);
+        }
+    }
+
+    fn load_time(&mut self) -> Option<u64> {
+        read_json(&self.folder.join(TIME_FILE)).ok()
+    }
+
+    fn save_time(&mut self, ticks: u64) {
+        if let Err(error) = write_json(&self.folder.join(TIME_FILE), &ticks) {
+            eprintln!("couldnt save the time: {}", error);
+        }
+    }
+
+    fn load_weather(&mut self) -> Option<WeatherState> {
+        read_json(&self.folder.join(WEATHER_FILE)).ok()
+    }
+
+    fn save_weather(&mut self, weather: &WeatherState) {
+        if let Err(error) = write_json(&self.folder.join(WEATHER_FILE), weather) {
+            eprintln!("couldnt save the weather: {}", error);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::block_inner::WOOD;
+
+    /// an empty save folder in the temp directory, removed again when the test is done
+    struct TempFolder(PathBuf);
+
+    impl TempFolder {
+        fn new(name: &str) -> Self {
+            let folder = std::env::temp_dir().join(format!("mine_clone_{}_{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&folder);
+            fs::create_dir_all(&folder).unwrap();
+            TempFolder(folder)
+        }
+    }
+
+    impl Drop for TempFolder {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn create_world(save_folder: &Path, name: &str, seed: u64) {
+        Files::create(save_folder, WorldInfo { name: name.to_string(), seed }).unwrap();
+    }
+
+    fn saved(folder: &str, name: &str, seed: u64) -> SavedWorld {
+        SavedWorld {
+            folder: folder.to_string(),
+            info: WorldInfo { name: name.to_string(), seed },
+        }
+    }
+
+    #[test]
+    fn valid_world_names() {
+        assert!(is_valid_world_name("My World_1"));
+        assert!(is_valid_world_name("a-b"));
+        assert!(!is_valid_world_name(""));
+        assert!(!is_valid_world_name("   "));
+        assert!(!is_valid_world_name("a/b"));
+        assert!(!is_valid_world_name(".."));
+        assert!(!is_valid_world_name(&"a".repeat(33)));
+        assert!(is_valid_world_name(&"a".repeat(32)));
+    }
+
+    #[test]
+    fn worlds_are_listed_by_name() {
+        let saves = TempFolder::new("list");
+        assert!(list_worlds(&saves.0).is_empty());
+        create_world(&saves.0, "b", 2);
+        create_world(&saves.0, "a", 1);
+        //folders without a world file arent worlds
+        fs::create_dir_all(saves.0.join("c")).unwrap();
+        assert_eq!(list_worlds(&saves.0), vec![saved("a", "a", 1), saved("b", "b", 2)]);
+        assert!(Files::create(&saves.0, WorldInfo { name: "a".to_string(), seed: 3 }).is_err());
+    }
+
+    #[test]
+    fn rename_world_keeps_the_seed() {
+        let saves = TempFolder::new("rename");
+        create_world(&saves.0, "a", 1);
+        create_world(&saves.0, "b", 2);
+        assert!(rename_world(&saves.0, "a", "b").is_err());
+        assert!(rename_world(&saves.0, "a", "../c").is_err());
+        assert!(rename_world(&saves.0, "missing", "d").is_err());
+        rename_world(&saves.0, "a", "c").unwrap();
+        assert_eq!(list_worlds(&saves.0), vec![saved("b", "b", 2), saved("c", "c", 1)]);
+        assert_eq!(Files::open(&saves.0, "c").unwrap().seed(), 1);
+    }
+
+    #[test]
+    fn changes_are_written_on_unload_and_flush() {
+        let saves = TempFolder::new("changes");
+        create_world(&saves.0, "a", 1);
+        let mut files = Files::open(&saves.0, "a").unwrap();
+        let (first, second) = (ChunkPosition::new(0, 0, 0), ChunkPosition::new(1, 0, 0));
+        let block = WOOD;
+        files.load_chunk(first);
+        files.load_chunk(second);
+        files.apply_chunk_update(ChunkUpdate::BlockUpdate(first.lowest(), block));
+        files.apply_chunk_update(ChunkUpdate::BlockUpdate(second.lowest(), block));
+        assert!(!files.chunk_path(first).exists());
+
+        files.unload_chunk(first);
+        assert!(files.chunk_path(first).exists());
+        assert!(!files.changes.contains_key(&first));
+        assert!(!files.chunk_path(second).exists());
+        files.flush();
+        assert!(files.chunk_path(second).exists());
+
+        let mut reopened = Files::open(&saves.0, "a").unwrap();
+        assert_eq!(reopened.load_chunk(first)[BlockVector::new(0, 0, 0)], block);
+        assert_eq!(reopened.load_chunk(second)[BlockVector::new(0, 0, 0)], block);
+    }
+
+    #[test]
+    fn delete_world_removes_the_folder() {
+        let saves = TempFolder::new("delete");
+        create_world(&saves.0, "a", 1);
+        create_world(&saves.0, "b", 2);
+        delete_world(&saves.0, "a").unwrap();
+        assert!(!saves.0.join("a").exists());
+        assert_eq!(list_worlds(&saves.0), vec![saved("b", "b", 2)]);
+        assert!(delete_world(&saves.0, "a").is_err());
+    }
+
+    #[test]
+    fn worlds_are_found_by_their_folder() {
+        let saves = TempFolder::new("folders");
+        create_world(&saves.0, "a", 1);
+        fs::rename(saves.0.join("a"), saves.0.join("moved")).unwrap();
+        assert_eq!(list_worlds(&saves.0), vec![saved("moved", "a", 1)]);
+        assert_eq!(Files::open(&saves.0, "moved").unwrap().seed(), 1);
+
+        rename_world(&saves.0, "moved", "b").unwrap();
+        assert_eq!(list_worlds(&saves.0), vec![saved("b", "b", 1)]);
+        delete_world(&saves.0, "b").unwrap();
+        assert!(list_worlds(&saves.0).is_empty());
+    }
+}
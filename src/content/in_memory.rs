@@ -3,28 +3,44 @@ use crate::world::chunk::ChunkData;
 use crate::world::coordinates::ChunkPosition;
 use crate::content::world_generation::generate_chunk;
 use rand::RngCore;
+use crate::player::inventory::Inventory;
+use crate::entities::entity::SavedEntity;
+use crate::world::weather::WeatherState;
+use std::collections::HashMap;
 
 /// In memory is a singleplayer provider which doesnt save anything!
 pub struct InMemory {
     seed: u64,
+    inventory: Option<Inventory>,
+    entities: HashMap<ChunkPosition, Vec<SavedEntity>>,
+    time: Option<u64>,
+    weather: Option<WeatherState>,
 }
 
 impl InMemory {
     pub fn new() -> Self {
         InMemory {
-            seed: rand::thread_rng().next_u64()
+            seed: rand::thread_rng().next_u64(),
+            inventory: None,
+            entities: HashMap::new(),
+            time: None,
+            weather: None,
         }
     }
     pub fn with_seed(seed: u64) -> Self {
         InMemory {
             seed,
+            inventory: None,
+            entities: HashMap::new(),
+            time: None,
+            weather: None,
         }
     }
 }
 
 impl Provider for InMemory {
     fn load_chunk(&mut self, position: ChunkPosition) -> ChunkData {
-        generate_chunk(position)
+        generate_chunk(self.seed, position)
     }
 
     fn get_chunk_update(&mut self) -> Option<ChunkUpdate> {
@@ -36,4 +52,44 @@ impl Provider for InMemory {
         println!("send update!");
         //We dont safe anything: do nothing
     }
+
+    fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    fn load_inventory(&mut self) -> Option<Inventory> {
+        self.inventory.clone()
+    }
+
+    fn save_inventory(&mut self, inventory: &Inventory) {
+        //Only kept as long as the game runs
+        self.inventory = Some(inventory.clone());
+    }
+
+    fn load_entities(&mut self, position: ChunkPosition) -> Option<Vec<SavedEntity>> {
+        self.entities.remove(&position)
+    }
+
+    fn save_entities(&mut self, position: ChunkPosition, entities: Vec<SavedEntity>) {
+        //Only kept as long as the game runs
+        self.entities.insert(position, entities);
+    }
+
+    fn load_time(&mut self) -> Option<u64> {
+        self.time
+    }
+
+    fn save_time(&mut self, ticks: u64) {
+        //Only kept as long as the game runs
+        self.time = Some(ticks);
+    }
+
+    fn load_weather(&mut self) -> Option<WeatherState> {
+        self.weather.clone()
+    }
+
+    fn save_weather(&mut self, weather: &WeatherState) {
+        //Only kept as long as the game runs
+        self.weather = Some(weather.clone());
+    }
 }
\ No newline at end of file
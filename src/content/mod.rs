@@ -1,6 +1,6 @@
 mod network;
-mod world_generation;
-mod files;
+pub mod world_generation;
+pub mod files;
 
 pub mod provider;
 pub mod in_memory;
\ No newline at end of file
@@ -1,6 +1,9 @@
 use crate::world::coordinates::{ChunkPosition, BlockPosition};
 use crate::world::chunk::ChunkData;
 use crate::world::block_inner::BlockInner;
+use crate::player::inventory::Inventory;
+use crate::entities::entity::SavedEntity;
+use crate::world::weather::WeatherState;
 
 pub enum ChunkUpdate {
     BlockUpdate(BlockPosition, BlockInner),
@@ -22,4 +25,40 @@ pub trait Provider {
     fn load_chunk(&mut self, position: ChunkPosition) -> ChunkData;
     fn get_chunk_update(&mut self) -> Option<ChunkUpdate>;
     fn apply_chunk_update(&mut self, update: ChunkUpdate);
+    /// called when the chunk is unloaded, its pending changes can be saved and dropped
+    fn unload_chunk(&mut self, _position: ChunkPosition) {}
+    /// saves the pending changes of all chunks
+    fn flush(&mut self) {}
+    /// the seed of the world generation
+    fn seed(&self) -> u64;
+    /// the inventory of the local player, None if the player didnt join this world yet
+    fn load_inventory(&mut self) -> Option<Inventory>;
+    fn save_inventory(&mut self, inventory: &Inventory);
+    /// takes the entities stored with the chunk, None if the chunk wasnt populated yet
+    fn load_entities(&mut self, position: ChunkPosition) -> Option<Vec<SavedEntity>>;
+    /// stores the entities of an unloaded chunk, an empty list marks the chunk as populated
+    fn save_entities(&mut self, position: ChunkPosition, entities: Vec<SavedEntity>);
+    /// the ticks since the world was created, None for new worlds
+    fn load_time(&mut self) -> Option<u64>;
+    fn save_time(&mut self, ticks: u64);
+    /// the weather of the world, None for new worlds
+    fn load_weather(&mut self) -> Option<WeatherState>;
+    /// called when the weather changes, a server sends it to the clients
+    fn save_weather(&mut self, weather: &WeatherState);
+    /// false for the clients of a server, they only apply the weather updates
+    fn owns_weather(&self) -> bool {
+        true
+    }
+    /// a weather change of the server
+    fn get_weather_update(&mut self) -> Option<WeatherState> {
+        None
+    }
+    /// sends a chat message to the other players, returns false if there are none
+    fn send_chat(&mut self, _message: &str) -> bool {
+        false
+    }
+    /// a chat message of another player
+    fn get_chat_message(&mut self) -> Option<String> {
+        None
+    }
 }
\ No newline at end of file
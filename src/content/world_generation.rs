@@ -1,30 +1,88 @@
-use crate::world::coordinates::{ChunkPosition, CHUNK_SIZE};
+use crate::world::coordinates::{ChunkPosition, BlockPosition, CHUNK_SIZE};
 use crate::world::chunk::ChunkData;
-use crate::world::block_inner::{AIR, GRASS, DIRT, STONE};
-use utils::{create_perlin_noise, write_perlin_noise};
+use crate::world::block_inner::{BlockInner, AIR, GRASS, DIRT, STONE, BEDROCK};
+use utils::{create_perlin_noise, write_perlin_noise, MapData};
 
-pub fn generate_chunk(position: ChunkPosition) -> ChunkData {
+/// mixed into the world seed, so temperature and height dont follow the same noise
+const TEMPERATURE_SEED: u64 = 8472519376451829;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Biome {
+    Snowy,
+    Plains,
+    Desert,
+}
+
+/// columns below this temperature are snowy
+pub const SNOW_TEMPERATURE: f32 = 0.3;
+/// columns at or above this temperature are deserts
+pub const DESERT_TEMPERATURE: f32 = 0.7;
+
+/// the temperatures of the columns of the chunk (indexed by x and z) between 0 (cold) and 1 (hot)
+pub fn temperature_map(seed: u64, chunk: ChunkPosition) -> MapData {
+    create_perlin_noise(seed ^ TEMPERATURE_SEED, chunk.x * CHUNK_SIZE, chunk.z * CHUNK_SIZE, 128, 0.0..=1.0)
+}
+
+/// the temperature of the column between 0 (cold) and 1 (hot)
+pub fn temperature(seed: u64, x: i64, z: i64) -> f32 {
+    let chunk = BlockPosition::new(x, 0, z).chunk();
+    let local = BlockPosition::new(x, 0, z).chunk_relative();
+    temperature_map(seed, chunk)[local.x as usize][local.z as usize]
+}
+
+pub fn biome(seed: u64, x: i64, z: i64) -> Biome {
+    let temperature = temperature(seed, x, z);
+    if temperature < SNOW_TEMPERATURE {
+        Biome::Snowy
+    } else if temperature < DESERT_TEMPERATURE {
+        Biome::Plains
+    } else {
+        Biome::Desert
+    }
+}
+
+/// the terrain height noise of the chunk column (indexed by x and z), blocks at most half a block above it are solid
+pub fn height_map(seed: u64, chunk: ChunkPosition) -> MapData {
+    let mut noise = create_perlin_noise(seed, chunk.x * CHUNK_SIZE, chunk.z * CHUNK_SIZE, 64, 0.0..=8.0);
+    write_perlin_noise(&mut noise, seed, chunk.x * CHUNK_SIZE, chunk.z * CHUNK_SIZE, 16, 0.0..=2.0);
+    write_perlin_noise(&mut noise, seed, chunk.x * CHUNK_SIZE, chunk.z * CHUNK_SIZE, 4, 0.0..=0.8);
+    write_perlin_noise(&mut noise, seed, chunk.x * CHUNK_SIZE, chunk.z * CHUNK_SIZE, 2, 0.0..=0.2);
+    noise
+}
+
+/// the solid block at the height (relative to the chunk) of the terrain
+fn ground_block(y: i64) -> BlockInner {
+    if y == 15 {
+        GRASS
+    } else if y > 9 {
+        DIRT
+    } else {
+        STONE
+    }
+}
+
+/// the height (relative to the chunk) and the block of the top of a generated column
+pub fn surface_block(noise: f32) -> (i64, BlockInner) {
+    let height = ((noise + 0.5).floor() as i64).max(0).min(CHUNK_SIZE - 1);
+    (height, if height == 0 { BEDROCK } else { ground_block(height) })
+}
+
+pub fn generate_chunk(seed: u64, position: ChunkPosition) -> ChunkData {
     if position.y != 0 {
         ChunkData::filled(AIR)
     } else {
 
-        let mut noise = create_perlin_noise(264958643553465476, position.x * CHUNK_SIZE, position.z * CHUNK_SIZE, 64, 0.0..=8.0);
-        write_perlin_noise(&mut noise, 264958643553465476, position.x * CHUNK_SIZE, position.z * CHUNK_SIZE, 16, 0.0..=2.0);
-        write_perlin_noise(&mut noise, 264958643553465476, position.x * CHUNK_SIZE, position.z * CHUNK_SIZE, 4, 0.0..=0.8);
-        write_perlin_noise(&mut noise, 264958643553465476, position.x * CHUNK_SIZE, position.z * CHUNK_SIZE, 2, 0.0..=0.2);
+        let noise = height_map(seed, position);
 
         let mut chunk = ChunkData::filled(AIR);
         for (position , block) in chunk.iter_mut() {
-            if noise[position.x as usize][position.z as usize] >= position.y as f32 - 0.5 {
-                *block = if position.y == 15 {
-                    GRASS
-                } else if position.y > 9 {
-                    DIRT
-                } else {
-                    STONE
-                };
+            if position.y == 0 {
+                //the world has no blocks below this layer
+                *block = BEDROCK;
+            } else if noise[position.x as usize][position.z as usize] >= position.y as f32 - 0.5 {
+                *block = ground_block(position.y);
             }
         }
         chunk
     }
-}
\ No newline at end of file
+}
@@ -6,6 +6,12 @@ use crate::player::init_player;
 use crate::world::init_world;
 use crate::renderer::init_rendering;
 use crate::physics::init_physics;
+use crate::entities::init_entities;
+use crate::input::init_input;
+use crate::state::init_state;
+use crate::menu::init_menu;
+use crate::hud::init_hud;
+use crate::console::init_console;
 
 pub fn load_engine(settings: Settings) -> App {
 
@@ -13,12 +19,25 @@ pub fn load_engine(settings: Settings) -> App {
     //MSaa hast to be the first resource
     builder.add_resource(Msaa { samples: settings.render_settings.msaa_samples });
     builder.add_plugins(DefaultPlugins);
+    builder.add_resource(settings.clone());
 
+    //the stages of the app states are used by the other systems
+    init_state(&mut builder);
     init_physics(&mut builder, &settings);
+    //Add input actions
+    init_input(&mut builder, &settings);
     //Add Player
     init_player(&mut builder, &settings);
     //Add World
     init_world(&mut builder, &settings);
+    //Add moving entities
+    init_entities(&mut builder, &settings);
+    //Add main menu and world selection
+    init_menu(&mut builder, &settings);
+    //Add HUD and debug overlay
+    init_hud(&mut builder, &settings);
+    //Add chat and commands
+    init_console(&mut builder, &settings);
     //Add rendering Systems
     init_rendering(&mut builder, &settings);
 
@@ -1,45 +1,68 @@
-use bevy::math::Vec3;
-use crate::physics::collider::AAQuader;
+use bevy::prelude::*;
+use crate::physics::rigid_body::{PhysicsEngine, RigidBodyHandle};
+use crate::entities::registry::{EntityTypes, EntityTypeId};
+use crate::player::inventory::ItemStack;
+use serde::{Serialize, Deserialize};
 
 /// An movable object which interacts with others and the static chunks
 ///
+/// The movement is simulated by the rigid body of the object.
+#[derive(Copy, Clone, Debug)]
 pub struct Object {
-    velocity: Vec3,
-    next_acceleration: Vec3,
-    collider: AAQuader,
-    flying: bool,
+    kind: EntityTypeId,
+    body: RigidBodyHandle,
 }
 
 impl Object {
-    pub fn new(collider: AAQuader, is_flying: bool) -> Self {
-        Object {
-            velocity: Vec3::zero(),
-            next_acceleration: Vec3::zero(),
-            collider,
-            flying: is_flying,
-        }
+    pub fn kind(&self) -> EntityTypeId {
+        self.kind
     }
-    pub fn apply_force(&mut self, force: Vec3) {
-        self.next_acceleration += force;
+    pub fn body(&self) -> RigidBodyHandle {
+        self.body
     }
-    pub fn set_flying(&mut self, flying: bool) {
-        self.flying = flying;
-    }
-    pub fn is_flying(&self) -> bool {
-        self.flying
-    }
-    pub fn velocity(&self) -> Vec3 {
-        self.velocity
-    }
-    pub(crate) fn calc_velocity(&mut self) {
-        let friction = 0.9;
-        self.velocity = self.velocity * friction + self.next_acceleration;
-        if !self.flying {
-            self.velocity.y -= 5.0;
-        }
-        self.next_acceleration = Vec3::zero();
-    }
-    pub fn get_collider(&self) -> AAQuader {
-        self.collider
-    }
-}
\ No newline at end of file
+}
+
+/// An entity stored with its chunk while the chunk is unloaded
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SavedEntity {
+    /// the name of the entity type
+    pub kind: String,
+    pub position: [f32; 3],
+    /// the stack of a dropped item, see `item::ITEM_KIND`
+    #[serde(default)]
+    pub item: Option<ItemStack>,
+}
+
+/// spawns an entity of the given type with its rigid body and model
+pub fn spawn_entity(
+    commands: &mut Commands,
+    physics: &mut PhysicsEngine,
+    types: &EntityTypes,
+    kind: EntityTypeId,
+    position: Vec3,
+) -> Entity {
+    let entity_type = types.get(kind);
+    let body = physics.create_rigid_body(position, entity_type.inv_mass, entity_type.flying, entity_type.collider);
+
+    commands
+        .spawn(PbrBundle {
+            mesh: entity_type.model.mesh.clone(),
+            material: entity_type.model.material.clone(),
+            transform: Transform::from_translation(position),
+            ..Default::default()
+        })
+        .with(Object {
+            kind,
+            body,
+        })
+        .with(body);
+    (entity_type.insert)(commands);
+
+    commands.current_entity().unwrap()
+}
+
+/// removes the entity together with its rigid body
+pub fn despawn_entity(commands: &mut Commands, physics: &mut PhysicsEngine, entity: Entity, object: &Object) {
+    physics.remove_rigid_body(object.body);
+    commands.despawn(entity);
+}
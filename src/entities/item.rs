@@ -0,0 +1,231 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+use crate::physics::rigid_body::{PhysicsEngine, PhysicsEvent, RigidBodyHandle};
+use crate::physics::collider::AAQuader;
+use crate::player::inventory::{Inventory, ItemStack, MAX_STACK_SIZE};
+use crate::world::block_types::{StaticBlocksRes, BlockLook};
+use crate::world::block_inner::Sides;
+use crate::world::chunk::ChunkManager;
+use crate::world::chunk_mesh::{BevyChunkMeshBuilder, ChunkMeshBuilder, ChunkMesh, add_cube};
+
+/// the edge length of a dropped item
+const ITEM_SIZE: f32 = 0.25;
+/// items are picked up, if they are this close to the collider of a player
+const PICKUP_RANGE: f32 = 1.0;
+/// freshly dropped items cant be picked up, so they are visible for a moment
+const PICKUP_DELAY: f32 = 0.5;
+/// identical stacks closer than this merge into one
+const MERGE_RANGE: f32 = 1.0;
+/// items older than this (in seconds) are removed
+const DESPAWN_TIME: f32 = 300.0;
+/// in radians per second
+const ROTATION_SPEED: f32 = 1.5;
+/// the atlas index of the texture of items without a cube look
+const FALLBACK_TEXTURE: u32 = 0;
+/// the kind of the saved entities, which are dropped items
+pub const ITEM_KIND: &str = "item";
+
+/// A stack lying in the world
+pub struct DroppedItem {
+    stack: ItemStack,
+    /// the time since the item was dropped in seconds
+    age: f32,
+    body: RigidBodyHandle,
+}
+
+impl DroppedItem {
+    pub fn stack(&self) -> ItemStack {
+        self.stack
+    }
+    pub fn body(&self) -> RigidBodyHandle {
+        self.body
+    }
+}
+
+/// Requests a dropped item at the position
+#[derive(Copy, Clone, Debug)]
+pub struct DropItem {
+    pub stack: ItemStack,
+    pub position: Vec3,
+    pub velocity: Vec3,
+}
+
+/// one small cube mesh per block type, created when the first item of the type is dropped
+#[derive(Default)]
+pub struct ItemMeshes(HashMap<u16, Handle<Mesh>>);
+
+impl ItemMeshes {
+    fn get(&mut self, btype: u16, static_blocks: &StaticBlocksRes, meshes: &mut Assets<Mesh>) -> Option<Handle<Mesh>> {
+        if let Some(mesh) = self.0.get(&btype) {
+            return Some(mesh.clone());
+        }
+        let textures = match &static_blocks.get(btype as usize)?.0 {
+            BlockLook::Cube { textures } => *textures,
+            BlockLook::Layers { texture } => Sides::filled(*texture),
+            //blocks without a cube look still drop something visible
+            _ => Sides::filled(FALLBACK_TEXTURE),
+        };
+        let mut builder = BevyChunkMeshBuilder::empty();
+        add_cube(&mut builder, &textures, Vec3::new(-0.5, 0.0, -0.5));
+        let mesh = meshes.add(Mesh::from_builder(builder)?);
+        self.0.insert(btype, mesh.clone());
+        Some(mesh)
+    }
+}
+
+pub fn despawn_item(commands: &mut Commands, physics: &mut PhysicsEngine, entity: Entity, item: &DroppedItem) {
+    physics.remove_rigid_body(item.body);
+    commands.despawn(entity);
+}
+
+pub fn spawn_dropped_items(
+    commands: &mut Commands,
+    mut reader: Local<EventReader<DropItem>>,
+    events: Res<Events<DropItem>>,
+    mut physics: ResMut<PhysicsEngine>,
+    mut item_meshes: ResMut<ItemMeshes>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    static_blocks: Res<StaticBlocksRes>,
+    chunk_manager: Res<ChunkManager>,
+) {
+    let material = match chunk_manager.texture_atlas() {
+        Some(material) => material,
+        None => return,
+    };
+    for drop in reader.iter(&events) {
+        let mesh = match item_meshes.get(drop.stack.block.btype, &static_blocks, &mut meshes) {
+            Some(mesh) => mesh,
+            None => continue,
+        };
+        let half = ITEM_SIZE / 2.0;
+        let body = physics.create_rigid_body(
+            drop.position,
+            1.0,
+            false,
+            AAQuader::new(Vec3::new(-half, 0.0, -half), Vec3::new(half, ITEM_SIZE, half)),
+        );
+        if let Some(rigid_body) = physics.get_mut(body) {
+            //items dont push the players and mobs walking over them
+            rigid_body.set_ghost(true);
+            rigid_body.apply_impulse(drop.velocity);
+        }
+
+        let mut transform = Transform::from_translation(drop.position);
+        transform.scale = Vec3::splat(ITEM_SIZE);
+        commands
+            .spawn(PbrBundle {
+                mesh,
+                material: material.clone(),
+                transform,
+                ..Default::default()
+            })
+            .with(DroppedItem {
+                stack: drop.stack,
+                age: 0.0,
+                body,
+            })
+            .with(body);
+    }
+}
+
+/// rotates the items and removes old items and items which fell out of the world
+pub fn item_lifetime(
+    commands: &mut Commands,
+    time: Res<Time>,
+    mut reader: Local<EventReader<PhysicsEvent>>,
+    events: Res<Events<PhysicsEvent>>,
+    mut physics: ResMut<PhysicsEngine>,
+    mut items: Query<(Entity, &mut DroppedItem, &mut Transform)>,
+) {
+    let fallen: Vec<RigidBodyHandle> = reader.iter(&events)
+        .filter_map(|event|match event {
+            PhysicsEvent::FellOutOfWorld { body } => Some(*body),
+            _ => None,
+        })
+        .collect();
+
+    for (entity, mut item, mut transform) in items.iter_mut() {
+        item.age += time.delta_seconds();
+        transform.rotation = Quat::from_rotation_y(item.age * ROTATION_SPEED);
+        if item.age > DESPAWN_TIME || fallen.contains(&item.body) {
+            despawn_item(commands, &mut physics, entity, &item);
+        }
+    }
+}
+
+/// merges identical stacks lying next to each other into the older stack
+pub fn merge_items(
+    commands: &mut Commands,
+    mut physics: ResMut<PhysicsEngine>,
+    mut items: Query<(Entity, &mut DroppedItem, &Transform)>,
+) {
+    //merged stacks are empty until they are despawned
+    let mut lying: Vec<(Entity, ItemStack, f32, Vec3)> = items.iter_mut()
+        .filter(|(_, item, _)|item.stack.count > 0)
+        .map(|(entity, item, transform)|(entity, item.stack, item.age, transform.translation))
+        .collect();
+    //the oldest first, so they absorb the younger stacks
+    lying.sort_by(|a, b|b.2.partial_cmp(&a.2).unwrap());
+    let mut merged = Vec::new();
+
+    for (index, (entity, stack, _, position)) in lying.iter().enumerate() {
+        if merged.contains(entity) {
+            continue;
+        }
+        let mut count = stack.count;
+        for (other, other_stack, _, other_position) in lying[index + 1..].iter() {
+            if merged.contains(other) ||
+                !stack.stacks_with(other_stack) ||
+                count as u32 + other_stack.count as u32 > MAX_STACK_SIZE as u32 ||
+                position.distance(*other_position) > MERGE_RANGE {
+                continue;
+            }
+            count += other_stack.count;
+            merged.push(*other);
+        }
+        if count != stack.count {
+            if let Ok(mut item) = items.get_component_mut::<DroppedItem>(*entity) {
+                item.stack.count = count;
+            }
+        }
+    }
+
+    for entity in merged {
+        if let Ok(mut item) = items.get_component_mut::<DroppedItem>(entity) {
+            //the item is despawned at the end of the stage, it cant be picked up until then
+            item.stack.count = 0;
+            despawn_item(commands, &mut physics, entity, &item);
+        }
+    }
+}
+
+/// moves items close to a player into its inventory, what doesnt fit stays on the ground
+pub fn pickup_items(
+    commands: &mut Commands,
+    mut physics: ResMut<PhysicsEngine>,
+    mut players: Query<(&RigidBodyHandle, &mut Inventory)>,
+    mut items: Query<(Entity, &mut DroppedItem, &Transform)>,
+) {
+    for (handle, mut inventory) in players.iter_mut() {
+        let collider = match physics.get(*handle) {
+            Some(body) => body.world_collider(),
+            None => continue,
+        };
+        for (entity, mut item, transform) in items.iter_mut() {
+            if item.age < PICKUP_DELAY || item.stack.count == 0 {
+                continue;
+            }
+            let closest = transform.translation.max(collider.lower()).min(collider.higher());
+            if closest.distance(transform.translation) > PICKUP_RANGE {
+                continue;
+            }
+            match inventory.add(item.stack) {
+                Some(rest) => item.stack = rest,
+                None => {
+                    item.stack.count = 0;
+                    despawn_item(commands, &mut physics, entity, &item);
+                }
+            }
+        }
+    }
+}
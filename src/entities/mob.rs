@@ -0,0 +1,164 @@
+use bevy::prelude::*;
+use std::collections::VecDeque;
+use rand::Rng;
+use crate::entities::entity::Object;
+use crate::entities::pathfinding::{Pathfinder, receive_paths};
+use crate::physics::rigid_body::{PhysicsEngine, PhysicsCommands, PhysicsCommand};
+use crate::player::player::PlayerMovement;
+use crate::player::EYE_HEIGHT;
+use crate::world::coordinates::BlockPosition;
+
+/// hostile mobs follow players closer than this
+const FOLLOW_RANGE: f32 = 16.0;
+/// passive mobs flee from players closer than this
+const FLEE_RANGE: f32 = 6.0;
+/// the farthest a wandering or fleeing mob walks
+const WANDER_RANGE: i64 = 8;
+/// the time between two decisions
+const THINK_INTERVAL: f32 = 1.5;
+const WANDER_CHANCE: f64 = 0.4;
+/// a waypoint is reached, if the mob is this close to its center
+const WAYPOINT_DISTANCE: f32 = 0.3;
+/// the upwards velocity of a jump, enough for one block
+const JUMP_SPEED: f32 = 9.0;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Temperament {
+    Hostile,
+    Passive,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Behaviour {
+    Idle,
+    Wander,
+    Follow,
+    Flee,
+}
+
+/// A walking entity controlled by the ai
+pub struct Mob {
+    temperament: Temperament,
+    behaviour: Behaviour,
+    /// the acceleration while walking
+    speed: f32,
+    /// the height of the mob in blocks
+    height: i64,
+    path: VecDeque<BlockPosition>,
+    think_timer: f32,
+}
+
+impl Mob {
+    pub fn new(temperament: Temperament, speed: f32, height: i64) -> Self {
+        Mob {
+            temperament,
+            behaviour: Behaviour::Idle,
+            speed,
+            height,
+            path: VecDeque::new(),
+            think_timer: 0.0,
+        }
+    }
+    pub fn behaviour(&self) -> Behaviour {
+        self.behaviour
+    }
+    pub fn temperament(&self) -> Temperament {
+        self.temperament
+    }
+    fn decide(&self, player_distance: Option<f32>) -> Behaviour {
+        match (self.temperament, player_distance) {
+            (Temperament::Hostile, Some(distance)) if distance < FOLLOW_RANGE => Behaviour::Follow,
+            (Temperament::Passive, Some(distance)) if distance < FLEE_RANGE => Behaviour::Flee,
+            _ => if rand::thread_rng().gen_bool(WANDER_CHANCE) {
+                Behaviour::Wander
+            } else {
+                Behaviour::Idle
+            }
+        }
+    }
+}
+
+/// picks a behaviour for every mob and requests the path for it
+pub fn mob_behaviour(
+    time: Res<Time>,
+    mut pathfinder: ResMut<Pathfinder>,
+    players: Query<(&Transform, &PlayerMovement)>,
+    mut mobs: Query<(Entity, &Transform, &mut Mob)>,
+) {
+    let mut rng = rand::thread_rng();
+    for (entity, transform, mut mob) in mobs.iter_mut() {
+        mob.think_timer -= time.delta_seconds();
+        if mob.think_timer > 0.0 {
+            continue;
+        }
+        mob.think_timer = THINK_INTERVAL;
+
+        let position = transform.translation;
+        let player = players.iter()
+            .map(|(transform, _)|transform.translation - Vec3::new(0.0, EYE_HEIGHT, 0.0))
+            .min_by(|a, b|{
+                a.distance_squared(position).partial_cmp(&b.distance_squared(position)).unwrap()
+            });
+
+        mob.behaviour = mob.decide(player.map(|player|player.distance(position)));
+
+        let start = BlockPosition::from_vector(position);
+        let goal = match (mob.behaviour, player) {
+            (Behaviour::Follow, Some(player)) => BlockPosition::from_vector(player),
+            (Behaviour::Flee, Some(player)) => {
+                let away = Vec3::new(position.x - player.x, 0.0, position.z - player.z);
+                let away = if away.length() > 0.0 { away.normalize() } else { Vec3::unit_x() };
+                BlockPosition::from_vector(position + away * WANDER_RANGE as f32)
+            }
+            (Behaviour::Wander, _) => start
+                .with_x(rng.gen_range(-WANDER_RANGE..=WANDER_RANGE))
+                .with_z(rng.gen_range(-WANDER_RANGE..=WANDER_RANGE)),
+            _ => {
+                mob.path.clear();
+                continue;
+            }
+        };
+        pathfinder.request(entity, start, goal, mob.height);
+    }
+}
+
+pub fn apply_paths(mut pathfinder: ResMut<Pathfinder>, mut mobs: Query<&mut Mob>) {
+    for (entity, path) in receive_paths(&mut pathfinder) {
+        if let Ok(mut mob) = mobs.get_mut(entity) {
+            mob.path = path.unwrap_or_default().into();
+        }
+    }
+}
+
+/// walks the mobs along their paths
+pub fn mob_movement(
+    mut physics: ResMut<PhysicsEngine>,
+    physics_commands: Res<PhysicsCommands>,
+    mut mobs: Query<(&Object, &Transform, &mut Mob)>,
+) {
+    for (object, transform, mut mob) in mobs.iter_mut() {
+        let position = transform.translation;
+        let waypoint = match mob.path.front() {
+            Some(waypoint) => *waypoint,
+            None => continue,
+        };
+        let target = waypoint.lower_corner() + Vec3::new(0.5, 0.0, 0.5);
+        let difference = target - position;
+        let horizontal = Vec3::new(difference.x, 0.0, difference.z);
+
+        if horizontal.length() < WAYPOINT_DISTANCE && difference.y.abs() < 1.0 {
+            mob.path.pop_front();
+            continue;
+        }
+
+        let speed = mob.speed;
+        if let Some(body) = physics.get_mut(object.body()) {
+            if horizontal.length() > 0.0 {
+                body.add_force(horizontal.normalize() * speed);
+            }
+            if difference.y > 0.5 && body.is_grounded() {
+                physics_commands.send(PhysicsCommand::ApplyImpulse(object.body(), Vec3::new(0.0, JUMP_SPEED, 0.0)));
+            }
+        }
+    }
+}
@@ -1,2 +1,81 @@
-mod entity;
-mod systems;
\ No newline at end of file
+use bevy::prelude::*;
+use crate::settings::Settings;
+use crate::physics::collider::AAQuader;
+use crate::entities::registry::{EntityTypes, EntityType, load_entity_models};
+use crate::entities::systems::{Slime, slime_update, despawn_fallen_entities};
+use crate::entities::pathfinding::{Pathfinder, invalidate_path_cache, dispatch_path_requests};
+use crate::entities::mob::{Mob, Temperament, mob_behaviour, apply_paths, mob_movement};
+use crate::entities::spawning::{SpawnRule, Spawner, chunk_entities, natural_spawning};
+use crate::entities::item::{DropItem, ItemMeshes, spawn_dropped_items, item_lifetime, merge_items, pickup_items};
+use crate::content::world_generation::Biome;
+use crate::world::block_inner::{GRASS, DIRT, STONE};
+use crate::state::GAME_UPDATE;
+
+pub mod entity;
+pub mod registry;
+pub mod systems;
+pub mod pathfinding;
+pub mod mob;
+pub mod spawning;
+pub mod item;
+
+fn insert_zombie(commands: &mut Commands) {
+    commands.with(Mob::new(Temperament::Hostile, 40.0, 2));
+}
+
+fn insert_pig(commands: &mut Commands) {
+    commands.with(Mob::new(Temperament::Passive, 30.0, 1));
+}
+
+pub fn init_entities(builder: &mut AppBuilder, _settings: &Settings) {
+    let mut types = EntityTypes::new();
+    types.register(EntityType::new(
+        "slime",
+        AAQuader::new(Vec3::new(-0.4, 0.0, -0.4), Vec3::new(0.4, 0.8, 0.4)),
+        1.0,
+        Color::rgb(0.3, 0.8, 0.3),
+        Slime::insert,
+    ));
+    types.register(EntityType::new(
+        "zombie",
+        AAQuader::new(Vec3::new(-0.3, 0.0, -0.3), Vec3::new(0.3, 1.8, 0.3)),
+        1.0,
+        Color::rgb(0.2, 0.5, 0.4),
+        insert_zombie,
+    ).spawns(SpawnRule {
+        biomes: &[Biome::Snowy, Biome::Plains, Biome::Desert],
+        light: 0..=7,
+        surfaces: &[GRASS, DIRT, STONE],
+    }));
+    types.register(EntityType::new(
+        "pig",
+        AAQuader::new(Vec3::new(-0.45, 0.0, -0.45), Vec3::new(0.45, 0.9, 0.45)),
+        1.0,
+        Color::rgb(0.9, 0.6, 0.6),
+        insert_pig,
+    ).spawns(SpawnRule {
+        biomes: &[Biome::Plains],
+        light: 8..=15,
+        surfaces: &[GRASS],
+    }));
+
+    builder.add_resource(types)
+        .add_resource(Pathfinder::new())
+        .add_resource(Spawner::default())
+        .add_resource(ItemMeshes::default())
+        .add_event::<DropItem>()
+        .add_startup_system(load_entity_models.system())
+        .add_system_to_stage(GAME_UPDATE, slime_update.system())
+        .add_system_to_stage(GAME_UPDATE, despawn_fallen_entities.system())
+        .add_system_to_stage(GAME_UPDATE, invalidate_path_cache.system())
+        .add_system_to_stage(GAME_UPDATE, dispatch_path_requests.system())
+        .add_system_to_stage(GAME_UPDATE, mob_behaviour.system())
+        .add_system_to_stage(GAME_UPDATE, apply_paths.system())
+        .add_system_to_stage(GAME_UPDATE, mob_movement.system())
+        .add_system_to_stage(GAME_UPDATE, chunk_entities.system())
+        .add_system_to_stage(GAME_UPDATE, natural_spawning.system())
+        .add_system_to_stage(GAME_UPDATE, spawn_dropped_items.system())
+        .add_system_to_stage(GAME_UPDATE, item_lifetime.system())
+        .add_system_to_stage(GAME_UPDATE, merge_items.system())
+        .add_system_to_stage(GAME_UPDATE, pickup_items.system());
+}
@@ -0,0 +1,379 @@
+use bevy::prelude::*;
+use std::collections::{HashMap, BinaryHeap, VecDeque};
+use std::cmp::Reverse;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{channel, Sender, Receiver};
+use std::thread;
+use crate::world::chunk::{Chunk, ChunkData, ChunkManager, ChunkEvent};
+use crate::world::coordinates::{BlockPosition, ChunkPosition};
+use crate::world::block_types::{StaticBlocks, StaticBlocksRes};
+use crate::physics::collision::STEP_HEIGHT;
+
+/// the most nodes a single search expands, before it returns the best partial path
+pub const MAX_EXPANSIONS: usize = 2000;
+/// the deepest drop a path contains
+pub const MAX_DROP: i64 = 3;
+/// the most searches started per tick
+const MAX_REQUESTS_PER_TICK: usize = 4;
+
+const WALK_COST: u32 = 10;
+const JUMP_COST: u32 = 20;
+const DROP_COST: u32 = 5;
+
+static DIRECTIONS: [(i64, i64); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+/// The walkability of a block type
+#[derive(Copy, Clone, Debug)]
+pub struct BlockShape {
+    solid: bool,
+    /// the top of the highest collider
+    height: f32,
+}
+
+impl BlockShape {
+    pub fn of_blocks(blocks: &StaticBlocks) -> Vec<BlockShape> {
+        blocks.iter().map(|(_, _, personality)|{
+            let colliders = personality.get_collider(0);
+            BlockShape {
+                solid: !colliders.is_empty(),
+                height: colliders.iter().map(|collider|collider.higher().y).fold(0.0, f32::max),
+            }
+        }).collect()
+    }
+    /// bodies can stand inside low blocks, they are walked up like steps
+    fn blocks_body(&self) -> bool {
+        self.solid && self.height > STEP_HEIGHT
+    }
+}
+
+/// A copy of the chunks around a search, so it can run off the main thread
+pub struct WorldSnapshot {
+    chunks: HashMap<ChunkPosition, Arc<ChunkData>>,
+    shapes: Arc<Vec<BlockShape>>,
+}
+
+impl WorldSnapshot {
+    fn shape(&self, position: BlockPosition) -> Option<BlockShape> {
+        let (local, chunk) = position.local();
+        self.chunks.get(&chunk).map(|data|self.shapes[data[local].btype as usize])
+    }
+    /// unknown blocks are never passable
+    fn passable(&self, position: BlockPosition) -> bool {
+        self.shape(position).map_or(false, |shape|!shape.blocks_body())
+    }
+    fn clear(&self, position: BlockPosition, height: i64) -> bool {
+        (0..height).all(|offset|self.passable(position.with_y(offset)))
+    }
+    /// a body with the given height can stand with its feet in the block
+    pub fn standable(&self, position: BlockPosition, height: i64) -> bool {
+        self.clear(position, height) && (
+            self.shape(position).map_or(false, |shape|shape.solid) ||
+            self.shape(position.with_y(-1)).map_or(false, |shape|shape.solid)
+        )
+    }
+    fn neighbours(&self, position: BlockPosition, height: i64) -> Vec<(BlockPosition, u32)> {
+        let mut neighbours = Vec::new();
+        for (x, z) in DIRECTIONS.iter() {
+            let next = position.with_x(*x).with_z(*z);
+            if self.standable(next, height) {
+                neighbours.push((next, WALK_COST));
+            } else if self.standable(next.with_y(1), height) && self.clear(position.with_y(height), 1) {
+                neighbours.push((next.with_y(1), JUMP_COST));
+            } else if self.clear(next, height) {
+                for depth in 1..=MAX_DROP {
+                    let below = next.with_y(-depth);
+                    if self.standable(below, height) {
+                        neighbours.push((below, WALK_COST + DROP_COST * depth as u32));
+                        break;
+                    }
+                    if !self.passable(below) {
+                        break;
+                    }
+                }
+            }
+        }
+        neighbours
+    }
+}
+
+/// A lower bound of the path cost, so the search stays optimal.
+/// Every horizontal step costs at least `WALK_COST` and every vertical block at least
+/// the cheaper one of dropping and jumping on top of the walk.
+fn distance(a: BlockPosition, b: BlockPosition) -> u32 {
+    let horizontal = (a.x() - b.x()).abs() + (a.z() - b.z()).abs();
+    let vertical = (a.y() - b.y()).abs();
+    horizontal as u32 * WALK_COST + vertical as u32 * DROP_COST.min(JUMP_COST - WALK_COST)
+}
+
+/// A* search over the standable blocks. If the goal cant be reached within `max_expansions`,
+/// the path to the expanded block closest to the goal is returned.
+pub fn find_path(world: &WorldSnapshot, start: BlockPosition, goal: BlockPosition, height: i64, max_expansions: usize) -> Option<Vec<BlockPosition>> {
+    let mut open = BinaryHeap::new();
+    let mut came_from: HashMap<BlockPosition, BlockPosition> = HashMap::new();
+    let mut costs: HashMap<BlockPosition, u32> = HashMap::new();
+
+    open.push(Reverse((distance(start, goal), 0u32, start)));
+    costs.insert(start, 0);
+    let mut best = (distance(start, goal), start);
+    let mut expansions = 0;
+
+    while let Some(Reverse((_, cost, current))) = open.pop() {
+        if current == goal {
+            best = (0, goal);
+            break;
+        }
+        if costs.get(&current).map_or(false, |known|*known < cost) {
+            //outdated entry
+            continue;
+        }
+        expansions += 1;
+        if expansions > max_expansions {
+            break;
+        }
+        for (next, step_cost) in world.neighbours(current, height) {
+            let next_cost = cost + step_cost;
+            if costs.get(&next).map_or(true, |known|next_cost < *known) {
+                costs.insert(next, next_cost);
+                came_from.insert(next, current);
+                let remaining = distance(next, goal);
+                if remaining < best.0 {
+                    best = (remaining, next);
+                }
+                open.push(Reverse((next_cost + remaining, next_cost, next)));
+            }
+        }
+    }
+
+    if best.1 == start {
+        return None;
+    }
+    let mut path = vec![best.1];
+    while let Some(previous) = came_from.get(path.last().unwrap()) {
+        if *previous == start {
+            break;
+        }
+        path.push(*previous);
+    }
+    path.reverse();
+    Some(path)
+}
+
+struct PathRequest {
+    id: u64,
+    start: BlockPosition,
+    goal: BlockPosition,
+    height: i64,
+    world: WorldSnapshot,
+}
+
+struct PathResult {
+    id: u64,
+    path: Option<Vec<BlockPosition>>,
+}
+
+/// Runs the searches on a worker thread.
+/// Paths arent cached, only the chunk copies the searches run on are kept per chunk until the chunk changes.
+pub struct Pathfinder {
+    requests: Mutex<Sender<PathRequest>>,
+    results: Mutex<Receiver<PathResult>>,
+    queue: VecDeque<(Entity, BlockPosition, BlockPosition, i64)>,
+    pending: HashMap<u64, Entity>,
+    next_id: u64,
+    cache: HashMap<ChunkPosition, Arc<ChunkData>>,
+    shapes: Option<Arc<Vec<BlockShape>>>,
+}
+
+impl Pathfinder {
+    pub fn new() -> Self {
+        let (request_sender, request_receiver) = channel::<PathRequest>();
+        let (result_sender, result_receiver) = channel();
+
+        thread::Builder::new()
+            .name("pathfinding".to_string())
+            .spawn(move||{
+                for request in request_receiver.iter() {
+                    let path = find_path(&request.world, request.start, request.goal, request.height, MAX_EXPANSIONS);
+                    if result_sender.send(PathResult { id: request.id, path }).is_err() {
+                        break;
+                    }
+                }
+            })
+            .expect("couldnt start the pathfinding thread!");
+
+        Pathfinder {
+            requests: Mutex::new(request_sender),
+            results: Mutex::new(result_receiver),
+            queue: VecDeque::new(),
+            pending: HashMap::new(),
+            next_id: 0,
+            cache: HashMap::new(),
+            shapes: None,
+        }
+    }
+    /// forgets the queued searches and the cached chunks, when the world is closed
+    pub fn clear(&mut self) {
+        self.queue.clear();
+        self.pending.clear();
+        self.cache.clear();
+    }
+    /// queues a search, the result is handed to the `Mob` component of the entity
+    pub fn request(&mut self, entity: Entity, start: BlockPosition, goal: BlockPosition, height: i64) {
+        //only the newest request of an entity matters
+        self.queue.retain(|(queued, _, _, _)|*queued != entity);
+        self.queue.push_back((entity, start, goal, height));
+    }
+    fn snapshot(&mut self, start: BlockPosition, goal: BlockPosition, chunk_manager: &ChunkManager, chunks: &Query<(&Chunk,)>) -> WorldSnapshot {
+        let (a, b) = (start.chunk(), goal.chunk());
+        let mut snapshot = HashMap::new();
+        for x in a.x.min(b.x) - 1..=a.x.max(b.x) + 1 {
+            for y in a.y.min(b.y) - 1..=a.y.max(b.y) + 1 {
+                for z in a.z.min(b.z) - 1..=a.z.max(b.z) + 1 {
+                    let position = ChunkPosition::new(x, y, z);
+                    if !self.cache.contains_key(&position) {
+                        if let Some(data) = chunk_manager.chunk_data(position, chunks) {
+                            self.cache.insert(position, Arc::new(data.clone()));
+                        }
+                    }
+                    if let Some(data) = self.cache.get(&position) {
+                        snapshot.insert(position, data.clone());
+                    }
+                }
+            }
+        }
+        WorldSnapshot {
+            chunks: snapshot,
+            shapes: self.shapes.clone().unwrap(),
+        }
+    }
+}
+
+pub fn invalidate_path_cache(
+    mut pathfinder: ResMut<Pathfinder>,
+    mut reader: Local<EventReader<ChunkEvent>>,
+    events: Res<Events<ChunkEvent>>,
+) {
+    for event in reader.iter(&events) {
+        match event {
+            ChunkEvent::Loaded(_) => {}
+            ChunkEvent::Unloaded(position) => {
+                pathfinder.cache.remove(position);
+            }
+            ChunkEvent::BlockChanged(position, _) => {
+                pathfinder.cache.remove(&position.chunk());
+            }
+        }
+    }
+}
+
+/// starts at most `MAX_REQUESTS_PER_TICK` searches
+pub fn dispatch_path_requests(
+    mut pathfinder: ResMut<Pathfinder>,
+    chunk_manager: Res<ChunkManager>,
+    static_blocks: Res<StaticBlocksRes>,
+    chunks: Query<(&Chunk,)>,
+) {
+    if pathfinder.shapes.is_none() {
+        pathfinder.shapes = Some(Arc::new(BlockShape::of_blocks(&static_blocks)));
+    }
+    for _ in 0..MAX_REQUESTS_PER_TICK {
+        let (entity, start, goal, height) = match pathfinder.queue.pop_front() {
+            Some(request) => request,
+            None => break,
+        };
+        let world = pathfinder.snapshot(start, goal, &chunk_manager, &chunks);
+        let id = pathfinder.next_id;
+        pathfinder.next_id += 1;
+        pathfinder.pending.insert(id, entity);
+        let _ = pathfinder.requests.lock().unwrap().send(PathRequest {
+            id,
+            start,
+            goal,
+            height,
+            world,
+        });
+    }
+}
+
+/// the finished searches
+pub fn receive_paths(pathfinder: &mut Pathfinder) -> Vec<(Entity, Option<Vec<BlockPosition>>)> {
+    let results: Vec<PathResult> = pathfinder.results.lock().unwrap().try_iter().collect();
+    results.into_iter()
+        .filter_map(|result|Some((pathfinder.pending.remove(&result.id)?, result.path)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::block_inner::{AIR, STONE};
+
+    /// a single chunk with a stone floor at y = 0 and the given stone blocks
+    fn world(stone: &[(i64, i64, i64)]) -> WorldSnapshot {
+        let mut data = ChunkData::filled(AIR);
+        for (position, block) in data.iter_mut() {
+            if position.y == 0 {
+                *block = STONE;
+            }
+        }
+        for (x, y, z) in stone.iter() {
+            data[BlockPosition::new(*x, *y, *z).chunk_relative()] = STONE;
+        }
+        let mut chunks = HashMap::new();
+        chunks.insert(ChunkPosition::new(0, 0, 0), Arc::new(data));
+        WorldSnapshot {
+            chunks,
+            shapes: Arc::new(vec![
+                BlockShape { solid: false, height: 0.0 },
+                BlockShape { solid: true, height: 1.0 },
+            ]),
+        }
+    }
+
+    /// a wall of the given height across the chunk at x
+    fn wall(x: i64, height: i64) -> Vec<(i64, i64, i64)> {
+        (0..16).flat_map(|z|(1..=height).map(move|y|(x, y, z))).collect()
+    }
+
+    #[test]
+    fn walks_straight() {
+        let path = find_path(&world(&[]), BlockPosition::new(1, 1, 1), BlockPosition::new(5, 1, 1), 2, MAX_EXPANSIONS).unwrap();
+        let expected: Vec<BlockPosition> = (2..=5).map(|x|BlockPosition::new(x, 1, 1)).collect();
+        assert_eq!(path, expected);
+    }
+
+    #[test]
+    fn steps_up_ledges() {
+        let plateau: Vec<_> = (3..16).flat_map(|x|wall(x, 1)).collect();
+        let path = find_path(&world(&plateau), BlockPosition::new(1, 1, 1), BlockPosition::new(5, 2, 1), 2, MAX_EXPANSIONS).unwrap();
+        assert_eq!(path.len(), 4);
+        assert_eq!(path[1], BlockPosition::new(3, 2, 1));
+        assert_eq!(path.last(), Some(&BlockPosition::new(5, 2, 1)));
+    }
+
+    #[test]
+    fn drops_down_ledges() {
+        let plateau: Vec<_> = (3..16).flat_map(|x|wall(x, 1)).collect();
+        let path = find_path(&world(&plateau), BlockPosition::new(5, 2, 1), BlockPosition::new(1, 1, 1), 2, MAX_EXPANSIONS).unwrap();
+        assert_eq!(path.len(), 4);
+        assert_eq!(path[2], BlockPosition::new(2, 1, 1));
+        assert_eq!(path.last(), Some(&BlockPosition::new(1, 1, 1)));
+    }
+
+    #[test]
+    fn blocked_paths_end_next_to_the_wall() {
+        let path = find_path(&world(&wall(3, 3)), BlockPosition::new(1, 1, 1), BlockPosition::new(5, 1, 1), 2, MAX_EXPANSIONS).unwrap();
+        assert_eq!(path.last(), Some(&BlockPosition::new(2, 1, 1)));
+    }
+
+    #[test]
+    fn stops_after_max_expansions() {
+        let world = world(&[]);
+        let (start, goal) = (BlockPosition::new(1, 1, 1), BlockPosition::new(14, 1, 14));
+        assert_eq!(find_path(&world, start, goal, 2, 0), None);
+
+        let path = find_path(&world, start, goal, 2, 3).unwrap();
+        assert_ne!(path.last(), Some(&goal));
+        assert!(path.len() <= 3);
+        assert_eq!(find_path(&world, start, goal, 2, MAX_EXPANSIONS).unwrap().len(), 26);
+    }
+}
@@ -0,0 +1,101 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+use crate::physics::collider::AAQuader;
+use crate::entities::spawning::SpawnRule;
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct EntityTypeId(u16);
+
+/// The mesh of an entity type, created once the assets are available
+#[derive(Clone, Default)]
+pub struct EntityModel {
+    pub mesh: Handle<Mesh>,
+    pub material: Handle<StandardMaterial>,
+}
+
+/// The static description of an entity type
+pub struct EntityType {
+    pub name: &'static str,
+    pub collider: AAQuader,
+    pub inv_mass: f32,
+    pub flying: bool,
+    pub color: Color,
+    pub model: EntityModel,
+    /// adds the type specific components to the spawned entity
+    pub insert: fn(&mut Commands),
+    /// where the type spawns naturally, None if it doesnt
+    pub spawn_rule: Option<SpawnRule>,
+}
+
+impl EntityType {
+    pub fn new(name: &'static str, collider: AAQuader, inv_mass: f32, color: Color, insert: fn(&mut Commands)) -> Self {
+        EntityType {
+            name,
+            collider,
+            inv_mass,
+            flying: false,
+            color,
+            model: EntityModel::default(),
+            insert,
+            spawn_rule: None,
+        }
+    }
+    pub fn spawns(mut self, rule: SpawnRule) -> Self {
+        self.spawn_rule = Some(rule);
+        self
+    }
+}
+
+pub struct EntityTypes {
+    types: Vec<EntityType>,
+    names: HashMap<&'static str, EntityTypeId>,
+}
+
+impl EntityTypes {
+    pub fn new() -> Self {
+        EntityTypes {
+            types: Vec::new(),
+            names: HashMap::new(),
+        }
+    }
+    pub fn register(&mut self, entity_type: EntityType) -> EntityTypeId {
+        let id = EntityTypeId(self.types.len() as u16);
+        assert!(self.names.insert(entity_type.name, id).is_none(), "entity type {} registered twice!", entity_type.name);
+        self.types.push(entity_type);
+        id
+    }
+    pub fn get(&self, id: EntityTypeId) -> &EntityType {
+        &self.types[id.0 as usize]
+    }
+    pub fn by_name(&self, name: &str) -> Option<EntityTypeId> {
+        self.names.get(name).cloned()
+    }
+    pub fn iter(&self) -> impl Iterator<Item=(EntityTypeId, &EntityType)> {
+        self.types.iter().enumerate().map(|(index, entity_type)|(EntityTypeId(index as u16), entity_type))
+    }
+}
+
+/// creates the meshes of all registered types, a box filling the collider
+pub fn load_entity_models(
+    mut types: ResMut<EntityTypes>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for entity_type in types.types.iter_mut() {
+        let collider = entity_type.collider;
+        let center = collider.lower() + collider.size() / 2.0;
+        let size = collider.size() / 2.0;
+
+        entity_type.model = EntityModel {
+            mesh: meshes.add(Mesh::from(shape::Box {
+                min_x: center.x - size.x,
+                max_x: center.x + size.x,
+                min_y: center.y - size.y,
+                max_y: center.y + size.y,
+                min_z: center.z - size.z,
+                max_z: center.z + size.z,
+            })),
+            material: materials.add(entity_type.color.into()),
+        };
+    }
+}
@@ -0,0 +1,230 @@
+use bevy::prelude::*;
+use std::collections::{VecDeque, HashMap};
+use std::ops::RangeInclusive;
+use rand::Rng;
+use crate::entities::entity::{Object, SavedEntity, spawn_entity, despawn_entity};
+use crate::entities::registry::EntityTypes;
+use crate::entities::item::{DroppedItem, DropItem, ITEM_KIND, despawn_item};
+use crate::content::world_generation::{Biome, biome};
+use crate::physics::rigid_body::PhysicsEngine;
+use crate::world::block_inner::{BlockInner, AIR, BLOCK_MESH};
+use crate::world::chunk::{Chunk, ChunkManager, ChunkEvent};
+use crate::world::coordinates::{BlockPosition, BlockVector, ChunkPosition, MAX_CHILD};
+use crate::world::time::WorldTime;
+use crate::world::heightmap::Heightmap;
+use crate::world::sky::sky_light;
+
+/// no natural spawning happens, while this many objects and dropped items are loaded
+const MAX_OBJECTS: usize = 32;
+/// the number of columns tested per newly loaded chunk
+const SPAWN_ATTEMPTS: usize = 2;
+const SPAWN_CHANCE: f64 = 0.25;
+/// the light of a block open to the sky at day
+pub const SKY_LIGHT: u8 = 15;
+
+/// The conditions for the natural spawning of an entity type
+#[derive(Clone, Debug)]
+pub struct SpawnRule {
+    pub biomes: &'static [Biome],
+    pub light: RangeInclusive<u8>,
+    /// the block the entity stands on
+    pub surfaces: &'static [BlockInner],
+}
+
+impl SpawnRule {
+    pub fn allows(&self, biome: Biome, light: u8, surface: BlockInner) -> bool {
+        self.biomes.contains(&biome) &&
+            self.light.contains(&light) &&
+            self.surfaces.iter().any(|block|block.btype == surface.btype)
+    }
+}
+
+/// The recently loaded chunks, which didnt get their natural spawns yet.
+/// Chunks are populated once, afterwards their entities are saved, even if there are none.
+#[derive(Default)]
+pub struct Spawner {
+    recent: VecDeque<ChunkPosition>,
+}
+
+impl Spawner {
+    pub fn clear(&mut self) {
+        self.recent.clear();
+    }
+}
+
+fn saved_entity(types: &EntityTypes, object: &Object, transform: &Transform) -> SavedEntity {
+    SavedEntity {
+        kind: types.get(object.kind()).name.to_string(),
+        position: transform.translation.into(),
+        item: None,
+    }
+}
+
+fn saved_item(item: &DroppedItem, transform: &Transform) -> SavedEntity {
+    SavedEntity {
+        kind: ITEM_KIND.to_string(),
+        position: transform.translation.into(),
+        item: Some(item.stack()),
+    }
+}
+
+/// saves the entities and dropped items of all loaded chunks, when the world is closed or autosaved.
+/// The entities stay loaded, the saved chunks count as populated, so the spawner has to be cleared.
+pub fn save_all_entities(
+    chunk_manager: &mut ChunkManager,
+    types: &EntityTypes,
+    objects: &Query<(Entity, &Object, &Transform)>,
+    items: &Query<(Entity, &DroppedItem, &Transform)>,
+) {
+    //the loaded chunks without entities are saved as populated too
+    let mut saved: HashMap<ChunkPosition, Vec<SavedEntity>> = chunk_manager.loaded_chunks()
+        .map(|position|(position, Vec::new()))
+        .collect();
+    for (_, object, transform) in objects.iter() {
+        saved.entry(BlockPosition::from_vector(transform.translation).chunk())
+            .or_default()
+            .push(saved_entity(types, object, transform));
+    }
+    //merged and picked up stacks are empty until they are despawned
+    for (_, item, transform) in items.iter().filter(|(_, item, _)|item.stack().count > 0) {
+        saved.entry(BlockPosition::from_vector(transform.translation).chunk())
+            .or_default()
+            .push(saved_item(item, transform));
+    }
+    for (position, entities) in saved {
+        chunk_manager.provider_mut().save_entities(position, entities);
+    }
+}
+
+/// the light of the block, it is the current sky light unless a loaded block above is solid.
+/// Only the blocks up to the highest block of the column are checked.
+pub fn light_level(position: BlockPosition, sky_light: u8, heightmap: &Heightmap, chunk_manager: &ChunkManager, chunks: &Query<(&Chunk,)>) -> u8 {
+    let top = match heightmap.height(position.x(), position.z()) {
+        Some(top) => top,
+        None => return sky_light,
+    };
+    let shaded = (position.y() + 1..=top)
+        .filter_map(|y|chunk_manager.get(BlockPosition::new(position.x(), y, position.z()), chunks))
+        .any(|block|block.info.contains(BLOCK_MESH));
+    if shaded { 0 } else { sky_light }
+}
+
+/// the highest block of the column in the chunk with two air blocks above and the block below it
+fn surface(chunk: ChunkPosition, x: i64, z: i64, chunk_manager: &ChunkManager, chunks: &Query<(&Chunk,)>) -> Option<(BlockPosition, BlockInner)> {
+    let data = chunk_manager.chunk_data(chunk, chunks)?;
+    (1..MAX_CHILD).rev()
+        .map(|y|BlockVector::new(x, y, z))
+        .find(|local|{
+            data[*local].btype == AIR.btype &&
+                data[local.with_y(1)].btype == AIR.btype &&
+                data[local.with_y(-1)].info.contains(BLOCK_MESH)
+        })
+        .map(|local|(chunk + local, data[local.with_y(-1)]))
+}
+
+/// restores the entities and dropped items of loaded chunks and stores the ones of unloaded chunks
+pub fn chunk_entities(
+    commands: &mut Commands,
+    mut reader: Local<EventReader<ChunkEvent>>,
+    events: Res<Events<ChunkEvent>>,
+    mut drops: ResMut<Events<DropItem>>,
+    mut spawner: ResMut<Spawner>,
+    mut chunk_manager: ResMut<ChunkManager>,
+    mut physics: ResMut<PhysicsEngine>,
+    types: Res<EntityTypes>,
+    objects: Query<(Entity, &Object, &Transform)>,
+    items: Query<(Entity, &DroppedItem, &Transform)>,
+) {
+    for event in reader.iter(&events) {
+        match event {
+            ChunkEvent::Loaded(position) => {
+                let saved = match chunk_manager.provider_mut().load_entities(*position) {
+                    Some(saved) => saved,
+                    None => {
+                        spawner.recent.push_back(*position);
+                        continue;
+                    }
+                };
+                for entity in saved {
+                    if let Some(stack) = entity.item {
+                        drops.send(DropItem { stack, position: entity.position.into(), velocity: Vec3::zero() });
+                    } else if let Some(kind) = types.by_name(&entity.kind) {
+                        spawn_entity(commands, &mut physics, &types, kind, entity.position.into());
+                    }
+                }
+            }
+            ChunkEvent::Unloaded(position) => {
+                let unpopulated = spawner.recent.contains(position);
+                spawner.recent.retain(|recent|recent != position);
+                let mut saved = Vec::new();
+                for (entity, object, transform) in objects.iter() {
+                    if BlockPosition::from_vector(transform.translation).chunk() == *position {
+                        saved.push(saved_entity(&types, object, transform));
+                        despawn_entity(commands, &mut physics, entity, object);
+                    }
+                }
+                for (entity, item, transform) in items.iter() {
+                    if item.stack().count > 0 && BlockPosition::from_vector(transform.translation).chunk() == *position {
+                        saved.push(saved_item(item, transform));
+                        despawn_item(commands, &mut physics, entity, item);
+                    }
+                }
+                //chunks unloaded before their natural spawns are populated when they load again
+                if !unpopulated || !saved.is_empty() {
+                    chunk_manager.provider_mut().save_entities(*position, saved);
+                }
+            }
+            ChunkEvent::BlockChanged(_, _) => {}
+        }
+    }
+}
+
+/// spawns entities in one recently loaded chunk per tick
+pub fn natural_spawning(
+    commands: &mut Commands,
+    mut spawner: ResMut<Spawner>,
+    mut physics: ResMut<PhysicsEngine>,
+    types: Res<EntityTypes>,
+    chunk_manager: Res<ChunkManager>,
+    world_time: Res<WorldTime>,
+    heightmap: Res<Heightmap>,
+    chunks: Query<(&Chunk,)>,
+    objects: Query<&Object>,
+    items: Query<&DroppedItem>,
+) {
+    if objects.iter().count() + items.iter().count() >= MAX_OBJECTS {
+        //the chunks stay empty, the entities are bounded around the player
+        spawner.recent.clear();
+        return;
+    }
+    let chunk = match spawner.recent.pop_front() {
+        Some(chunk) => chunk,
+        None => return,
+    };
+
+    let mut rng = rand::thread_rng();
+    for _ in 0..SPAWN_ATTEMPTS {
+        if !rng.gen_bool(SPAWN_CHANCE) {
+            continue;
+        }
+        let (x, z) = (rng.gen_range(0..=MAX_CHILD), rng.gen_range(0..=MAX_CHILD));
+        let (position, ground) = match surface(chunk, x, z, &chunk_manager, &chunks) {
+            Some(surface) => surface,
+            None => continue,
+        };
+        let biome = biome(chunk_manager.provider().seed(), position.x(), position.z());
+        let light = light_level(position, sky_light(world_time.time_of_day()), &heightmap, &chunk_manager, &chunks);
+
+        let candidates: Vec<_> = types.iter()
+            .filter(|(_, entity_type)|{
+                entity_type.spawn_rule.as_ref().map_or(false, |rule|rule.allows(biome, light, ground))
+            })
+            .map(|(kind, _)|kind)
+            .collect();
+        if candidates.is_empty() {
+            continue;
+        }
+        let kind = candidates[rng.gen_range(0..candidates.len())];
+        spawn_entity(commands, &mut physics, &types, kind, position.lower_corner() + Vec3::new(0.5, 0.0, 0.5));
+    }
+}
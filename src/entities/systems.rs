@@ -1,33 +1,53 @@
-use std::ops::Range;
+use bevy::prelude::*;
+use crate::physics::rigid_body::{PhysicsEngine, PhysicsEvent, PhysicsCommands, PhysicsCommand};
+use crate::entities::entity::{Object, despawn_entity};
+use rand::Rng;
 
-fn interval(low: f32, high: f32) -> Range<i64> {
-    (low.floor() as i64)..(high.ceil() as i64)
+/// Hops around randomly
+pub struct Slime {
+    cooldown: f32,
 }
 
-/*fn object_movement(
-    chunk_manager: Res<ChunkManager>,
-    mut objects: Query<(&mut Object, &mut Transform)>,
-    world: Query<(&Chunk)>,
-) {
-    for (mut object, mut transform) in objects.iter() {
-        let old_position = transform.translation;
-        object.calc_velocity();
-        let velocity = object.velocity();
-        let movement = velocity;
-        if velocity.y < 0.0 {
-            //Check down!
-            let collider = object.get_collider();
+impl Slime {
+    pub fn insert(commands: &mut Commands) {
+        commands.with(Slime { cooldown: 1.0 });
+    }
+}
 
-            for x in interval(collider.lower().x, collider.higher().x) {
-                for z in interval(collider.lower().z, collider.higher().z) {
+pub fn slime_update(
+    time: Res<Time>,
+    physics: Res<PhysicsEngine>,
+    physics_commands: Res<PhysicsCommands>,
+    mut slimes: Query<(&Object, &mut Slime)>,
+) {
+    let mut rng = rand::thread_rng();
+    for (object, mut slime) in slimes.iter_mut() {
+        slime.cooldown -= time.delta_seconds();
+        let grounded = physics.get(object.body()).map_or(false, |body|body.is_grounded());
+        if slime.cooldown <= 0.0 && grounded {
+            let direction = rng.gen_range(0.0..std::f32::consts::PI * 2.0);
+            physics_commands.send(PhysicsCommand::ApplyImpulse(
+                object.body(),
+                Vec3::new(direction.cos() * 4.0, 8.0, direction.sin() * 4.0),
+            ));
+            slime.cooldown = rng.gen_range(1.0..4.0);
+        }
+    }
+}
 
-                }
+/// entities falling out of the world are gone
+pub fn despawn_fallen_entities(
+    commands: &mut Commands,
+    mut reader: Local<EventReader<PhysicsEvent>>,
+    events: Res<Events<PhysicsEvent>>,
+    mut physics: ResMut<PhysicsEngine>,
+    objects: Query<(Entity, &Object)>,
+) {
+    for event in reader.iter(&events) {
+        if let PhysicsEvent::FellOutOfWorld { body } = event {
+            if let Some((entity, object)) = objects.iter().find(|(_, object)|object.body() == *body) {
+                despawn_entity(commands, &mut physics, entity, object);
             }
         }
-
-        transform.translation += movement;
-
-        //we assume, that the old position was valid, now we have to check the new position
-
     }
-}*/
\ No newline at end of file
+}
@@ -0,0 +1,115 @@
+use bevy::prelude::*;
+use crate::util::FPS;
+use crate::input::actions::{Actions, TOGGLE_DEBUG};
+use crate::menu::{MenuAssets, text};
+use crate::player::player::PlayerMovement;
+use crate::player::target::Targeting;
+use crate::world::chunk::{Chunk, ChunkManager, ChunkMeshStats};
+use crate::world::block_types::StaticBlocksRes;
+use crate::world::coordinates::BlockPosition;
+use crate::world::culling::CullingStats;
+
+const DEBUG_FONT_SIZE: f32 = 16.0;
+const DEBUG_LINES: usize = 9;
+
+/// A line of the debug overlay
+struct DebugLine(usize);
+
+/// The column of the debug lines, it is hidden by default
+struct DebugOverlay {
+    shown: bool,
+}
+
+/// the horizontal direction of the view, -z is north
+pub fn facing(direction: Vec3) -> &'static str {
+    if direction.x.abs() > direction.z.abs() {
+        if direction.x > 0.0 { "east" } else { "west" }
+    } else if direction.z > 0.0 {
+        "south"
+    } else {
+        "north"
+    }
+}
+
+pub fn spawn_debug_overlay(parent: &mut ChildBuilder, assets: &MenuAssets) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                //the children of a column are placed bottom up
+                flex_direction: FlexDirection::ColumnReverse,
+                align_self: AlignSelf::FlexStart,
+                margin: Rect::all(Val::Px(8.0)),
+                ..Default::default()
+            },
+            material: assets.transparent.clone(),
+            ..Default::default()
+        })
+        .with(DebugOverlay { shown: false })
+        .with_children(|parent| {
+            for line in 0..DEBUG_LINES {
+                parent.spawn(text(assets, "", DEBUG_FONT_SIZE))
+                    .with(DebugLine(line));
+            }
+        });
+}
+
+pub fn toggle_debug_overlay(actions: Res<Actions>, mut overlays: Query<&mut DebugOverlay>) {
+    if actions.just_pressed(TOGGLE_DEBUG) {
+        for mut overlay in overlays.iter_mut() {
+            overlay.shown = !overlay.shown;
+        }
+    }
+}
+
+/// the lines are empty while the overlay is hidden
+pub fn update_debug_overlay(
+    fps: Res<FPS>,
+    chunk_manager: Res<ChunkManager>,
+    culling: Res<CullingStats>,
+    mesh_stats: Res<ChunkMeshStats>,
+    static_blocks: Res<StaticBlocksRes>,
+    overlays: Query<&DebugOverlay>,
+    players: Query<(&Transform, &PlayerMovement, &Targeting)>,
+    chunks: Query<(&Chunk,)>,
+    mut lines: Query<(&DebugLine, &mut Text)>,
+) {
+    let shown = overlays.iter().any(|overlay|overlay.shown);
+    let mut values = vec![String::new(); DEBUG_LINES];
+    if let (true, Some((transform, movement, targeting))) = (shown, players.iter().next()) {
+        let position = transform.translation;
+        let block = BlockPosition::from_vector(position);
+        let target = targeting.hit()
+            .and_then(|hit|chunk_manager.get(hit.position, &chunks).map(|block|(hit.position, block)))
+            .map(|(position, block)|format!("{} at {}", static_blocks[block.btype as usize].2.name(), position))
+            .unwrap_or_else(||"none".to_string());
+        values = vec![
+            format!("{:.1} FPS ({:.1})", fps.current(), fps.average()),
+            format!("Position: {:.2} / {:.2} / {:.2}", position.x, position.y, position.z),
+            format!("Block: {}", block),
+            format!("Chunk: {}", block.chunk()),
+            format!("Facing: {} (yaw {:.1}, pitch {:.1})", facing(transform.rotation.mul_vec3(-Vec3::unit_z())), movement.yaw, movement.pitch),
+            format!("Target: {}", target),
+            format!("Chunks: {} loaded, {} meshes", chunk_manager.loaded_chunk_count(), chunk_manager.current_meshes()),
+            format!("Culling: {} drawn, {} outside the view, {} hidden in caves", culling.drawn, culling.frustum_culled, culling.cave_culled),
+            format!("CPU meshing: {} chunks in {:.2} ms, {} bytes per chunk", mesh_stats.meshes, mesh_stats.cpu_time.as_secs_f64() * 1000.0, mesh_stats.bytes_per_mesh()),
+        ];
+    }
+    for (DebugLine(line), mut text) in lines.iter_mut() {
+        if text.value != values[*line] {
+            text.value = values[*line].clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn facing_uses_the_dominant_axis() {
+        assert_eq!(facing(Vec3::new(0.1, 0.0, -1.0)), "north");
+        assert_eq!(facing(Vec3::new(0.0, -0.9, 0.2)), "south");
+        assert_eq!(facing(Vec3::new(2.0, 5.0, 1.0)), "east");
+        assert_eq!(facing(Vec3::new(-1.0, 0.0, 0.5)), "west");
+    }
+}
@@ -0,0 +1,228 @@
+use bevy::prelude::*;
+use crate::settings::Settings;
+use crate::state::{AppState, APP_STATE_STAGE, GAME_UPDATE, GAME_POST_UPDATE};
+use crate::menu::{MenuAssets, text};
+use crate::player::inventory::{Inventory, HOTBAR_SIZE};
+use crate::player::survival::{Health, MAX_HEALTH, MAX_AIR};
+use crate::world::block_types::StaticBlocksRes;
+use crate::console::spawn_console;
+use crate::hud::debug::{spawn_debug_overlay, toggle_debug_overlay, update_debug_overlay};
+
+pub mod debug;
+
+const CROSSHAIR_SIZE: f32 = 16.0;
+const CROSSHAIR_WIDTH: f32 = 2.0;
+const SLOT_SIZE: f32 = 52.0;
+const SLOT_FONT_SIZE: f32 = 14.0;
+/// slot names are cut to this length
+const SLOT_NAME_LENGTH: usize = 6;
+const BAR_WIDTH: f32 = 200.0;
+const BAR_HEIGHT: f32 = 8.0;
+const HUD_MARGIN: f32 = 8.0;
+
+/// The node containing the whole HUD, it exists while a world is loaded
+pub struct HudRoot;
+
+/// The colors of the HUD
+pub struct HudMaterials {
+    pub crosshair: Handle<ColorMaterial>,
+    pub slot: Handle<ColorMaterial>,
+    pub selected_slot: Handle<ColorMaterial>,
+    pub bar_background: Handle<ColorMaterial>,
+    pub health: Handle<ColorMaterial>,
+    pub air: Handle<ColorMaterial>,
+}
+
+/// A hotbar slot, the index is the inventory slot
+struct HotbarSlot(usize);
+
+/// The name and count of the stack in a hotbar slot
+struct HotbarText(usize);
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum Bar {
+    Health,
+    Air,
+}
+
+pub fn init_hud(builder: &mut AppBuilder, _settings: &Settings) {
+    builder.add_startup_system(setup_hud.system())
+        .on_state_enter(APP_STATE_STAGE, AppState::Loading, spawn_hud.system())
+        //entering the main menu closes the world
+        .on_state_enter(APP_STATE_STAGE, AppState::MainMenu, despawn_hud.system())
+        .add_system_to_stage(GAME_UPDATE, toggle_debug_overlay.system())
+        .add_system_to_stage(GAME_POST_UPDATE, update_hotbar.system())
+        .add_system_to_stage(GAME_POST_UPDATE, update_bars.system())
+        .add_system_to_stage(GAME_POST_UPDATE, update_debug_overlay.system());
+}
+
+fn setup_hud(commands: &mut Commands, mut materials: ResMut<Assets<ColorMaterial>>) {
+    commands.insert_resource(HudMaterials {
+        crosshair: materials.add(Color::rgba(1.0, 1.0, 1.0, 0.8).into()),
+        slot: materials.add(Color::rgba(0.1, 0.1, 0.1, 0.6).into()),
+        selected_slot: materials.add(Color::rgba(0.7, 0.7, 0.7, 0.8).into()),
+        bar_background: materials.add(Color::rgba(0.1, 0.1, 0.1, 0.6).into()),
+        health: materials.add(Color::rgb(0.8, 0.1, 0.1).into()),
+        air: materials.add(Color::rgb(0.2, 0.4, 0.9).into()),
+    });
+}
+
+fn node(style: Style, material: Handle<ColorMaterial>) -> NodeBundle {
+    NodeBundle {
+        style,
+        material,
+        ..Default::default()
+    }
+}
+
+/// a node which only arranges its children
+fn layout(assets: &MenuAssets, style: Style) -> NodeBundle {
+    node(style, assets.transparent.clone())
+}
+
+fn spawn_crosshair(parent: &mut ChildBuilder, materials: &HudMaterials) {
+    //the bars are centered, so the direction of the y axis doesnt matter
+    for (width, height) in [(CROSSHAIR_SIZE, CROSSHAIR_WIDTH), (CROSSHAIR_WIDTH, CROSSHAIR_SIZE)].iter() {
+        parent.spawn(node(Style {
+            position_type: PositionType::Absolute,
+            position: Rect {
+                left: Val::Percent(50.0),
+                bottom: Val::Percent(50.0),
+                ..Default::default()
+            },
+            margin: Rect {
+                left: Val::Px(-width / 2.0),
+                bottom: Val::Px(-height / 2.0),
+                ..Default::default()
+            },
+            size: Size::new(Val::Px(*width), Val::Px(*height)),
+            ..Default::default()
+        }, materials.crosshair.clone()));
+    }
+}
+
+fn spawn_bar(parent: &mut ChildBuilder, materials: &HudMaterials, bar: Bar, material: Handle<ColorMaterial>) {
+    parent
+        .spawn(node(Style {
+            size: Size::new(Val::Px(BAR_WIDTH), Val::Px(BAR_HEIGHT)),
+            margin: Rect::all(Val::Px(2.0)),
+            ..Default::default()
+        }, materials.bar_background.clone()))
+        .with_children(|parent| {
+            parent.spawn(node(Style {
+                size: Size::new(Val::Px(BAR_WIDTH), Val::Px(BAR_HEIGHT)),
+                ..Default::default()
+            }, material))
+                .with(bar);
+        });
+}
+
+fn spawn_hotbar(parent: &mut ChildBuilder, assets: &MenuAssets, materials: &HudMaterials) {
+    parent
+        .spawn(layout(assets, Style {
+            flex_direction: FlexDirection::Row,
+            ..Default::default()
+        }))
+        .with_children(|parent| {
+            for slot in 0..HOTBAR_SIZE {
+                parent
+                    .spawn(node(Style {
+                        size: Size::new(Val::Px(SLOT_SIZE), Val::Px(SLOT_SIZE)),
+                        margin: Rect::all(Val::Px(2.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..Default::default()
+                    }, materials.slot.clone()))
+                    .with(HotbarSlot(slot))
+                    .with_children(|parent| {
+                        parent.spawn(text(assets, "", SLOT_FONT_SIZE))
+                            .with(HotbarText(slot));
+                    });
+            }
+        });
+}
+
+/// the crosshair in the center, the debug overlay at the top and the console, health and hotbar at the bottom
+fn spawn_hud(commands: &mut Commands, assets: Res<MenuAssets>, materials: Res<HudMaterials>) {
+    commands
+        .spawn(layout(&assets, Style {
+            size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+            //the children of a column are placed bottom up
+            flex_direction: FlexDirection::ColumnReverse,
+            justify_content: JustifyContent::SpaceBetween,
+            ..Default::default()
+        }))
+        .with(HudRoot)
+        .with_children(|parent| {
+            spawn_crosshair(parent, &materials);
+            spawn_debug_overlay(parent, &assets);
+            parent
+                .spawn(layout(&assets, Style {
+                    flex_direction: FlexDirection::ColumnReverse,
+                    align_items: AlignItems::Center,
+                    margin: Rect::all(Val::Px(HUD_MARGIN)),
+                    ..Default::default()
+                }))
+                .with_children(|parent| {
+                    spawn_console(parent, &assets);
+                    spawn_bar(parent, &materials, Bar::Air, materials.air.clone());
+                    spawn_bar(parent, &materials, Bar::Health, materials.health.clone());
+                    spawn_hotbar(parent, &assets, &materials);
+                });
+        });
+}
+
+fn despawn_hud(commands: &mut Commands, roots: Query<Entity, With<HudRoot>>) {
+    for root in roots.iter() {
+        commands.despawn_recursive(root);
+    }
+}
+
+/// the text of a hotbar slot, the shortened block name above the count
+fn slot_text(inventory: &Inventory, slot: usize, static_blocks: &StaticBlocksRes) -> String {
+    match inventory.hotbar()[slot] {
+        Some(stack) => {
+            let name: String = static_blocks[stack.block.btype as usize].2.name()
+                .chars()
+                .take(SLOT_NAME_LENGTH)
+                .collect();
+            format!("{} {}", name, stack.count)
+        }
+        None => String::new(),
+    }
+}
+
+fn update_hotbar(
+    materials: Res<HudMaterials>,
+    static_blocks: Res<StaticBlocksRes>,
+    inventories: Query<&Inventory, Changed<Inventory>>,
+    mut slots: Query<(&HotbarSlot, &mut Handle<ColorMaterial>)>,
+    mut texts: Query<(&HotbarText, &mut Text)>,
+) {
+    for inventory in inventories.iter() {
+        for (HotbarSlot(slot), mut material) in slots.iter_mut() {
+            *material = if *slot == inventory.selected() {
+                materials.selected_slot.clone()
+            } else {
+                materials.slot.clone()
+            };
+        }
+        for (HotbarText(slot), mut text) in texts.iter_mut() {
+            text.value = slot_text(inventory, *slot, &static_blocks);
+        }
+    }
+}
+
+/// the bars are scaled to the remaining health and air, air is only shown under water
+fn update_bars(players: Query<&Health>, mut bars: Query<(&Bar, &mut Style)>) {
+    for health in players.iter() {
+        for (bar, mut style) in bars.iter_mut() {
+            let fraction = match bar {
+                Bar::Health => health.current() / MAX_HEALTH,
+                Bar::Air if health.air() < MAX_AIR => health.air() / MAX_AIR,
+                Bar::Air => 0.0,
+            };
+            style.size.width = Val::Px(BAR_WIDTH * fraction.max(0.0));
+        }
+    }
+}
@@ -0,0 +1,344 @@
+use bevy::prelude::*;
+use std::collections::{HashMap, BTreeMap};
+use crate::input::binding::{Binding, Inputs, PRESS_THRESHOLD};
+use crate::settings::Controls;
+
+pub const FORWARD: &str = "forward";
+pub const BACKWARD: &str = "backward";
+pub const LEFT: &str = "left";
+pub const RIGHT: &str = "right";
+/// also moves up while flying
+pub const JUMP: &str = "jump";
+/// also moves down while flying
+pub const SNEAK: &str = "sneak";
+pub const SPRINT: &str = "sprint";
+pub const BREAK: &str = "break";
+pub const PLACE: &str = "place";
+pub const TOGGLE_GAME_MODE: &str = "toggle_game_mode";
+/// opens and closes the pause menu
+pub const PAUSE: &str = "pause";
+/// shows and hides the debug overlay
+pub const TOGGLE_DEBUG: &str = "toggle_debug";
+/// opens the console for chat messages
+pub const OPEN_CHAT: &str = "open_chat";
+/// opens the console with a slash for a command
+pub const OPEN_COMMAND: &str = "open_command";
+/// turning the camera, the mouse turns it directly
+pub const LOOK_LEFT: &str = "look_left";
+pub const LOOK_RIGHT: &str = "look_right";
+pub const LOOK_UP: &str = "look_up";
+pub const LOOK_DOWN: &str = "look_down";
+pub const HOTBAR_NEXT: &str = "hotbar_next";
+pub const HOTBAR_PREVIOUS: &str = "hotbar_previous";
+/// the actions selecting the hotbar slots, in slot order
+pub const HOTBAR: [&str; 9] = [
+    "hotbar_1",
+    "hotbar_2",
+    "hotbar_3",
+    "hotbar_4",
+    "hotbar_5",
+    "hotbar_6",
+    "hotbar_7",
+    "hotbar_8",
+    "hotbar_9",
+];
+
+#[derive(Copy, Clone, Default, Debug)]
+struct ActionState {
+    /// the highest value of the bindings between 0.0 and 1.0
+    value: f32,
+    pressed: bool,
+    just_pressed: bool,
+    just_released: bool,
+}
+
+/// The state of the named actions in the current frame.
+///
+/// Systems query actions instead of keys, so the bindings can be changed in the settings.
+/// Unknown actions are never pressed.
+/// While the actions are blocked (by a text field), all actions are released.
+#[derive(Default)]
+pub struct Actions {
+    bindings: BTreeMap<String, Vec<Binding>>,
+    dead_zone: f32,
+    states: HashMap<String, ActionState>,
+    gamepads: Vec<Gamepad>,
+    blocked: bool,
+}
+
+impl Actions {
+    pub fn new(controls: &Controls) -> Self {
+        Actions {
+            bindings: controls.bindings.clone(),
+            dead_zone: controls.gamepad_dead_zone,
+            states: HashMap::new(),
+            gamepads: Vec::new(),
+            blocked: false,
+        }
+    }
+    pub fn bindings(&self) -> &BTreeMap<String, Vec<Binding>> {
+        &self.bindings
+    }
+    pub fn set_controls(&mut self, controls: &Controls) {
+        self.bindings = controls.bindings.clone();
+        self.dead_zone = controls.gamepad_dead_zone;
+        self.states.clear();
+    }
+    pub fn connect(&mut self, gamepad: Gamepad) {
+        if !self.gamepads.contains(&gamepad) {
+            self.gamepads.push(gamepad);
+        }
+    }
+    pub fn disconnect(&mut self, gamepad: Gamepad) {
+        self.gamepads.retain(|connected|*connected != gamepad);
+    }
+    /// the states keep being updated while blocked, so keys held when unblocking arent just pressed
+    pub fn set_blocked(&mut self, blocked: bool) {
+        self.blocked = blocked;
+    }
+    pub fn is_blocked(&self) -> bool {
+        self.blocked
+    }
+    fn state(&self, action: &str) -> ActionState {
+        if self.blocked {
+            return ActionState::default();
+        }
+        self.states.get(action).cloned().unwrap_or_default()
+    }
+    /// the analog value of the action between 0.0 and 1.0, buttons are either 0.0 or 1.0
+    pub fn value(&self, action: &str) -> f32 {
+        self.state(action).value
+    }
+    pub fn pressed(&self, action: &str) -> bool {
+        self.state(action).pressed
+    }
+    pub fn just_pressed(&self, action: &str) -> bool {
+        self.state(action).just_pressed
+    }
+    pub fn just_released(&self, action: &str) -> bool {
+        self.state(action).just_released
+    }
+    /// the value of the positive minus the value of the negative action
+    pub fn axis(&self, positive: &str, negative: &str) -> f32 {
+        self.value(positive) - self.value(negative)
+    }
+    /// updates the actions, an action is pressed while the value of any binding exceeds `PRESS_THRESHOLD`
+    pub fn update(
+        &mut self,
+        keys: &Input<KeyCode>,
+        mouse: &Input<MouseButton>,
+        gamepad_buttons: &Input<GamepadButton>,
+        gamepad_axes: &Axis<GamepadAxis>,
+    ) {
+        let inputs = Inputs {
+            keys,
+            mouse,
+            gamepad_buttons,
+            gamepad_axes,
+            gamepads: &self.gamepads,
+            dead_zone: self.dead_zone,
+        };
+        for (action, bindings) in self.bindings.iter() {
+            let value = bindings.iter().map(|binding|binding.value(&inputs)).fold(0.0, f32::max);
+            let pressed = value > PRESS_THRESHOLD;
+            let state = self.states.entry(action.clone()).or_default();
+            *state = ActionState {
+                value,
+                pressed,
+                just_pressed: pressed && !state.pressed,
+                just_released: !pressed && state.pressed,
+            };
+        }
+    }
+}
+
+pub fn update_actions(
+    mut actions: ResMut<Actions>,
+    mut gamepad_reader: Local<EventReader<GamepadEvent>>,
+    gamepad_events: Res<Events<GamepadEvent>>,
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+) {
+    for GamepadEvent(gamepad, event) in gamepad_reader.iter(&gamepad_events) {
+        match event {
+            GamepadEventType::Connected => actions.connect(*gamepad),
+            GamepadEventType::Disconnected => actions.disconnect(*gamepad),
+            _ => {}
+        }
+    }
+    actions.update(&keys, &mouse, &gamepad_buttons, &gamepad_axes);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::binding::{Modifier, AxisDirection};
+    use crate::input::init_input;
+    use crate::settings::Settings;
+    use bevy::input::InputPlugin;
+    use bevy::input::gamepad::GamepadEventRaw;
+
+    fn actions() -> Actions {
+        let mut controls = Controls::default();
+        controls.bindings.clear();
+        controls.bindings.insert(FORWARD.to_string(), vec![
+            Binding::key(KeyCode::W),
+            Binding::key(KeyCode::Up),
+            Binding::gamepad_axis(GamepadAxisType::LeftStickY, AxisDirection::Positive),
+        ]);
+        controls.bindings.insert(PLACE.to_string(), vec![Binding::mouse(MouseButton::Right)]);
+        controls.bindings.insert(SPRINT.to_string(), vec![Binding::key(KeyCode::W).with(Modifier::Control)]);
+        Actions::new(&controls)
+    }
+
+    /// updates the actions with the keyboard and mouse input
+    fn update(actions: &mut Actions, keys: &Input<KeyCode>, mouse: &Input<MouseButton>) {
+        actions.update(keys, mouse, &Input::default(), &Axis::default());
+    }
+
+    #[test]
+    fn any_binding_presses_the_action() {
+        let mut actions = actions();
+        let mut keys = Input::default();
+        let mouse = Input::default();
+        keys.press(KeyCode::Up);
+        update(&mut actions, &keys, &mouse);
+        assert!(actions.pressed(FORWARD));
+        assert!(actions.just_pressed(FORWARD));
+        assert!(!actions.pressed(PLACE));
+
+        update(&mut actions, &keys, &mouse);
+        assert!(actions.pressed(FORWARD));
+        assert!(!actions.just_pressed(FORWARD));
+
+        keys.release(KeyCode::Up);
+        update(&mut actions, &keys, &mouse);
+        assert!(!actions.pressed(FORWARD));
+        assert!(actions.just_released(FORWARD));
+    }
+
+    #[test]
+    fn modifiers_are_required() {
+        let mut actions = actions();
+        let mut keys = Input::default();
+        let mouse = Input::default();
+        keys.press(KeyCode::W);
+        update(&mut actions, &keys, &mouse);
+        assert!(actions.pressed(FORWARD));
+        assert!(!actions.pressed(SPRINT));
+
+        keys.press(KeyCode::RControl);
+        update(&mut actions, &keys, &mouse);
+        assert!(actions.pressed(FORWARD));
+        assert!(actions.pressed(SPRINT));
+    }
+
+    #[test]
+    fn unknown_actions_are_released() {
+        let actions = actions();
+        assert!(!actions.pressed("unknown"));
+        assert_eq!(actions.axis(FORWARD, BACKWARD), 0.0);
+    }
+
+    #[test]
+    fn blocked_actions_are_released() {
+        let mut actions = actions();
+        let mut keys = Input::default();
+        let mouse = Input::default();
+        actions.set_blocked(true);
+        keys.press(KeyCode::W);
+        update(&mut actions, &keys, &mouse);
+        assert!(!actions.pressed(FORWARD));
+
+        //a key held while unblocking isnt just pressed
+        actions.set_blocked(false);
+        update(&mut actions, &keys, &mouse);
+        assert!(actions.pressed(FORWARD));
+        assert!(!actions.just_pressed(FORWARD));
+    }
+
+    #[test]
+    fn sticks_respect_the_dead_zone() {
+        let mut actions = actions();
+        let gamepad = Gamepad(0);
+        let axis = GamepadAxis(gamepad, GamepadAxisType::LeftStickY);
+        let mut axes = Axis::default();
+        actions.connect(gamepad);
+
+        axes.set(axis, 0.1);
+        actions.update(&Input::default(), &Input::default(), &Input::default(), &axes);
+        assert_eq!(actions.value(FORWARD), 0.0);
+
+        axes.set(axis, 0.6);
+        actions.update(&Input::default(), &Input::default(), &Input::default(), &axes);
+        let expected = (0.6 - Controls::default().gamepad_dead_zone) / (1.0 - Controls::default().gamepad_dead_zone);
+        assert!((actions.value(FORWARD) - expected).abs() < 1e-4);
+
+        //pulling the stick back doesnt move forward
+        axes.set(axis, -1.0);
+        actions.update(&Input::default(), &Input::default(), &Input::default(), &axes);
+        assert_eq!(actions.value(FORWARD), 0.0);
+    }
+
+    #[test]
+    fn default_controls_dont_conflict() {
+        assert!(Controls::default().conflicts().is_empty());
+    }
+
+    #[test]
+    fn shared_bindings_conflict() {
+        let mut controls = Controls::default();
+        controls.bindings.get_mut(PLACE).unwrap().push(Binding::key(KeyCode::W).with(Modifier::Shift));
+        controls.bindings.get_mut(JUMP).unwrap().push(Binding::key(KeyCode::W));
+        let conflicts = controls.conflicts();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!((conflicts[0].0.as_str(), conflicts[0].1.as_str()), (FORWARD, JUMP));
+    }
+
+    /// an app with the input systems of bevy and the action layer, but without a window
+    fn headless_app() -> App {
+        let mut builder = App::build();
+        builder.add_plugin(InputPlugin);
+        init_input(&mut builder, &Settings::default());
+        builder.app
+    }
+
+    fn send(app: &mut App, event: GamepadEventType) {
+        app.resources.get_mut::<Events<GamepadEventRaw>>().unwrap().send(GamepadEventRaw(Gamepad(0), event));
+    }
+
+    #[test]
+    fn gamepad_events_trigger_actions() {
+        let mut app = headless_app();
+        send(&mut app, GamepadEventType::Connected);
+        send(&mut app, GamepadEventType::AxisChanged(GamepadAxisType::LeftStickY, 1.0));
+        send(&mut app, GamepadEventType::ButtonChanged(GamepadButtonType::RightTrigger2, 1.0));
+        app.update();
+        {
+            let actions = app.resources.get::<Actions>().unwrap();
+            assert!(actions.pressed(FORWARD));
+            assert!((actions.value(FORWARD) - 1.0).abs() < 1e-4);
+            assert!(actions.just_pressed(BREAK));
+            assert!(!actions.pressed(PLACE));
+        }
+
+        send(&mut app, GamepadEventType::ButtonChanged(GamepadButtonType::RightTrigger2, 0.0));
+        send(&mut app, GamepadEventType::ButtonChanged(GamepadButtonType::RightTrigger, 1.0));
+        app.update();
+        let actions = app.resources.get::<Actions>().unwrap();
+        assert!(actions.just_released(BREAK));
+        assert!(actions.just_pressed(HOTBAR_NEXT));
+    }
+
+    #[test]
+    fn disconnected_gamepads_are_ignored() {
+        let mut app = headless_app();
+        send(&mut app, GamepadEventType::Connected);
+        send(&mut app, GamepadEventType::Disconnected);
+        send(&mut app, GamepadEventType::ButtonChanged(GamepadButtonType::South, 1.0));
+        app.update();
+        assert!(!app.resources.get::<Actions>().unwrap().pressed(JUMP));
+    }
+}
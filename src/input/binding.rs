@@ -0,0 +1,137 @@
+use bevy::prelude::*;
+use serde::{Serialize, Deserialize};
+
+/// actions are pressed, if the value of a binding exceeds this
+pub const PRESS_THRESHOLD: f32 = 0.5;
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
+pub enum AxisDirection {
+    Positive,
+    Negative,
+}
+
+/// A button or a half of an axis which can trigger an action.
+/// Gamepad inputs are read from all connected gamepads.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
+pub enum InputSource {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    GamepadButton(GamepadButtonType),
+    GamepadAxis(GamepadAxisType, AxisDirection),
+}
+
+/// A key which has to be held in addition to the button of a binding.
+/// The left and the right key are equivalent.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+pub enum Modifier {
+    Shift,
+    Control,
+    Alt,
+}
+
+impl Modifier {
+    fn keys(&self) -> [KeyCode; 2] {
+        match self {
+            Modifier::Shift => [KeyCode::LShift, KeyCode::RShift],
+            Modifier::Control => [KeyCode::LControl, KeyCode::RControl],
+            Modifier::Alt => [KeyCode::LAlt, KeyCode::RAlt],
+        }
+    }
+    pub fn is_held(&self, keys: &Input<KeyCode>) -> bool {
+        self.keys().iter().any(|key|keys.pressed(*key))
+    }
+}
+
+/// The raw input of one frame
+pub struct Inputs<'a> {
+    pub keys: &'a Input<KeyCode>,
+    pub mouse: &'a Input<MouseButton>,
+    pub gamepad_buttons: &'a Input<GamepadButton>,
+    pub gamepad_axes: &'a Axis<GamepadAxis>,
+    pub gamepads: &'a [Gamepad],
+    /// axis values below this are ignored, the rest is rescaled to 0.0 to 1.0
+    pub dead_zone: f32,
+}
+
+impl<'a> Inputs<'a> {
+    fn axis(&self, axis: GamepadAxisType, direction: AxisDirection) -> f32 {
+        self.gamepads.iter()
+            .filter_map(|gamepad|self.gamepad_axes.get(GamepadAxis(*gamepad, axis)))
+            .map(|value|match direction {
+                AxisDirection::Positive => value,
+                AxisDirection::Negative => -value,
+            })
+            .map(|value|apply_dead_zone(value, self.dead_zone))
+            .fold(0.0, f32::max)
+    }
+}
+
+/// maps the value to 0.0 inside the dead zone and rescales the rest to 0.0 to 1.0
+pub fn apply_dead_zone(value: f32, dead_zone: f32) -> f32 {
+    if value <= dead_zone {
+        0.0
+    } else {
+        ((value - dead_zone) / (1.0 - dead_zone)).min(1.0)
+    }
+}
+
+/// A button together with the modifiers, which have to be held.
+/// Bindings without modifiers are active regardless of the held modifiers.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
+pub struct Binding {
+    pub input: InputSource,
+    #[serde(default)]
+    pub modifiers: Vec<Modifier>,
+}
+
+impl Binding {
+    pub fn new(input: InputSource) -> Self {
+        Binding {
+            input,
+            modifiers: Vec::new(),
+        }
+    }
+    pub fn key(key: KeyCode) -> Self {
+        Self::new(InputSource::Key(key))
+    }
+    pub fn mouse(button: MouseButton) -> Self {
+        Self::new(InputSource::Mouse(button))
+    }
+    pub fn gamepad_button(button: GamepadButtonType) -> Self {
+        Self::new(InputSource::GamepadButton(button))
+    }
+    pub fn gamepad_axis(axis: GamepadAxisType, direction: AxisDirection) -> Self {
+        Self::new(InputSource::GamepadAxis(axis, direction))
+    }
+    pub fn with(mut self, modifier: Modifier) -> Self {
+        if !self.modifiers.contains(&modifier) {
+            self.modifiers.push(modifier);
+            self.modifiers.sort();
+        }
+        self
+    }
+    /// 1.0 for pressed buttons, the deflection for axes, 0.0 if a modifier isnt held
+    pub fn value(&self, inputs: &Inputs) -> f32 {
+        if !self.modifiers.iter().all(|modifier|modifier.is_held(inputs.keys)) {
+            return 0.0;
+        }
+        let pressed = match self.input {
+            InputSource::Key(key) => inputs.keys.pressed(key),
+            InputSource::Mouse(button) => inputs.mouse.pressed(button),
+            InputSource::GamepadButton(button) => inputs.gamepads.iter()
+                .any(|gamepad|inputs.gamepad_buttons.pressed(GamepadButton(*gamepad, button))),
+            InputSource::GamepadAxis(axis, direction) => return inputs.axis(axis, direction),
+        };
+        if pressed { 1.0 } else { 0.0 }
+    }
+    /// both bindings are triggered by exactly the same input
+    pub fn conflicts_with(&self, other: &Binding) -> bool {
+        let mut modifiers = self.modifiers.clone();
+        let mut other_modifiers = other.modifiers.clone();
+        modifiers.sort();
+        modifiers.dedup();
+        other_modifiers.sort();
+        other_modifiers.dedup();
+        self.input == other.input && modifiers == other_modifiers
+    }
+}
@@ -0,0 +1,17 @@
+use bevy::prelude::*;
+use bevy::prelude::stage::PRE_UPDATE;
+use crate::settings::Settings;
+use crate::input::actions::{Actions, update_actions};
+
+pub mod binding;
+pub mod actions;
+
+pub fn init_input(builder: &mut AppBuilder, settings: &Settings) {
+    for conflict in settings.controls.conflicts() {
+        eprintln!("conflicting bindings: {} and {} are both bound to {:?}", conflict.0, conflict.1, conflict.2);
+    }
+
+    builder.add_resource(Actions::new(&settings.controls))
+        //bevy updates the input in the event stage
+        .add_system_to_stage(PRE_UPDATE, update_actions.system());
+}
@@ -6,8 +6,8 @@
 
 use crate::engine::load_engine;
 use anyhow::Result;
-use crate::settings::Settings;
-use std::io::{BufReader, BufWriter};
+use crate::settings::{Settings, SETTINGS_PATH};
+use std::io::BufReader;
 use std::fs::File;
 
 #[macro_use] extern crate impl_ops;
@@ -22,23 +22,19 @@ pub mod settings;
 pub mod entities;
 pub mod content;
 pub mod physics;
+pub mod input;
+pub mod state;
+pub mod menu;
+pub mod hud;
+pub mod console;
 
 fn load_settings() -> Result<Settings> {
-    Ok(match serde_json::from_reader(BufReader::new(File::open("./settings.json")?)) {
+    Ok(match serde_json::from_reader(BufReader::new(File::open(SETTINGS_PATH)?)) {
         Ok(settings) => settings,
         Err(error) => {
             eprintln!("could`nt load settings: {}", error.to_string());
             let settings = Settings::default();
-
-            serde_json::to_writer_pretty(
-                BufWriter::new(File::with_options()
-                    .write(true)
-                    .create(true)
-                    .truncate(true)
-                    .open("./settings.json")?),
-                &settings,
-            )?;
-
+            settings.save()?;
             settings
         }
     })
@@ -0,0 +1,203 @@
+use bevy::prelude::*;
+use bevy::app::AppExit;
+use crate::settings::Settings;
+use crate::state::{AppState, APP_STATE_STAGE};
+use crate::menu::pause::init_pause;
+use crate::menu::worlds::{WorldSelection, spawn_world_list, spawn_edit_world, world_list_buttons, edit_world_input, update_form_texts, finish_loading};
+
+pub mod worlds;
+pub mod pause;
+
+const BUTTON_WIDTH: f32 = 400.0;
+const BUTTON_HEIGHT: f32 = 40.0;
+const FONT_SIZE: f32 = 24.0;
+const TITLE_FONT_SIZE: f32 = 48.0;
+
+/// The entity containing the whole screen of a menu, it is despawned when the state is left
+pub struct MenuRoot;
+
+/// The camera rendering the menus, it is kept when a world is closed
+pub struct MenuCamera;
+
+/// The shared font and colors of the menus
+pub struct MenuAssets {
+    pub font: Handle<Font>,
+    pub background: Handle<ColorMaterial>,
+    /// the background of menus shown on top of the world
+    pub overlay: Handle<ColorMaterial>,
+    pub transparent: Handle<ColorMaterial>,
+    pub normal: Handle<ColorMaterial>,
+    pub hovered: Handle<ColorMaterial>,
+    pub pressed: Handle<ColorMaterial>,
+    pub selected: Handle<ColorMaterial>,
+}
+
+/// The buttons of the main menu
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum MainMenuButton {
+    Singleplayer,
+    Quit,
+}
+
+pub fn init_menu(builder: &mut AppBuilder, settings: &Settings) {
+    builder.add_resource(WorldSelection::default())
+        .add_startup_system(setup_menu.system())
+        .on_state_enter(APP_STATE_STAGE, AppState::MainMenu, spawn_main_menu.system())
+        .on_state_update(APP_STATE_STAGE, AppState::MainMenu, main_menu_buttons.system())
+        .on_state_exit(APP_STATE_STAGE, AppState::MainMenu, despawn_menu.system())
+        .on_state_enter(APP_STATE_STAGE, AppState::WorldList, spawn_world_list.system())
+        .on_state_update(APP_STATE_STAGE, AppState::WorldList, world_list_buttons.system())
+        .on_state_exit(APP_STATE_STAGE, AppState::WorldList, despawn_menu.system())
+        .on_state_enter(APP_STATE_STAGE, AppState::EditWorld, spawn_edit_world.system())
+        .on_state_update(APP_STATE_STAGE, AppState::EditWorld, edit_world_input.system())
+        .on_state_update(APP_STATE_STAGE, AppState::EditWorld, update_form_texts.system())
+        .on_state_exit(APP_STATE_STAGE, AppState::EditWorld, despawn_menu.system())
+        .on_state_update(APP_STATE_STAGE, AppState::Loading, finish_loading.system())
+        .add_system(button_colors.system());
+
+    init_pause(builder);
+}
+
+fn setup_menu(
+    commands: &mut Commands,
+    settings: Res<Settings>,
+    server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands
+        .insert_resource(MenuAssets {
+            font: server.load(settings.game_settings.asset_path.join("font.ttf")),
+            background: materials.add(Color::rgb(0.1, 0.1, 0.1).into()),
+            overlay: materials.add(Color::rgba(0.0, 0.0, 0.0, 0.6).into()),
+            transparent: materials.add(Color::NONE.into()),
+            normal: materials.add(Color::rgb(0.25, 0.25, 0.25).into()),
+            hovered: materials.add(Color::rgb(0.35, 0.35, 0.35).into()),
+            pressed: materials.add(Color::rgb(0.45, 0.45, 0.45).into()),
+            selected: materials.add(Color::rgb(0.2, 0.35, 0.6).into()),
+        })
+        .spawn(CameraUiBundle::default())
+        .with(MenuCamera);
+}
+
+/// the text style of all menu texts
+pub fn text(assets: &MenuAssets, value: &str, font_size: f32) -> TextBundle {
+    TextBundle {
+        text: Text {
+            value: value.to_string(),
+            font: assets.font.clone(),
+            style: TextStyle {
+                font_size,
+                color: Color::WHITE,
+                ..Default::default()
+            },
+        },
+        ..Default::default()
+    }
+}
+
+/// spawns a full screen node with the title, the content is added by `children`
+pub fn spawn_screen(commands: &mut Commands, assets: &MenuAssets, background: Handle<ColorMaterial>, title: &str, children: impl FnOnce(&mut ChildBuilder)) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                //the children of a column are placed bottom up
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            material: background,
+            ..Default::default()
+        })
+        .with(MenuRoot)
+        .with_children(|parent| {
+            parent.spawn(text(assets, title, TITLE_FONT_SIZE));
+            children(parent);
+        });
+}
+
+/// spawns a button with a label and the component `button`, which tells the buttons apart
+pub fn spawn_button<T: Component>(parent: &mut ChildBuilder, assets: &MenuAssets, label: &str, button: T) {
+    spawn_sized_button(parent, assets, label, button, BUTTON_WIDTH);
+}
+
+pub fn spawn_sized_button<T: Component>(parent: &mut ChildBuilder, assets: &MenuAssets, label: &str, button: T, width: f32) {
+    parent
+        .spawn(ButtonBundle {
+            style: Style {
+                size: Size::new(Val::Px(width), Val::Px(BUTTON_HEIGHT)),
+                margin: Rect::all(Val::Px(4.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            material: assets.normal.clone(),
+            ..Default::default()
+        })
+        .with(button)
+        .with_children(|parent| {
+            parent.spawn(text(assets, label, FONT_SIZE));
+        });
+}
+
+/// a row of buttons
+pub fn spawn_row(parent: &mut ChildBuilder, assets: &MenuAssets, children: impl FnOnce(&mut ChildBuilder)) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Row,
+                justify_content: JustifyContent::Center,
+                ..Default::default()
+            },
+            material: assets.transparent.clone(),
+            ..Default::default()
+        })
+        .with_children(children);
+}
+
+pub fn despawn_menu(commands: &mut Commands, roots: Query<Entity, With<MenuRoot>>) {
+    for root in roots.iter() {
+        commands.despawn_recursive(root);
+    }
+}
+
+/// highlights hovered and pressed buttons, selected buttons keep their material
+fn button_colors(
+    assets: Res<MenuAssets>,
+    mut buttons: Query<(&Interaction, &mut Handle<ColorMaterial>), (Mutated<Interaction>, With<Button>)>,
+) {
+    for (interaction, mut material) in buttons.iter_mut() {
+        if *material == assets.selected {
+            continue;
+        }
+        *material = match interaction {
+            Interaction::Clicked => assets.pressed.clone(),
+            Interaction::Hovered => assets.hovered.clone(),
+            Interaction::None => assets.normal.clone(),
+        };
+    }
+}
+
+fn spawn_main_menu(commands: &mut Commands, assets: Res<MenuAssets>) {
+    spawn_screen(commands, &assets, assets.background.clone(), "Mine Clone", |parent| {
+        spawn_button(parent, &assets, "Singleplayer", MainMenuButton::Singleplayer);
+        spawn_button(parent, &assets, "Quit", MainMenuButton::Quit);
+    });
+}
+
+fn main_menu_buttons(
+    mut state: ResMut<State<AppState>>,
+    mut app_exit: ResMut<Events<AppExit>>,
+    buttons: Query<(&Interaction, &MainMenuButton), Mutated<Interaction>>,
+) {
+    for (interaction, button) in buttons.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        match button {
+            MainMenuButton::Singleplayer => state.set_next(AppState::WorldList).unwrap(),
+            MainMenuButton::Quit => app_exit.send(AppExit),
+        }
+    }
+}
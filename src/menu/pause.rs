@@ -0,0 +1,329 @@
+use bevy::prelude::*;
+use bevy::ui::Node;
+use bevy::app::AppExit;
+use bevy::window::WindowCloseRequested;
+use crate::settings::Settings;
+use crate::state::{AppState, APP_STATE_STAGE, GAME_POST_UPDATE};
+use crate::input::actions::{Actions, PAUSE};
+use crate::world::chunk::ChunkManager;
+use crate::physics::rigid_body::PhysicsEngine;
+use crate::entities::entity::Object;
+use crate::entities::registry::EntityTypes;
+use crate::entities::item::DroppedItem;
+use crate::entities::spawning::{Spawner, save_all_entities};
+use crate::entities::pathfinding::Pathfinder;
+use crate::player::inventory::Inventory;
+use crate::world::time::WorldTime;
+use crate::world::weather::WeatherState;
+use crate::menu::{MenuAssets, MenuCamera, spawn_screen, spawn_button, spawn_sized_button, spawn_row, despawn_menu, text};
+
+const STEP_BUTTON_WIDTH: f32 = 40.0;
+const LABEL_WIDTH: f32 = 360.0;
+const LABEL_HEIGHT: f32 = 40.0;
+const LABEL_FONT_SIZE: f32 = 22.0;
+/// the possible values of `RenderSettings::msaa_samples`
+const MSAA_SAMPLES: [u32; 4] = [1, 2, 4, 8];
+/// the maximal load distance in chunks, distant chunks are meshed at a coarse level of detail
+const MAX_DISTANCE: f32 = 32.0;
+const MAX_MOUSE_SENSITIVITY: f32 = 30.0;
+
+/// The settings, which can be changed in the pause menu
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Setting {
+    MsaaSamples,
+    LoadDistance,
+    UnloadDistance,
+    MouseSensitivity,
+    PrintFps,
+}
+
+impl Setting {
+    pub const ALL: [Setting; 5] = [
+        Setting::MsaaSamples,
+        Setting::LoadDistance,
+        Setting::UnloadDistance,
+        Setting::MouseSensitivity,
+        Setting::PrintFps,
+    ];
+
+    /// settings which are only read at startup, changes are saved but take effect after a restart
+    pub fn needs_restart(&self) -> bool {
+        //the render pipelines are built with the samples of the `Msaa` resource at startup
+        *self == Setting::MsaaSamples
+    }
+
+    pub fn text(&self, settings: &Settings) -> String {
+        let text = self.value_text(settings);
+        if self.needs_restart() {
+            format!("{} (after restart)", text)
+        } else {
+            text
+        }
+    }
+
+    fn value_text(&self, settings: &Settings) -> String {
+        match self {
+            Setting::MsaaSamples => format!("MSAA: {}x", settings.render_settings.msaa_samples),
+            Setting::LoadDistance => format!("Load distance: {} chunks", settings.game_settings.load_distance),
+            Setting::UnloadDistance => format!("Unload distance: {} chunks", settings.game_settings.unload_distance),
+            Setting::MouseSensitivity => format!("Mouse sensitivity: {:.0}", settings.controls.mouse_sensitivity),
+            Setting::PrintFps => format!("Print FPS: {}", if settings.game_settings.print_fps { "on" } else { "off" }),
+        }
+    }
+
+    /// increases or decreases the setting, the unload distance stays above the load distance
+    pub fn step(&self, settings: &mut Settings, up: bool) {
+        let sign = if up { 1.0 } else { -1.0 };
+        let game_settings = &mut settings.game_settings;
+        match self {
+            Setting::MsaaSamples => {
+                let samples = &mut settings.render_settings.msaa_samples;
+                let index = MSAA_SAMPLES.iter().position(|value|value == samples).unwrap_or(0);
+                let index = if up { (index + 1).min(MSAA_SAMPLES.len() - 1) } else { index.saturating_sub(1) };
+                *samples = MSAA_SAMPLES[index];
+            }
+            Setting::LoadDistance => {
+                game_settings.load_distance = (game_settings.load_distance + sign).max(1.0).min(MAX_DISTANCE);
+                game_settings.unload_distance = game_settings.unload_distance.max(game_settings.load_distance + 1.0);
+            }
+            Setting::UnloadDistance => {
+                game_settings.unload_distance = (game_settings.unload_distance + sign)
+                    .min(MAX_DISTANCE + 1.0)
+                    .max(game_settings.load_distance + 1.0);
+            }
+            Setting::MouseSensitivity => {
+                let sensitivity = &mut settings.controls.mouse_sensitivity;
+                *sensitivity = (*sensitivity + sign).max(1.0).min(MAX_MOUSE_SENSITIVITY);
+            }
+            Setting::PrintFps => game_settings.print_fps = !game_settings.print_fps,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum PauseButton {
+    Resume,
+    QuitToMenu,
+    Decrease(Setting),
+    Increase(Setting),
+}
+
+/// The text showing the current value of a setting
+struct SettingText(Setting);
+
+pub fn init_pause(builder: &mut AppBuilder) {
+    builder.on_state_enter(APP_STATE_STAGE, AppState::InGame, grab_cursor.system())
+        .on_state_update(APP_STATE_STAGE, AppState::InGame, pause_game.system())
+        .on_state_exit(APP_STATE_STAGE, AppState::InGame, release_cursor.system())
+        .on_state_enter(APP_STATE_STAGE, AppState::Paused, spawn_pause_menu.system())
+        .on_state_update(APP_STATE_STAGE, AppState::Paused, pause_menu_buttons.system())
+        .on_state_update(APP_STATE_STAGE, AppState::Paused, quit_world.system())
+        .on_state_exit(APP_STATE_STAGE, AppState::Paused, despawn_menu.system())
+        .add_system_to_stage(GAME_POST_UPDATE, save_on_exit.system());
+}
+
+fn grab_cursor(mut windows: ResMut<Windows>) {
+    if let Some(window) = windows.get_primary_mut() {
+        window.set_cursor_lock_mode(true);
+        window.set_cursor_visibility(false);
+    }
+}
+
+fn release_cursor(mut windows: ResMut<Windows>) {
+    if let Some(window) = windows.get_primary_mut() {
+        window.set_cursor_lock_mode(false);
+        window.set_cursor_visibility(true);
+    }
+}
+
+fn pause_game(actions: Res<Actions>, mut state: ResMut<State<AppState>>) {
+    if actions.just_pressed(PAUSE) {
+        state.set_next(AppState::Paused).unwrap();
+    }
+}
+
+fn spawn_pause_menu(commands: &mut Commands, settings: Res<Settings>, assets: Res<MenuAssets>) {
+    spawn_screen(commands, &assets, assets.overlay.clone(), "Paused", |parent| {
+        for setting in Setting::ALL.iter() {
+            spawn_row(parent, &assets, |parent| {
+                spawn_sized_button(parent, &assets, "<", PauseButton::Decrease(*setting), STEP_BUTTON_WIDTH);
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(LABEL_WIDTH), Val::Px(LABEL_HEIGHT)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..Default::default()
+                        },
+                        material: assets.transparent.clone(),
+                        ..Default::default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn(text(&assets, &setting.text(&settings), LABEL_FONT_SIZE))
+                            .with(SettingText(*setting));
+                    });
+                spawn_sized_button(parent, &assets, ">", PauseButton::Increase(*setting), STEP_BUTTON_WIDTH);
+            });
+        }
+        spawn_button(parent, &assets, "Resume", PauseButton::Resume);
+        spawn_button(parent, &assets, "Save and Quit to Menu", PauseButton::QuitToMenu);
+    });
+}
+
+/// changes to the settings are applied immediately and written to the settings file
+fn pause_menu_buttons(
+    actions: Res<Actions>,
+    mut state: ResMut<State<AppState>>,
+    mut settings: ResMut<Settings>,
+    mut chunk_manager: ResMut<ChunkManager>,
+    clicked: Query<(&Interaction, &PauseButton), Mutated<Interaction>>,
+    mut texts: Query<(&SettingText, &mut Text)>,
+) {
+    if actions.just_pressed(PAUSE) {
+        state.set_next(AppState::InGame).unwrap();
+    }
+    let mut changed = false;
+    for (interaction, button) in clicked.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        match button {
+            PauseButton::Resume => state.set_next(AppState::InGame).unwrap(),
+            PauseButton::Decrease(setting) => {
+                setting.step(&mut settings, false);
+                changed = true;
+            }
+            PauseButton::Increase(setting) => {
+                setting.step(&mut settings, true);
+                changed = true;
+            }
+            PauseButton::QuitToMenu => {}
+        }
+    }
+    if !changed {
+        return;
+    }
+    chunk_manager.set_distances(settings.game_settings.load_distance, settings.game_settings.unload_distance);
+    for (SettingText(setting), mut text) in texts.iter_mut() {
+        text.value = setting.text(&settings);
+    }
+    if let Err(error) = settings.save() {
+        eprintln!("couldnt save the settings: {}", error);
+    }
+}
+
+/// saves everything, which isnt saved while playing
+fn save_world(
+    chunk_manager: &mut ChunkManager,
+    types: &EntityTypes,
+    world_time: &WorldTime,
+    weather: &WeatherState,
+    objects: &Query<(Entity, &Object, &Transform)>,
+    items: &Query<(Entity, &DroppedItem, &Transform)>,
+    inventories: &Query<&Inventory>,
+) {
+    save_all_entities(chunk_manager, types, objects, items);
+    for inventory in inventories.iter() {
+        chunk_manager.provider_mut().save_inventory(inventory);
+    }
+    chunk_manager.provider_mut().save_time(world_time.ticks());
+    chunk_manager.provider_mut().save_weather(weather);
+    chunk_manager.provider_mut().flush();
+}
+
+fn quit_world(
+    commands: &mut Commands,
+    mut state: ResMut<State<AppState>>,
+    mut chunk_manager: ResMut<ChunkManager>,
+    mut physics: ResMut<PhysicsEngine>,
+    mut spawner: ResMut<Spawner>,
+    mut pathfinder: ResMut<Pathfinder>,
+    types: Res<EntityTypes>,
+    world_time: Res<WorldTime>,
+    weather: Res<WeatherState>,
+    clicked: Query<(&Interaction, &PauseButton), Mutated<Interaction>>,
+    objects: Query<(Entity, &Object, &Transform)>,
+    items: Query<(Entity, &DroppedItem, &Transform)>,
+    inventories: Query<&Inventory>,
+    entities: Query<Entity, (Without<Node>, Without<MenuCamera>)>,
+) {
+    let quit = clicked.iter()
+        .any(|(interaction, button)|*interaction == Interaction::Clicked && *button == PauseButton::QuitToMenu);
+    if !quit {
+        return;
+    }
+    save_world(&mut chunk_manager, &types, &world_time, &weather, &objects, &items, &inventories);
+    for entity in entities.iter() {
+        commands.despawn(entity);
+    }
+    physics.clear();
+    spawner.clear();
+    pathfinder.clear();
+    state.set_next(AppState::MainMenu).unwrap();
+}
+
+/// saves the open world, if the window is closed or the app exits without quitting the world first
+fn save_on_exit(
+    mut close_reader: Local<EventReader<WindowCloseRequested>>,
+    close_events: Res<Events<WindowCloseRequested>>,
+    mut exit_reader: Local<EventReader<AppExit>>,
+    exit_events: Res<Events<AppExit>>,
+    mut chunk_manager: ResMut<ChunkManager>,
+    types: Res<EntityTypes>,
+    world_time: Res<WorldTime>,
+    weather: Res<WeatherState>,
+    objects: Query<(Entity, &Object, &Transform)>,
+    items: Query<(Entity, &DroppedItem, &Transform)>,
+    inventories: Query<&Inventory>,
+) {
+    //both readers are drained, so one exit doesnt save twice
+    let closed = close_reader.iter(&close_events).count() > 0;
+    let exited = exit_reader.iter(&exit_events).count() > 0;
+    if closed || exited {
+        save_world(&mut chunk_manager, &types, &world_time, &weather, &objects, &items, &inventories);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unload_distance_stays_above_load_distance() {
+        let mut settings = Settings::default();
+        settings.game_settings.load_distance = 3.0;
+        settings.game_settings.unload_distance = 4.0;
+        Setting::LoadDistance.step(&mut settings, true);
+        assert_eq!(settings.game_settings.load_distance, 4.0);
+        assert_eq!(settings.game_settings.unload_distance, 5.0);
+
+        Setting::UnloadDistance.step(&mut settings, false);
+        assert_eq!(settings.game_settings.unload_distance, 5.0);
+    }
+
+    #[test]
+    fn settings_are_clamped() {
+        let mut settings = Settings::default();
+        settings.game_settings.load_distance = 1.0;
+        Setting::LoadDistance.step(&mut settings, false);
+        assert_eq!(settings.game_settings.load_distance, 1.0);
+
+        settings.render_settings.msaa_samples = 8;
+        Setting::MsaaSamples.step(&mut settings, true);
+        assert_eq!(settings.render_settings.msaa_samples, 8);
+        Setting::MsaaSamples.step(&mut settings, false);
+        assert_eq!(settings.render_settings.msaa_samples, 4);
+
+        Setting::PrintFps.step(&mut settings, true);
+        assert!(settings.game_settings.print_fps);
+    }
+
+    #[test]
+    fn only_msaa_needs_a_restart() {
+        let settings = Settings::default();
+        for setting in Setting::ALL.iter() {
+            assert_eq!(setting.needs_restart(), *setting == Setting::MsaaSamples);
+            assert_eq!(setting.text(&settings).ends_with("(after restart)"), setting.needs_restart());
+        }
+    }
+}
@@ -0,0 +1,346 @@
+use bevy::prelude::*;
+use bevy::window::ReceivedCharacter;
+use rand::RngCore;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use anyhow::Result;
+use crate::settings::Settings;
+use crate::state::AppState;
+use crate::content::files::{Files, WorldInfo, SavedWorld, list_worlds, rename_world, delete_world};
+use crate::world::chunk::ChunkManager;
+use crate::world::coordinates::ChunkPosition;
+use crate::menu::{MenuAssets, MenuRoot, spawn_screen, spawn_button, spawn_sized_button, spawn_row, text};
+
+const ROW_BUTTON_WIDTH: f32 = 130.0;
+const FORM_FONT_SIZE: f32 = 24.0;
+
+/// The buttons of the world list and the world form
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum WorldButton {
+    /// selects the world at this index
+    World(usize),
+    Play,
+    Create,
+    Rename,
+    Delete,
+    Back,
+    Confirm,
+    Cancel,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum Field {
+    Name,
+    Seed,
+}
+
+/// The texts of the world form, which are updated while typing
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum FormText {
+    Field(Field),
+    Error,
+}
+
+/// The state of the world list and the world form
+#[derive(Default)]
+pub struct WorldSelection {
+    worlds: Vec<SavedWorld>,
+    selected: Option<usize>,
+    /// the selected world is deleted, once the deletion is confirmed
+    deleting: bool,
+    /// the renamed world, None while a new world is created
+    renaming: Option<SavedWorld>,
+    name: String,
+    seed: String,
+    field: Option<Field>,
+    /// the last failed action
+    error: Option<String>,
+}
+
+impl WorldSelection {
+    fn selected_world(&self) -> Option<&SavedWorld> {
+        self.selected.and_then(|index|self.worlds.get(index))
+    }
+    fn field_mut(&mut self) -> Option<&mut String> {
+        match self.field {
+            Some(Field::Name) => Some(&mut self.name),
+            Some(Field::Seed) => Some(&mut self.seed),
+            None => None,
+        }
+    }
+    fn field_text(&self, field: Field) -> String {
+        let (label, value) = match field {
+            Field::Name => ("Name", &self.name),
+            Field::Seed => ("Seed", &self.seed),
+        };
+        let cursor = if self.field == Some(field) { "_" } else { "" };
+        format!("{}: {}{}", label, value, cursor)
+    }
+}
+
+/// numbers are used directly, other seeds are hashed and an empty seed is random
+pub fn parse_seed(seed: &str) -> u64 {
+    let seed = seed.trim();
+    if seed.is_empty() {
+        rand::thread_rng().next_u64()
+    } else if let Ok(number) = seed.parse() {
+        number
+    } else {
+        let mut hasher = DefaultHasher::new();
+        seed.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// hands the world to the chunk manager, the world is set up in the loading state
+fn open_world(commands: &mut Commands, state: &mut State<AppState>, settings: &Settings, files: Files) {
+    commands.insert_resource(ChunkManager::new(
+        Box::new(files),
+        ChunkPosition::new(0, 1, 0),
+        settings.game_settings.load_distance,
+        settings.game_settings.unload_distance,
+        settings.game_settings.asset_path.clone(),
+    ));
+    state.set_next(AppState::Loading).unwrap();
+}
+
+pub fn spawn_world_list(
+    commands: &mut Commands,
+    settings: Res<Settings>,
+    assets: Res<MenuAssets>,
+    mut selection: ResMut<WorldSelection>,
+) {
+    selection.worlds = list_worlds(&settings.game_settings.save_path);
+    selection.selected = None;
+    selection.deleting = false;
+    build_world_list(commands, &assets, &selection);
+}
+
+fn build_world_list(commands: &mut Commands, assets: &MenuAssets, selection: &WorldSelection) {
+    spawn_screen(commands, assets, assets.background.clone(), "Select World", |parent| {
+        for (index, world) in selection.worlds.iter().enumerate() {
+            spawn_button(parent, assets, &world.info.name, WorldButton::World(index));
+        }
+        if selection.worlds.is_empty() {
+            parent.spawn(text(assets, "No saved worlds", FORM_FONT_SIZE));
+        }
+        if let Some(error) = &selection.error {
+            parent.spawn(text(assets, error, FORM_FONT_SIZE));
+        }
+        let deleting = selection.selected_world().filter(|_|selection.deleting);
+        if let Some(world) = deleting {
+            parent.spawn(text(assets, &format!("Delete {} forever?", world.info.name), FORM_FONT_SIZE));
+            spawn_row(parent, assets, |parent| {
+                spawn_sized_button(parent, assets, "Delete", WorldButton::Confirm, ROW_BUTTON_WIDTH);
+                spawn_sized_button(parent, assets, "Cancel", WorldButton::Cancel, ROW_BUTTON_WIDTH);
+            });
+            return;
+        }
+        spawn_row(parent, assets, |parent| {
+            spawn_sized_button(parent, assets, "Play", WorldButton::Play, ROW_BUTTON_WIDTH);
+            spawn_sized_button(parent, assets, "Create", WorldButton::Create, ROW_BUTTON_WIDTH);
+            spawn_sized_button(parent, assets, "Rename", WorldButton::Rename, ROW_BUTTON_WIDTH);
+        });
+        spawn_row(parent, assets, |parent| {
+            spawn_sized_button(parent, assets, "Delete", WorldButton::Delete, ROW_BUTTON_WIDTH);
+            spawn_sized_button(parent, assets, "Back", WorldButton::Back, ROW_BUTTON_WIDTH);
+        });
+    });
+}
+
+pub fn world_list_buttons(
+    commands: &mut Commands,
+    settings: Res<Settings>,
+    assets: Res<MenuAssets>,
+    mut state: ResMut<State<AppState>>,
+    mut selection: ResMut<WorldSelection>,
+    roots: Query<Entity, With<MenuRoot>>,
+    clicked: Query<(&Interaction, &WorldButton), Mutated<Interaction>>,
+    mut buttons: Query<(&WorldButton, &mut Handle<ColorMaterial>)>,
+) {
+    let save_path = &settings.game_settings.save_path;
+    //the list is rebuilt to show changed worlds and errors
+    let mut rebuild = false;
+    for (interaction, button) in clicked.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        rebuild |= selection.error.take().is_some();
+        match button {
+            WorldButton::World(index) => {
+                //selecting another world cancels the deletion
+                rebuild |= selection.deleting && selection.selected != Some(*index);
+                selection.deleting &= selection.selected == Some(*index);
+                selection.selected = Some(*index);
+                for (button, mut material) in buttons.iter_mut() {
+                    if let WorldButton::World(other) = button {
+                        *material = if other == index { assets.selected.clone() } else { assets.normal.clone() };
+                    }
+                }
+            }
+            WorldButton::Play => if let Some(world) = selection.selected_world().cloned() {
+                match Files::open(save_path, &world.folder) {
+                    Ok(files) => open_world(commands, &mut state, &settings, files),
+                    Err(error) => {
+                        selection.error = Some(format!("couldnt open {}: {}", world.info.name, error));
+                        rebuild = true;
+                    }
+                }
+            },
+            WorldButton::Create => {
+                selection.renaming = None;
+                selection.name = "New World".to_string();
+                selection.seed = String::new();
+                selection.field = Some(Field::Name);
+                state.set_next(AppState::EditWorld).unwrap();
+            }
+            WorldButton::Rename => if let Some(world) = selection.selected_world().cloned() {
+                selection.name = world.info.name.clone();
+                selection.seed = world.info.seed.to_string();
+                selection.renaming = Some(world);
+                selection.field = Some(Field::Name);
+                state.set_next(AppState::EditWorld).unwrap();
+            },
+            WorldButton::Delete => if selection.selected_world().is_some() {
+                selection.deleting = true;
+                rebuild = true;
+            },
+            WorldButton::Confirm => if let Some(world) = selection.selected_world().cloned() {
+                if let Err(error) = delete_world(save_path, &world.folder) {
+                    selection.error = Some(format!("couldnt delete {}: {}", world.info.name, error));
+                }
+                selection.worlds = list_worlds(save_path);
+                selection.selected = None;
+                selection.deleting = false;
+                rebuild = true;
+            },
+            WorldButton::Cancel => {
+                selection.deleting = false;
+                rebuild = true;
+            }
+            WorldButton::Back => state.set_next(AppState::MainMenu).unwrap(),
+        }
+    }
+    if rebuild {
+        for root in roots.iter() {
+            commands.despawn_recursive(root);
+        }
+        build_world_list(commands, &assets, &selection);
+    }
+}
+
+pub fn spawn_edit_world(commands: &mut Commands, assets: Res<MenuAssets>, selection: Res<WorldSelection>) {
+    let title = if selection.renaming.is_some() { "Rename World" } else { "Create World" };
+    spawn_screen(commands, &assets, assets.background.clone(), title, |parent| {
+        parent.spawn(text(&assets, &selection.field_text(Field::Name), FORM_FONT_SIZE))
+            .with(FormText::Field(Field::Name));
+        //the seed of existing worlds cant be changed
+        if selection.renaming.is_none() {
+            parent.spawn(text(&assets, &selection.field_text(Field::Seed), FORM_FONT_SIZE))
+                .with(FormText::Field(Field::Seed));
+        }
+        parent.spawn(text(&assets, "", FORM_FONT_SIZE))
+            .with(FormText::Error);
+        spawn_row(parent, &assets, |parent| {
+            spawn_sized_button(parent, &assets, "Confirm", WorldButton::Confirm, ROW_BUTTON_WIDTH);
+            spawn_sized_button(parent, &assets, "Cancel", WorldButton::Cancel, ROW_BUTTON_WIDTH);
+        });
+    });
+}
+
+/// creates and opens or renames the world
+fn confirm_world(commands: &mut Commands, state: &mut State<AppState>, settings: &Settings, selection: &WorldSelection) -> Result<()> {
+    let save_path = &settings.game_settings.save_path;
+    let name = selection.name.trim();
+    match &selection.renaming {
+        Some(world) => {
+            if world.info.name != name {
+                rename_world(save_path, &world.folder, name)?;
+            }
+            state.set_next(AppState::WorldList).unwrap();
+        }
+        None => {
+            let files = Files::create(save_path, WorldInfo {
+                name: name.to_string(),
+                seed: parse_seed(&selection.seed),
+            })?;
+            open_world(commands, state, settings, files);
+        }
+    }
+    Ok(())
+}
+
+/// typing into the fields, tab switches the field, enter confirms and escape cancels
+pub fn edit_world_input(
+    commands: &mut Commands,
+    settings: Res<Settings>,
+    keys: Res<Input<KeyCode>>,
+    characters: Res<Events<ReceivedCharacter>>,
+    mut reader: Local<EventReader<ReceivedCharacter>>,
+    mut state: ResMut<State<AppState>>,
+    mut selection: ResMut<WorldSelection>,
+    clicked: Query<(&Interaction, &WorldButton), Mutated<Interaction>>,
+) {
+    for event in reader.iter(&characters) {
+        if event.char.is_control() {
+            continue;
+        }
+        if let Some(field) = selection.field_mut() {
+            field.push(event.char);
+        }
+    }
+    if keys.just_pressed(KeyCode::Back) {
+        if let Some(field) = selection.field_mut() {
+            field.pop();
+        }
+    }
+    if keys.just_pressed(KeyCode::Tab) && selection.renaming.is_none() {
+        selection.field = match selection.field {
+            Some(Field::Name) => Some(Field::Seed),
+            _ => Some(Field::Name),
+        };
+    }
+
+    let mut confirm = keys.just_pressed(KeyCode::Return);
+    let mut cancel = keys.just_pressed(KeyCode::Escape);
+    for (interaction, button) in clicked.iter() {
+        if *interaction == Interaction::Clicked {
+            confirm |= *button == WorldButton::Confirm;
+            cancel |= *button == WorldButton::Cancel;
+        }
+    }
+    if cancel {
+        selection.error = None;
+        state.set_next(AppState::WorldList).unwrap();
+    } else if confirm {
+        let error = confirm_world(commands, &mut state, &settings, &selection).err();
+        selection.error = error.map(|error|error.to_string());
+    }
+}
+
+pub fn update_form_texts(selection: Res<WorldSelection>, mut texts: Query<(&FormText, &mut Text)>) {
+    for (form_text, mut text) in texts.iter_mut() {
+        text.value = match form_text {
+            FormText::Field(field) => selection.field_text(*field),
+            FormText::Error => selection.error.clone().unwrap_or_default(),
+        };
+    }
+}
+
+/// the world is set up when the loading state is entered, the game starts in the next frame
+pub fn finish_loading(mut state: ResMut<State<AppState>>) {
+    state.set_next(AppState::InGame).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeds_are_numbers_or_hashed_text() {
+        assert_eq!(parse_seed("42"), 42);
+        assert_eq!(parse_seed("  42 "), 42);
+        assert_eq!(parse_seed("hello"), parse_seed(" hello"));
+        assert_ne!(parse_seed("hello"), parse_seed("world"));
+    }
+}
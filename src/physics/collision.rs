@@ -0,0 +1,327 @@
+use bevy::math::Vec3;
+use crate::physics::collider::AAQuader;
+use crate::world::coordinates::BlockPosition;
+
+/// the highest ledge a grounded body walks up without jumping
+pub const STEP_HEIGHT: f32 = 0.5;
+/// tolerance for touching colliders
+const EPSILON: f32 = 1e-4;
+
+static FULL_BLOCK: [AAQuader; 1] = [AAQuader::new(Vec3{x:0.0, y:0.0, z:0.0}, Vec3{x:1.0, y:1.0, z:1.0})];
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct MoveResult {
+    /// the movement after collision
+    pub motion: Vec3,
+    /// the body stands on a collider after the movement
+    pub grounded: bool,
+    /// the movement was stopped along the axis (x, y, z)
+    pub blocked: [bool; 3],
+}
+
+fn component(vector: Vec3, axis: usize) -> f32 {
+    match axis {
+        0 => vector.x,
+        1 => vector.y,
+        _ => vector.z,
+    }
+}
+
+fn axis_vector(axis: usize, value: f32) -> Vec3 {
+    match axis {
+        0 => Vec3::new(value, 0.0, 0.0),
+        1 => Vec3::new(0.0, value, 0.0),
+        _ => Vec3::new(0.0, 0.0, value),
+    }
+}
+
+/// the colliders overlap on every axis except the given one
+fn overlaps_besides(a: &AAQuader, b: &AAQuader, axis: usize) -> bool {
+    (0..3).filter(|other|*other != axis).all(|other|{
+        component(a.lower(), other) < component(b.higher(), other) - EPSILON &&
+            component(a.higher(), other) > component(b.lower(), other) + EPSILON
+    })
+}
+
+/// the part of the motion along the axis the collider can move before it hits an obstacle
+pub fn sweep_axis(collider: &AAQuader, axis: usize, motion: f32, obstacles: &[AAQuader]) -> f32 {
+    let mut allowed = motion;
+    for obstacle in obstacles.iter().filter(|obstacle|overlaps_besides(collider, obstacle, axis)) {
+        if motion > 0.0 {
+            let gap = component(obstacle.lower(), axis) - component(collider.higher(), axis);
+            if gap >= -EPSILON && gap < allowed {
+                allowed = gap.max(0.0);
+            }
+        } else if motion < 0.0 {
+            let gap = component(obstacle.higher(), axis) - component(collider.lower(), axis);
+            if gap <= EPSILON && gap > allowed {
+                allowed = gap.min(0.0);
+            }
+        }
+    }
+    allowed
+}
+
+/// collects the colliders of all blocks touching the region.
+/// `world` returns the colliders of a block, or None if the block isnt loaded.
+/// Unloaded blocks are solid, so nothing falls out of the world while chunks are loading.
+pub fn collect_obstacles(region: AAQuader, world: &mut impl FnMut(BlockPosition) -> Option<&'static [AAQuader]>) -> Vec<AAQuader> {
+    let lower = BlockPosition::from_vector(region.lower());
+    let higher = BlockPosition::from_vector(region.higher());
+    let (lower, higher) = (lower.lower_corner(), higher.lower_corner());
+
+    let mut obstacles = Vec::new();
+    for x in lower.x as i64..=higher.x as i64 {
+        for y in lower.y as i64..=higher.y as i64 {
+            for z in lower.z as i64..=higher.z as i64 {
+                let position = BlockPosition::new(x, y, z);
+                let colliders = world(position).unwrap_or(&FULL_BLOCK);
+                obstacles.extend(colliders.iter().map(|collider|collider.translated(position.lower_corner())));
+            }
+        }
+    }
+    obstacles
+}
+
+/// the collider (in world coordinates) doesnt overlap any block collider, touching is allowed
+pub fn fits(collider: AAQuader, world: &mut impl FnMut(BlockPosition) -> Option<&'static [AAQuader]>) -> bool {
+    collect_obstacles(collider, world).iter().all(|obstacle|{
+        !overlaps_besides(&collider, obstacle, 1) ||
+            collider.lower().y >= obstacle.higher().y - EPSILON ||
+            collider.higher().y <= obstacle.lower().y + EPSILON
+    })
+}
+
+/// moves the collider along y, x and z one after another
+fn sweep(mut collider: AAQuader, motion: Vec3, obstacles: &[AAQuader]) -> (Vec3, [bool; 3]) {
+    let mut moved = Vec3::zero();
+    let mut blocked = [false; 3];
+    for axis in [1, 0, 2].iter().cloned() {
+        let wanted = component(motion, axis);
+        if wanted == 0.0 {
+            continue;
+        }
+        let allowed = sweep_axis(&collider, axis, wanted, obstacles);
+        blocked[axis] = (allowed - wanted).abs() > EPSILON;
+        collider.translate(axis_vector(axis, allowed));
+        moved += axis_vector(axis, allowed);
+    }
+    (moved, blocked)
+}
+
+/// Moves the collider (in world coordinates) by the motion without entering any block collider.
+///
+/// Grounded bodies walk up ledges up to `STEP_HEIGHT`.
+pub fn move_collider(
+    collider: AAQuader,
+    motion: Vec3,
+    can_step: bool,
+    world: &mut impl FnMut(BlockPosition) -> Option<&'static [AAQuader]>,
+) -> MoveResult {
+    let swept = AAQuader::new(
+        collider.lower().min(collider.lower() + motion) - Vec3::splat(EPSILON),
+        collider.higher().max(collider.higher() + motion) + Vec3::new(EPSILON, STEP_HEIGHT + EPSILON, EPSILON),
+    );
+    let obstacles = collect_obstacles(swept, world);
+
+    let (mut moved, mut blocked) = sweep(collider, motion, &obstacles);
+
+    let landed = motion.y <= 0.0 && (blocked[1] || motion.y == 0.0);
+    if can_step && landed && (blocked[0] || blocked[2]) {
+        //try to walk up a ledge
+        let up = sweep_axis(&collider, 1, STEP_HEIGHT, &obstacles);
+        let lifted = collider.translated(Vec3::new(0.0, up, 0.0));
+        let (horizontal, step_blocked) = sweep(lifted, Vec3::new(motion.x, 0.0, motion.z), &obstacles);
+        let down = sweep_axis(&lifted.translated(horizontal), 1, -up + motion.y.min(0.0), &obstacles);
+
+        let stepped = horizontal.x * horizontal.x + horizontal.z * horizontal.z;
+        let walked = moved.x * moved.x + moved.z * moved.z;
+        if stepped > walked + EPSILON {
+            moved = horizontal + Vec3::new(0.0, up + down, 0.0);
+            blocked = [step_blocked[0], true, step_blocked[2]];
+        }
+    }
+
+    //a motion ending exactly on a surface isnt blocked, so the ground below is probed
+    let probe = -2.0 * EPSILON;
+    let touching = sweep_axis(&collider.translated(moved), 1, probe, &obstacles) > probe;
+    let grounded = motion.y <= 0.0 && (blocked[1] || touching);
+
+    MoveResult {
+        motion: moved,
+        grounded,
+        blocked,
+    }
+}
+
+/// the amount by which the motion is reduced, while searching for a supported position
+const EDGE_STEP: f32 = 0.05;
+
+/// the collider would stand on something, if it moves down by at most `STEP_HEIGHT`
+fn supported(collider: &AAQuader, obstacles: &[AAQuader]) -> bool {
+    let depth = STEP_HEIGHT + EPSILON;
+    sweep_axis(collider, 1, -depth, obstacles) > -depth
+}
+
+/// Reduces the horizontal motion of a grounded collider, so it doesnt walk off a ledge.
+/// Each axis is handled separately, which allows sliding along an edge.
+pub fn keep_on_edges(
+    collider: AAQuader,
+    motion: Vec3,
+    world: &mut impl FnMut(BlockPosition) -> Option<&'static [AAQuader]>,
+) -> Vec3 {
+    let region = AAQuader::new(
+        collider.lower().min(collider.lower() + motion) - Vec3::new(EPSILON, STEP_HEIGHT + 2.0 * EPSILON, EPSILON),
+        collider.higher().max(collider.higher() + motion) + Vec3::splat(EPSILON),
+    );
+    let obstacles = collect_obstacles(region, world);
+    if !supported(&collider, &obstacles) {
+        return motion;
+    }
+
+    let mut limit = |collider: &AAQuader, axis: usize, mut value: f32| {
+        while value != 0.0 && !supported(&collider.translated(axis_vector(axis, value)), &obstacles) {
+            value = if value.abs() < EDGE_STEP { 0.0 } else { value - EDGE_STEP * value.signum() };
+        }
+        value
+    };
+    let x = limit(&collider, 0, motion.x);
+    let z = limit(&collider.translated(Vec3::new(x, 0.0, 0.0)), 2, motion.z);
+    Vec3::new(x, motion.y, z)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    static HALF_BLOCK: [AAQuader; 1] = [AAQuader::new(Vec3{x:0.0, y:0.0, z:0.0}, Vec3{x:1.0, y:0.5, z:1.0})];
+
+    fn player_at(feet: Vec3) -> AAQuader {
+        AAQuader::new(Vec3::new(-0.4, 0.0, -0.4), Vec3::new(0.4, 1.8, 0.4)).translated(feet)
+    }
+
+    /// a world with a floor at y = -1 and the given additional blocks
+    fn world(solid: &[(i64, i64, i64)], half: &[(i64, i64, i64)]) -> impl FnMut(BlockPosition) -> Option<&'static [AAQuader]> {
+        let solid: HashSet<_> = solid.iter().map(|(x, y, z)|BlockPosition::new(*x, *y, *z)).collect();
+        let half: HashSet<_> = half.iter().map(|(x, y, z)|BlockPosition::new(*x, *y, *z)).collect();
+        move |position| {
+            if position.lower_corner().y < 0.0 || solid.contains(&position) {
+                Some(&FULL_BLOCK[..])
+            } else if half.contains(&position) {
+                Some(&HALF_BLOCK[..])
+            } else {
+                Some(&[][..])
+            }
+        }
+    }
+
+    #[test]
+    fn lands_on_floor() {
+        let result = move_collider(player_at(Vec3::new(0.5, 0.3, 0.5)), Vec3::new(0.0, -1.0, 0.0), false, &mut world(&[], &[]));
+        assert!((result.motion.y + 0.3).abs() < 1e-3);
+        assert!(result.grounded);
+        assert_eq!(result.blocked, [false, true, false]);
+    }
+
+    #[test]
+    fn high_velocity_does_not_tunnel() {
+        let result = move_collider(player_at(Vec3::new(0.5, 40.0, 0.5)), Vec3::new(0.0, -500.0, 0.0), false, &mut world(&[], &[]));
+        assert!((result.motion.y + 40.0).abs() < 1e-3);
+        assert!(result.grounded);
+
+        let result = move_collider(player_at(Vec3::new(0.5, 0.0, 0.5)), Vec3::new(100.0, 0.0, 0.0), false, &mut world(&[(3, 0, 0), (3, 1, 0)], &[]));
+        assert!((result.motion.x - 2.1).abs() < 1e-3);
+        assert!(result.blocked[0]);
+    }
+
+    #[test]
+    fn stops_at_ceiling() {
+        let result = move_collider(player_at(Vec3::new(0.5, 0.0, 0.5)), Vec3::new(0.0, 2.0, 0.0), false, &mut world(&[(0, 2, 0)], &[]));
+        assert!((result.motion.y - 0.2).abs() < 1e-3);
+        assert!(!result.grounded);
+        assert!(result.blocked[1]);
+    }
+
+    #[test]
+    fn stops_in_corner() {
+        let walls = [(2, 0, 0), (2, 1, 0), (2, 0, 1), (2, 1, 1), (0, 0, 2), (1, 0, 2), (0, 1, 2), (1, 1, 2)];
+        let result = move_collider(player_at(Vec3::new(0.5, 0.0, 0.5)), Vec3::new(3.0, 0.0, 3.0), false, &mut world(&walls, &[]));
+        assert!((result.motion.x - 1.1).abs() < 1e-3);
+        assert!((result.motion.z - 1.1).abs() < 1e-3);
+        assert_eq!(result.blocked, [true, false, true]);
+    }
+
+    #[test]
+    fn slides_over_block_seams() {
+        let result = move_collider(player_at(Vec3::new(0.5, 0.0, 0.5)), Vec3::new(3.0, -0.1, 0.0), false, &mut world(&[], &[]));
+        assert!((result.motion.x - 3.0).abs() < 1e-3);
+        assert_eq!(result.blocked, [false, true, false]);
+    }
+
+    #[test]
+    fn steps_up_half_blocks() {
+        let result = move_collider(player_at(Vec3::new(0.5, 0.0, 0.5)), Vec3::new(1.0, -0.1, 0.0), true, &mut world(&[], &[(1, 0, 0)]));
+        assert!((result.motion.x - 1.0).abs() < 1e-3);
+        assert!((result.motion.y - 0.5).abs() < 1e-3);
+        assert!(result.grounded);
+    }
+
+    #[test]
+    fn does_not_step_up_full_blocks() {
+        let result = move_collider(player_at(Vec3::new(0.5, 0.0, 0.5)), Vec3::new(1.0, -0.1, 0.0), true, &mut world(&[(1, 0, 0)], &[]));
+        assert!((result.motion.x - 0.1).abs() < 1e-3);
+        assert!(result.motion.y.abs() < 1e-3);
+        assert!(result.blocked[0]);
+    }
+
+    #[test]
+    fn sneaking_stops_at_edges() {
+        //a platform from x = 0 to x = 2 above a pit
+        let mut world = |position: BlockPosition|{
+            let floor = position.lower_corner().y < 0.0 && position.lower_corner().x < 2.0;
+            Some(if floor { &FULL_BLOCK[..] } else { &[][..] })
+        };
+        let motion = keep_on_edges(player_at(Vec3::new(1.0, 0.0, 0.5)), Vec3::new(3.0, 0.0, 1.0), &mut world);
+        assert!(motion.x < 1.45 && motion.x > 1.3);
+        assert!((motion.z - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn sneaking_walks_down_steps() {
+        let motion = keep_on_edges(player_at(Vec3::new(0.5, 0.5, 0.5)), Vec3::new(1.0, 0.0, 0.0), &mut world(&[], &[(0, 0, 0)]));
+        assert!((motion.x - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn standing_up_needs_headroom() {
+        //a half block below a ceiling at y = 2 leaves room for a sneaking player only
+        let mut world = world(&[(0, 2, 0)], &[(0, 0, 0)]);
+        let sneaking = AAQuader::new(Vec3::new(-0.4, 0.0, -0.4), Vec3::new(0.4, 1.5, 0.4));
+        assert!(fits(sneaking.translated(Vec3::new(0.5, 0.5, 0.5)), &mut world));
+        assert!(!fits(player_at(Vec3::new(0.5, 0.5, 0.5)), &mut world));
+        assert!(fits(player_at(Vec3::new(1.5, 0.0, 0.5)), &mut world));
+    }
+
+    #[test]
+    fn unloaded_blocks_are_solid() {
+        let result = move_collider(player_at(Vec3::new(0.5, 5.0, 0.5)), Vec3::new(0.0, -1.0, 0.0), false, &mut |position: BlockPosition|{
+            if position.lower_corner().y < 4.0 { None } else { Some(&[][..]) }
+        });
+        assert!(result.grounded);
+        assert!((result.motion.y + 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn ending_on_the_floor_is_grounded() {
+        let result = move_collider(player_at(Vec3::new(0.5, 0.25, 0.5)), Vec3::new(0.0, -0.25, 0.0), false, &mut world(&[], &[]));
+        assert_eq!(result.blocked, [false, false, false]);
+        assert!(result.grounded);
+
+        let result = move_collider(player_at(Vec3::new(0.5, 0.0, 0.5)), Vec3::new(0.3, 0.0, 0.0), false, &mut world(&[], &[]));
+        assert!(result.grounded);
+
+        let result = move_collider(player_at(Vec3::new(0.5, 0.5, 0.5)), Vec3::new(0.0, -0.25, 0.0), false, &mut world(&[], &[]));
+        assert!(!result.grounded);
+    }
+}
@@ -1,29 +1,44 @@
 use bevy::app::AppBuilder;
 use crate::settings::Settings;
-use crate::physics::rigid_body::{PhysicsEngine, RigidBodyHandle};
+use crate::physics::rigid_body::{PhysicsEngine, RigidBodyHandle, PhysicsCommands, PhysicsEvents, PhysicsEvent};
 use bevy::ecs::{ResMut, Query, Res};
+use bevy::app::Events;
 use crate::world::chunk::{Chunk, ChunkManager};
-use bevy::prelude::{Transform, IntoSystem};
+use bevy::prelude::{Transform, IntoSystem, Time};
 use crate::world::block_types::StaticBlocksRes;
+use crate::state::GAME_UPDATE;
 
 pub mod rigid_body;
 pub mod ray;
 pub mod collider;
+pub mod collision;
 
 pub fn init_physics(builder: &mut AppBuilder, settings: &Settings) {
-    let (engine, _sender, _reviever) = PhysicsEngine::new();
-    builder.add_resource(engine);
-    builder.add_system(update_physics.system());
+    let (engine, sender, reciever) = PhysicsEngine::new(settings.physics_settings.clone());
+    builder.add_resource(engine)
+        .add_resource(PhysicsCommands::new(sender))
+        .add_resource(PhysicsEvents::new(reciever))
+        .add_event::<PhysicsEvent>()
+        .add_system_to_stage(GAME_UPDATE, update_physics.system())
+        .add_system_to_stage(GAME_UPDATE, forward_physics_events.system());
+}
+
+/// hands the physics events to bevys event queue, so any number of systems can react to them
+fn forward_physics_events(physics_events: Res<PhysicsEvents>, mut events: ResMut<Events<PhysicsEvent>>) {
+    for event in physics_events.drain() {
+        events.send(event);
+    }
 }
 
 //TODO: split in two, to avoid reserving resources mutable too long
 fn update_physics(
+    time: Res<Time>,
     mut physic_engine: ResMut<PhysicsEngine>,
     chunk_manager: Res<ChunkManager>,
     static_blocks: Res<StaticBlocksRes>,
     chunks: Query<(&Chunk,)>,
     enities: Query<(&RigidBodyHandle, &mut Transform)>
 ) {
-    physic_engine.step(&chunk_manager, &static_blocks, chunks);
+    physic_engine.update(time.delta_seconds(), &chunk_manager, &static_blocks, &chunks);
     physic_engine.update_entities(enities);
 }
\ No newline at end of file
@@ -10,28 +10,54 @@ pub struct Ray{
     direction: Vec3,
 }
 
+/// Walks through all blocks the ray passes, ordered by their distance to the origin
 pub struct GridSnap {
-    ray: Ray,
     current_block: BlockPosition,
+    //-1, 0 or 1 per axis
+    step: BlockVector,
+    //distance along the ray to the next block boundary per axis
+    t_max: Vec3,
+    //distance along the ray between two block boundaries per axis
+    t_delta: Vec3,
+    //entry of current_block
+    next_entry: (f32, BlockVector),
+    //entry of the last returned block
+    last_entry: (f32, BlockVector),
+}
+
+impl GridSnap {
+    /// the distance at which the ray entered the last returned block
+    pub fn entry_distance(&self) -> f32 {
+        self.last_entry.0
+    }
+    /// the normal of the face the ray entered the last returned block through.
+    /// Zero for the block containing the origin.
+    pub fn entry_normal(&self) -> BlockVector {
+        self.last_entry.1
+    }
 }
 
 impl Iterator for GridSnap {
     type Item = BlockPosition;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let collider = unsafe {
-            AAQuader::unchecked(
-                self.current_block.lower_corner(),
-                self.current_block.higher_corner(),
-            )
-        };
-        let hit_info = self.ray.hit_info(
-            &collider
-        ).unwrap_or_else(||unreachable!("the ray didnt found its next block!"));
         let current = self.current_block;
-        self.current_block += hit_info.leaving_face_normal;
-        Some(current)
+        self.last_entry = self.next_entry;
 
+        let (distance, step) = if self.t_max.x < self.t_max.y && self.t_max.x < self.t_max.z {
+            self.t_max.x += self.t_delta.x;
+            (self.t_max.x - self.t_delta.x, BlockVector::new(self.step.x, 0, 0))
+        } else if self.t_max.y < self.t_max.z {
+            self.t_max.y += self.t_delta.y;
+            (self.t_max.y - self.t_delta.y, BlockVector::new(0, self.step.y, 0))
+        } else {
+            self.t_max.z += self.t_delta.z;
+            (self.t_max.z - self.t_delta.z, BlockVector::new(0, 0, self.step.z))
+        };
+        self.current_block += step;
+        self.next_entry = (distance, -step);
+
+        Some(current)
     }
 }
 
@@ -39,9 +65,27 @@ impl Iterator for GridSnap {
 pub struct RayHitInfo {
     start: f32,
     end: f32,
+    entering_face_normal: BlockVector,
     leaving_face_normal: BlockVector,
 }
 
+impl RayHitInfo {
+    /// the distance at which the ray enters the collider, negative if the origin lies inside
+    pub fn start(&self) -> f32 {
+        self.start
+    }
+    /// the distance at which the ray leaves the collider
+    pub fn end(&self) -> f32 {
+        self.end
+    }
+    pub fn entering_face_normal(&self) -> BlockVector {
+        self.entering_face_normal
+    }
+    pub fn leaving_face_normal(&self) -> BlockVector {
+        self.leaving_face_normal
+    }
+}
+
 impl Ray {
     pub fn from_global_transform(transform: &Transform) -> Self {
         Ray::new(
@@ -55,6 +99,16 @@ impl Ray {
             direction: direction.normalize(),
         }
     }
+    pub fn origin(&self) -> Vec3 {
+        self.origin
+    }
+    pub fn direction(&self) -> Vec3 {
+        self.direction
+    }
+    /// the point at the given distance from the origin
+    pub fn at(&self, distance: f32) -> Vec3 {
+        self.origin + self.direction * distance
+    }
     pub fn translate(&mut self, vec: Vec3) {
         self.origin += vec;
     }
@@ -126,9 +180,18 @@ impl Ray {
                 BlockVector::new(x_or, 0, 0)
             };
 
+            let entering_face_normal = if z_start > y_start && z_start > x_start {
+                BlockVector::new(0, 0, -z_or)
+            } else if y_start > x_start && y_start > z_start {
+                BlockVector::new(0, -y_or, 0)
+            } else {
+                BlockVector::new(-x_or, 0, 0)
+            };
+
             Some(RayHitInfo{
                 start,
                 end,
+                entering_face_normal,
                 leaving_face_normal,
             })
         } else {
@@ -136,12 +199,74 @@ impl Ray {
         }
     }
     pub fn grid_snap(&self) -> GridSnap {
-        //this protects the programm from crashing if the values of the origin are integers
-        //TODO: fix this in GridSnap::next
-        let ray = self.translated(self.direction / 2.0);
+        fn axis(origin: f32, direction: f32) -> (i64, f32, f32) {
+            if direction > 0.0 {
+                (1, (origin.floor() + 1.0 - origin) / direction, 1.0 / direction)
+            } else if direction < 0.0 {
+                (-1, (origin - origin.floor()) / -direction, -1.0 / direction)
+            } else {
+                (0, f32::INFINITY, f32::INFINITY)
+            }
+        }
+        let (step_x, max_x, delta_x) = axis(self.origin.x, self.direction.x);
+        let (step_y, max_y, delta_y) = axis(self.origin.y, self.direction.y);
+        let (step_z, max_z, delta_z) = axis(self.origin.z, self.direction.z);
+
         GridSnap {
-            ray,
-            current_block: BlockPosition::from_vector(self.origin)
+            current_block: BlockPosition::from_vector(self.origin),
+            step: BlockVector::new(step_x, step_y, step_z),
+            t_max: Vec3::new(max_x, max_y, max_z),
+            t_delta: Vec3::new(delta_x, delta_y, delta_z),
+            next_entry: (0.0, BlockVector::new(0, 0, 0)),
+            last_entry: (0.0, BlockVector::new(0, 0, 0)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blocks(grid_snap: &mut GridSnap, count: usize) -> Vec<(i64, i64, i64)> {
+        grid_snap.take(count).map(|position|(position.x(), position.y(), position.z())).collect()
+    }
+
+    #[test]
+    fn grid_snap_follows_an_axis() {
+        let mut grid_snap = Ray::new(Vec3::new(0.5, 0.5, 0.5), Vec3::unit_x()).grid_snap();
+        assert_eq!(grid_snap.next(), Some(BlockPosition::new(0, 0, 0)));
+        assert_eq!(grid_snap.entry_distance(), 0.0);
+        assert_eq!(grid_snap.entry_normal(), BlockVector::new(0, 0, 0));
+        assert_eq!(grid_snap.next(), Some(BlockPosition::new(1, 0, 0)));
+        assert!((grid_snap.entry_distance() - 0.5).abs() < 1e-4);
+        assert_eq!(grid_snap.entry_normal(), BlockVector::new(-1, 0, 0));
+        assert_eq!(blocks(&mut grid_snap, 2), vec![(2, 0, 0), (3, 0, 0)]);
+    }
+
+    #[test]
+    fn grid_snap_walks_diagonally() {
+        let mut grid_snap = Ray::new(Vec3::new(0.2, 0.5, 0.5), Vec3::new(1.0, 0.5, 0.0)).grid_snap();
+        assert_eq!(blocks(&mut grid_snap, 6), vec![(0, 0, 0), (1, 0, 0), (1, 1, 0), (2, 1, 0), (3, 1, 0), (3, 2, 0)]);
+    }
+
+    #[test]
+    fn grid_snap_walks_in_negative_direction() {
+        let mut grid_snap = Ray::new(Vec3::new(-0.5, 2.5, 0.5), -Vec3::unit_y()).grid_snap();
+        assert_eq!(blocks(&mut grid_snap, 2), vec![(-1, 2, 0), (-1, 1, 0)]);
+        assert!((grid_snap.entry_distance() - 0.5).abs() < 1e-4);
+        assert_eq!(grid_snap.entry_normal(), BlockVector::new(0, 1, 0));
+        assert_eq!(blocks(&mut grid_snap, 2), vec![(-1, 0, 0), (-1, -1, 0)]);
+    }
+
+    #[test]
+    fn grid_snap_entry_distances_grow() {
+        let mut grid_snap = Ray::new(Vec3::new(0.3, 0.7, 0.1), Vec3::new(-0.4, 1.0, 0.8)).grid_snap();
+        let mut last = 0.0;
+        for _ in 0..20 {
+            grid_snap.next();
+            assert!(grid_snap.entry_distance() >= last);
+            last = grid_snap.entry_distance();
         }
+        assert!(last > 5.0);
     }
-}
\ No newline at end of file
+}
@@ -7,19 +7,49 @@ use bevy::ecs::Query;
 use bevy::prelude::Transform;
 use crate::world::block_types::{StaticBlocks, BlockFeel};
 use crate::physics::collider::AAQuader;
-use std::mem::replace;
+use crate::physics::collision::{move_collider, keep_on_edges};
+use crate::world::coordinates::{BlockPosition, ChunkPosition};
 use std::sync::Mutex;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::atomic::{AtomicU64, Ordering};
+use crate::settings::PhysicsSettings;
+
+/// The most steps per frame, a slower simulation is better than freezing the game
+const MAX_STEPS_PER_FRAME: u32 = 8;
+
+/// Bodies below this height fell out of the world
+pub const WORLD_BOTTOM: f32 = -64.0;
 
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
 pub struct RigidBodyHandle(NonZeroU64);
 
+impl RigidBodyHandle {
+    /// reserves a new unique handle, this allows spawning bodies via `PhysicsCommand::SpawnBody`
+    pub fn next() -> Self {
+        static COUNTER: AtomicU64 = AtomicU64::new(1);
+
+        let value = COUNTER.fetch_add(1, Ordering::SeqCst);
+        RigidBodyHandle(unsafe { NonZeroU64::new_unchecked(value) })
+    }
+}
+
 pub struct RigidBody {
     position: Vec3,
+    /// the position before the last step, used for interpolation
+    previous_position: Vec3,
     velocity: Vec3,
     next_force: Vec3,
     flying: bool,
+    grounded: bool,
+    in_fluid: bool,
+    /// grounded sneaking bodies dont walk off ledges
+    sneaking: bool,
+    out_of_world: bool,
+    /// triggers report overlapping bodies, but dont block them
+    trigger: bool,
+    /// ghosts pass through other bodies, only the blocks stop them
+    ghost: bool,
+    /// zero for immovable bodies
     inv_mass: f32,
     handle: RigidBodyHandle,
 
@@ -28,32 +58,47 @@ pub struct RigidBody {
 
 impl RigidBody {
     pub fn new(position: Vec3, inv_mass: f32, flying: bool, collider: AAQuader) -> Self {
-        static COUNTER: AtomicU64 = AtomicU64::new(1);
-
-        let value = COUNTER.fetch_add(1, Ordering::SeqCst);
-
+        Self::with_handle(RigidBodyHandle::next(), position, inv_mass, flying, collider)
+    }
+    pub fn with_handle(handle: RigidBodyHandle, position: Vec3, inv_mass: f32, flying: bool, collider: AAQuader) -> Self {
         RigidBody {
             position,
+            previous_position: position,
             velocity: Default::default(),
             next_force: Default::default(),
             flying,
-            inv_mass: 0.0,
-            handle: RigidBodyHandle(unsafe { NonZeroU64::new_unchecked(value) }),
+            grounded: false,
+            in_fluid: false,
+            sneaking: false,
+            out_of_world: false,
+            trigger: false,
+            ghost: false,
+            inv_mass,
+            handle,
             collider,
         }
     }
-    pub fn apply_force(&mut self) {
+    /// applies friction, gravity and the force of the current frame for one step
+    pub fn apply_force(&mut self, delta: f32, settings: &PhysicsSettings) {
         //Dampening
-        //TODO: specify ground and air friction
+        let ground_damping = (-settings.ground_friction * delta).exp();
+        let air_damping = (-settings.air_friction * delta).exp();
         if self.flying {
-            self.velocity *= 0.8;
+            self.velocity *= ground_damping;
         } else {
-            self.velocity = (self.velocity * 0.75) + Vec3::new(0.0, -0.1, 0.0);
+            let horizontal = if self.grounded { ground_damping } else { air_damping };
+            self.velocity = Vec3::new(
+                self.velocity.x * horizontal,
+                self.velocity.y * air_damping - settings.gravity * delta,
+                self.velocity.z * horizontal,
+            );
         }
         //Apply force
-        let force = replace(&mut self.next_force, Vec3::zero());
-        self.velocity += force;
-        self.position += self.velocity;
+        self.velocity += self.next_force * delta;
+    }
+    /// the position between the last two steps
+    pub fn interpolated_position(&self, alpha: f32) -> Vec3 {
+        self.previous_position.lerp(self.position, alpha)
     }
     pub fn position(&self) -> Vec3 {
         self.position
@@ -61,91 +106,475 @@ impl RigidBody {
     pub fn velocity(&self) -> Vec3 {
         self.velocity
     }
+    /// the body stood on a block after the last step
+    pub fn is_grounded(&self) -> bool {
+        self.grounded
+    }
+    pub fn is_flying(&self) -> bool {
+        self.flying
+    }
     pub fn handle(&self) -> RigidBodyHandle {
         self.handle
     }
     pub fn set_position(&mut self, position: Vec3) {
         self.position = position;
+        self.previous_position = position;
     }
+    /// moves the body without interpolation and stops it
+    pub fn teleport(&mut self, position: Vec3) {
+        self.set_position(position);
+        self.velocity = Vec3::zero();
+    }
+    /// adds an acceleration (in blocks per second squared) for the steps of the current frame
     pub fn add_force(&mut self, force: Vec3) {
         self.next_force += force;
     }
+    pub fn apply_impulse(&mut self, impulse: Vec3) {
+        self.velocity += impulse;
+    }
+    pub fn set_flying(&mut self, flying: bool) {
+        self.flying = flying;
+    }
+    pub fn is_sneaking(&self) -> bool {
+        self.sneaking
+    }
+    pub fn set_sneaking(&mut self, sneaking: bool) {
+        self.sneaking = sneaking;
+    }
+    pub fn is_in_fluid(&self) -> bool {
+        self.in_fluid
+    }
+    pub fn collider(&self) -> AAQuader {
+        self.collider
+    }
+    /// the collider relative to the position of the body
+    pub fn set_collider(&mut self, collider: AAQuader) {
+        self.collider = collider;
+    }
+    /// the collider in world coordinates
+    pub fn world_collider(&self) -> AAQuader {
+        self.collider.translated(self.position)
+    }
+    pub fn is_trigger(&self) -> bool {
+        self.trigger
+    }
+    pub fn is_ghost(&self) -> bool {
+        self.ghost
+    }
+    pub fn set_ghost(&mut self, ghost: bool) {
+        self.ghost = ghost;
+    }
+    /// the chunks the collider of the body touches
+    fn chunks(&self) -> impl Iterator<Item=ChunkPosition> {
+        let collider = self.world_collider();
+        let lower = BlockPosition::from_vector(collider.lower()).chunk();
+        let higher = BlockPosition::from_vector(collider.higher()).chunk();
+        (lower.x..=higher.x).flat_map(move|x|{
+            (lower.y..=higher.y).flat_map(move|y|{
+                (lower.z..=higher.z).map(move|z|ChunkPosition::new(x, y, z))
+            })
+        })
+    }
 }
 
+#[derive(Clone, Debug)]
 pub enum PhysicsCommand {
+    /// the handle has to be reserved with `RigidBodyHandle::next`
+    SpawnBody {
+        handle: RigidBodyHandle,
+        position: Vec3,
+        inv_mass: f32,
+        flying: bool,
+        collider: AAQuader,
+    },
+    /// a volume reporting the bodies inside, the handle has to be reserved with `RigidBodyHandle::next`
+    SpawnTrigger {
+        handle: RigidBodyHandle,
+        position: Vec3,
+        collider: AAQuader,
+    },
+    RemoveBody(RigidBodyHandle),
+    ApplyImpulse(RigidBodyHandle, Vec3),
+    Teleport(RigidBodyHandle, Vec3),
+    SetFlying(RigidBodyHandle, bool),
 }
 
+#[derive(Clone, Debug)]
 pub enum PhysicsEvent {
+    /// the body hit the ground with the given velocity
+    Landed {
+        body: RigidBodyHandle,
+        velocity: Vec3,
+    },
+    /// the body was stopped by a block (not by the ground)
+    CollidedWithBlock {
+        body: RigidBodyHandle,
+        block: BlockPosition,
+        velocity: Vec3,
+    },
+    EnteredFluid {
+        body: RigidBodyHandle,
+        block: BlockPosition,
+    },
+    LeftFluid {
+        body: RigidBodyHandle,
+    },
+    FellOutOfWorld {
+        body: RigidBodyHandle,
+    },
+    TriggerEntered {
+        trigger: RigidBodyHandle,
+        body: RigidBodyHandle,
+    },
+    TriggerLeft {
+        trigger: RigidBodyHandle,
+        body: RigidBodyHandle,
+    },
+}
+
+/// The sending end of the physics commands as a resource
+pub struct PhysicsCommands(Mutex<Sender<PhysicsCommand>>);
+
+impl PhysicsCommands {
+    pub fn new(sender: Sender<PhysicsCommand>) -> Self {
+        PhysicsCommands(Mutex::new(sender))
+    }
+    /// the command gets applied before the next physics step
+    pub fn send(&self, command: PhysicsCommand) {
+        //the engine lives as long as the app, so the receiver cant be gone
+        let _ = self.0.lock().unwrap().send(command);
+    }
+}
 
+/// The receiving end of the physics events as a resource
+pub struct PhysicsEvents(Mutex<Receiver<PhysicsEvent>>);
+
+impl PhysicsEvents {
+    pub fn new(receiver: Receiver<PhysicsEvent>) -> Self {
+        PhysicsEvents(Mutex::new(receiver))
+    }
+    pub fn drain(&self) -> Vec<PhysicsEvent> {
+        self.0.lock().unwrap().try_iter().collect()
+    }
 }
 
 pub struct PhysicsEngine {
     mapping: HashMap<RigidBodyHandle, usize>,
     rigid_bodies: Vec<RigidBody>,
+    settings: PhysicsSettings,
+    /// simulated time, which wasnt stepped yet
+    accumulator: f32,
+    /// (trigger, body) pairs overlapping after the last step
+    trigger_contacts: HashSet<(RigidBodyHandle, RigidBodyHandle)>,
 
     connection: Mutex<(Receiver<PhysicsCommand>, Sender<PhysicsEvent>)>,
 }
 
 impl PhysicsEngine {
-    pub fn new() -> (PhysicsEngine, Sender<PhysicsCommand>, Receiver<PhysicsEvent>) {
+    pub fn new(settings: PhysicsSettings) -> (PhysicsEngine, Sender<PhysicsCommand>, Receiver<PhysicsEvent>) {
         let (command_sender, command_reciever) = channel();
         let (event_sender, event_reciever) = channel();
 
-        (PhysicsEngine::create(command_reciever, event_sender), command_sender, event_reciever)
+        (PhysicsEngine::create(settings, command_reciever, event_sender), command_sender, event_reciever)
     }
-    pub fn create(input: Receiver<PhysicsCommand>, output: Sender<PhysicsEvent>) -> PhysicsEngine {
+    pub fn create(settings: PhysicsSettings, input: Receiver<PhysicsCommand>, output: Sender<PhysicsEvent>) -> PhysicsEngine {
         PhysicsEngine {
             mapping: HashMap::new(),
             rigid_bodies: Vec::new(),
+            settings,
+            accumulator: 0.0,
+            trigger_contacts: HashSet::new(),
 
             connection: Mutex::new((input, output)),
         }
     }
-    /// recieves physic commands, updates the rigidbodies, sends physic events
+    pub fn settings(&self) -> &PhysicsSettings {
+        &self.settings
+    }
+    pub fn set_settings(&mut self, settings: PhysicsSettings) {
+        self.settings = settings;
+    }
+    /// advances the simulation by the frame time in steps of `PhysicsSettings::timestep`.
+    /// The remaining time is kept for the next frame.
+    pub fn update(&mut self, delta: f32, world: &ChunkManager, static_blocks: &StaticBlocks, chunks: &Query<(&Chunk,)>) {
+        self.advance(delta, |engine|engine.step(world, static_blocks, chunks));
+    }
+    /// runs as many steps as fit into the accumulated time.
+    /// Forces last for one frame, so they are cleared even if no step happened,
+    /// the next frame adds them again for its own steps.
+    fn advance(&mut self, delta: f32, mut step: impl FnMut(&mut Self)) {
+        let timestep = self.settings.timestep;
+        self.accumulator += delta;
+
+        let mut steps = 0;
+        while self.accumulator >= timestep && steps < MAX_STEPS_PER_FRAME {
+            step(self);
+            self.accumulator -= timestep;
+            steps += 1;
+        }
+        if steps == MAX_STEPS_PER_FRAME {
+            //drop the time we cant catch up with
+            self.accumulator = self.accumulator.min(timestep);
+        }
+        for rigid_body in self.rigid_bodies.iter_mut() {
+            rigid_body.next_force = Vec3::zero();
+        }
+    }
+    /// how far the simulation is between the last and the next step (from 0 to 1)
+    pub fn interpolation_alpha(&self) -> f32 {
+        (self.accumulator / self.settings.timestep).min(1.0)
+    }
+    /// recieves physic commands, updates the rigidbodies by one timestep, sends physic events
     ///
     /// The world state meight be inconsistent if the physic events are intercepted before update_entities
     /// was called!
-    pub fn step(&mut self, world: &ChunkManager, static_blocks: &StaticBlocks, chunks: Query<(&Chunk,)>) {
-        //TODO: interation with moving enitities
+    pub fn step(&mut self, world: &ChunkManager, static_blocks: &StaticBlocks, chunks: &Query<(&Chunk,)>) {
+        let delta = self.settings.timestep;
+        let commands: Vec<PhysicsCommand> = self.connection.get_mut().unwrap().0.try_iter().collect();
+        for command in commands {
+            self.apply_command(command);
+        }
+
+        let mut events = Vec::new();
 
         //Apply force and speed
         //Interaction moving entities and world
         for rigid_body in self.rigid_bodies.iter_mut() {
-            rigid_body.apply_force();
-
-            let mut collider = rigid_body.collider.translated(rigid_body.position);
-
-            for position in collider.contained() {
-                if let Some(block) = world.get(position, &chunks) {
-                    match static_blocks[block.btype as usize].1 {
-                        BlockFeel::Empty => {}
-                        BlockFeel::ColliderSet(colliders) => {
-                            println!("check colliders!");
-                            for block_collider in colliders.iter().map(|c|c.translated(position.lower_corner())) {
-                                let result = collider.impact_volume(block_collider);
-                                if result.x != 0.0 && result.y != 0.0 && result.z != 0.0 {
-                                    println!(" - impact: {}", result);
-                                    restrict_motion(result, rigid_body);
-                                    collider = rigid_body.collider.translated(rigid_body.position);
-                                }
-                            }
-                        }
-                        BlockFeel::Custom => {}
-                    }
+            rigid_body.previous_position = rigid_body.position;
+            rigid_body.apply_force(delta, &self.settings);
+
+            let collider = rigid_body.collider.translated(rigid_body.position);
+            let mut block_colliders = |position: BlockPosition| {
+                let block = world.get(position, chunks)?;
+                Some(match static_blocks[block.btype as usize].1 {
+                    BlockFeel::ColliderSet(colliders) => colliders,
+                    BlockFeel::Empty | BlockFeel::Fluid | BlockFeel::Custom => &[][..],
+                })
+            };
+
+            let mut motion = rigid_body.velocity * delta;
+            if rigid_body.sneaking && rigid_body.grounded && !rigid_body.flying {
+                let kept = keep_on_edges(collider, motion, &mut block_colliders);
+                if kept.x != motion.x {
+                    rigid_body.velocity.x = 0.0;
+                }
+                if kept.z != motion.z {
+                    rigid_body.velocity.z = 0.0;
+                }
+                motion = kept;
+            }
+            let velocity = rigid_body.velocity;
+            let result = move_collider(collider, motion, !rigid_body.flying, &mut block_colliders);
+
+            rigid_body.position += result.motion;
+            if result.grounded && !rigid_body.grounded {
+                events.push(PhysicsEvent::Landed { body: rigid_body.handle, velocity });
+            }
+            rigid_body.grounded = result.grounded;
+
+            let moved = collider.translated(result.motion);
+            let center = moved.lower() + moved.size() / 2.0;
+            for (axis, face) in [
+                (0, Vec3::new(velocity.x.signum() * (moved.size().x / 2.0 + 0.01), 0.0, 0.0)),
+                (1, Vec3::new(0.0, moved.size().y / 2.0 + 0.01, 0.0)),
+                (2, Vec3::new(0.0, 0.0, velocity.z.signum() * (moved.size().z / 2.0 + 0.01))),
+            ].iter() {
+                //landing is reported separately
+                let hit_ceiling = *axis == 1 && velocity.y > 0.0;
+                if result.blocked[*axis] && (*axis != 1 || hit_ceiling) {
+                    events.push(PhysicsEvent::CollidedWithBlock {
+                        body: rigid_body.handle,
+                        block: BlockPosition::from_vector(center + *face),
+                        velocity,
+                    });
+                }
+            }
+            if result.blocked[0] {
+                rigid_body.velocity.x = 0.0;
+            }
+            if result.blocked[1] {
+                rigid_body.velocity.y = 0.0;
+            }
+            if result.blocked[2] {
+                rigid_body.velocity.z = 0.0;
+            }
+
+            let center_block = BlockPosition::from_vector(center);
+            let in_fluid = world.get(center_block, chunks)
+                .map_or(false, |block|matches!(static_blocks[block.btype as usize].1, BlockFeel::Fluid));
+            if in_fluid && !rigid_body.in_fluid {
+                events.push(PhysicsEvent::EnteredFluid { body: rigid_body.handle, block: center_block });
+            } else if !in_fluid && rigid_body.in_fluid {
+                events.push(PhysicsEvent::LeftFluid { body: rigid_body.handle });
+            }
+            rigid_body.in_fluid = in_fluid;
+
+            let out_of_world = rigid_body.position.y < WORLD_BOTTOM;
+            if out_of_world && !rigid_body.out_of_world {
+                events.push(PhysicsEvent::FellOutOfWorld { body: rigid_body.handle });
+            }
+            rigid_body.out_of_world = out_of_world;
+        }
+
+        //Interaction between moving entities
+        let corrections = self.resolve_body_collisions(&mut events);
+        for (index, correction) in corrections {
+            let rigid_body = &mut self.rigid_bodies[index];
+            let mut block_colliders = |position: BlockPosition| {
+                let block = world.get(position, chunks)?;
+                Some(match static_blocks[block.btype as usize].1 {
+                    BlockFeel::ColliderSet(colliders) => colliders,
+                    BlockFeel::Empty | BlockFeel::Fluid | BlockFeel::Custom => &[][..],
+                })
+            };
+            //bodies are never pushed into blocks
+            let result = move_collider(rigid_body.world_collider(), correction, false, &mut block_colliders);
+            rigid_body.position += result.motion;
+        }
+
+        let output = &self.connection.get_mut().unwrap().1;
+        for event in events {
+            //nobody listens, if the receiver was dropped
+            let _ = output.send(event);
+        }
+    }
+
+    /// Separates overlapping bodies according to their inverse masses and reports trigger contacts.
+    /// Returns the position corrections, which still have to be checked against the blocks.
+    fn resolve_body_collisions(&mut self, events: &mut Vec<PhysicsEvent>) -> Vec<(usize, Vec3)> {
+        //Broad phase: only bodies sharing a chunk can touch
+        let mut grid: HashMap<ChunkPosition, Vec<usize>> = HashMap::new();
+        for (index, rigid_body) in self.rigid_bodies.iter().enumerate() {
+            for chunk in rigid_body.chunks() {
+                grid.entry(chunk).or_insert_with(Vec::new).push(index);
+            }
+        }
+        let mut pairs: Vec<(usize, usize)> = grid.values()
+            .flat_map(|indices|{
+                indices.iter().enumerate().flat_map(move|(offset, a)|{
+                    indices[offset + 1..].iter().map(move|b|(*a.min(b), *a.max(b)))
+                })
+            })
+            .collect();
+        //keeps the result independent from the hash map order
+        pairs.sort();
+        pairs.dedup();
+
+        let mut corrections: HashMap<usize, Vec3> = HashMap::new();
+        let mut contacts = HashSet::new();
+
+        //Narrow phase
+        for (a, b) in pairs {
+            let (body_a, body_b) = (&self.rigid_bodies[a], &self.rigid_bodies[b]);
+            let impact = body_a.world_collider().impact_volume(body_b.world_collider());
+            if impact.x == 0.0 || impact.y == 0.0 || impact.z == 0.0 {
+                continue;
+            }
+
+            if body_a.trigger || body_b.trigger {
+                if body_a.trigger && !body_b.trigger {
+                    contacts.insert((body_a.handle, body_b.handle));
+                } else if body_b.trigger && !body_a.trigger {
+                    contacts.insert((body_b.handle, body_a.handle));
+                }
+                continue;
+            }
+            if body_a.ghost || body_b.ghost {
+                continue;
+            }
+
+            let total_inv_mass = body_a.inv_mass + body_b.inv_mass;
+            if total_inv_mass == 0.0 {
+                continue;
+            }
+
+            //separate along the axis with the smallest overlap
+            let abs = impact.abs();
+            let separation = if abs.x < abs.y && abs.x < abs.z {
+                Vec3::new(impact.x, 0.0, 0.0)
+            } else if abs.y < abs.z {
+                Vec3::new(0.0, impact.y, 0.0)
+            } else {
+                Vec3::new(0.0, 0.0, impact.z)
+            };
+            let (inv_a, inv_b) = (body_a.inv_mass / total_inv_mass, body_b.inv_mass / total_inv_mass);
+            *corrections.entry(a).or_insert_with(Vec3::zero) += separation * inv_a;
+            *corrections.entry(b).or_insert_with(Vec3::zero) -= separation * inv_b;
+
+            //inelastic impulse, if the bodies move towards each other
+            let normal = separation.normalize();
+            let approach = (body_a.velocity - body_b.velocity).dot(normal);
+            if approach < 0.0 {
+                let impulse = normal * (-approach / total_inv_mass);
+                let (inv_mass_a, inv_mass_b) = (body_a.inv_mass, body_b.inv_mass);
+                self.rigid_bodies[a].velocity += impulse * inv_mass_a;
+                self.rigid_bodies[b].velocity -= impulse * inv_mass_b;
+            }
+        }
+
+        for (trigger, body) in contacts.difference(&self.trigger_contacts) {
+            events.push(PhysicsEvent::TriggerEntered { trigger: *trigger, body: *body });
+        }
+        for (trigger, body) in self.trigger_contacts.difference(&contacts) {
+            events.push(PhysicsEvent::TriggerLeft { trigger: *trigger, body: *body });
+        }
+        self.trigger_contacts = contacts;
+
+        let mut corrections: Vec<(usize, Vec3)> = corrections.into_iter().collect();
+        corrections.sort_by_key(|(index, _)|*index);
+        corrections
+    }
+
+    fn apply_command(&mut self, command: PhysicsCommand) {
+        match command {
+            PhysicsCommand::SpawnBody { handle, position, inv_mass, flying, collider } => {
+                self.insert_rigid_body(RigidBody::with_handle(handle, position, inv_mass, flying, collider));
+            }
+            PhysicsCommand::SpawnTrigger { handle, position, collider } => {
+                let mut trigger = RigidBody::with_handle(handle, position, 0.0, true, collider);
+                trigger.trigger = true;
+                self.insert_rigid_body(trigger);
+            }
+            PhysicsCommand::RemoveBody(handle) => {
+                self.remove_rigid_body(handle);
+            }
+            PhysicsCommand::ApplyImpulse(handle, impulse) => {
+                if let Some(body) = self.get_mut(handle) {
+                    body.apply_impulse(impulse);
+                }
+            }
+            PhysicsCommand::Teleport(handle, position) => {
+                if let Some(body) = self.get_mut(handle) {
+                    body.teleport(position);
+                }
+            }
+            PhysicsCommand::SetFlying(handle, flying) => {
+                if let Some(body) = self.get_mut(handle) {
+                    body.set_flying(flying);
                 }
             }
         }
     }
 
+    /// moves the entities to their bodies, interpolated between the last two steps
     pub fn update_entities(&mut self, mut objects: Query<(&RigidBodyHandle, &mut Transform)>) {
+        let alpha = self.interpolation_alpha();
         for (handle, mut transform) in objects.iter_mut() {
-            if let Some(rigid_body) = self.get_mut(*handle) {
-                transform.translation = rigid_body.position();
+            if let Some(rigid_body) = self.get(*handle) {
+                transform.translation = rigid_body.interpolated_position(alpha);
             }
         }
     }
     pub fn create_rigid_body(&mut self, position: Vec3, inv_mass: f32, is_flying: bool, collider: AAQuader) -> RigidBodyHandle {
-        let rigid_body = RigidBody::new(position, inv_mass, is_flying, collider);
+        self.insert_rigid_body(RigidBody::new(position, inv_mass, is_flying, collider))
+    }
+    /// a static volume, which reports the bodies entering and leaving it
+    pub fn create_trigger(&mut self, position: Vec3, collider: AAQuader) -> RigidBodyHandle {
+        let mut trigger = RigidBody::new(position, 0.0, true, collider);
+        trigger.trigger = true;
+        self.insert_rigid_body(trigger)
+    }
+    fn insert_rigid_body(&mut self, rigid_body: RigidBody) -> RigidBodyHandle {
         let handle = rigid_body.handle();
         self.rigid_bodies.push(rigid_body);
         self.mapping.insert(handle, self.rigid_bodies.len() - 1);
@@ -159,27 +588,63 @@ impl PhysicsEngine {
             }
         };
     }
+    /// removes all bodies, when the world is closed
+    pub fn clear(&mut self) {
+        self.mapping.clear();
+        self.rigid_bodies.clear();
+        self.trigger_contacts.clear();
+        self.accumulator = 0.0;
+    }
     pub fn get_mut(&mut self, handle: RigidBodyHandle) -> Option<&mut RigidBody> {
         let index = self.mapping.get(&handle).cloned();
         index.and_then(move|index|self.rigid_bodies.get_mut(index))
     }
+    pub fn get(&self, handle: RigidBodyHandle) -> Option<&RigidBody> {
+        self.mapping.get(&handle).and_then(|index|self.rigid_bodies.get(*index))
+    }
 }
 
-fn restrict_motion(impact_volume: Vec3, rigid_body: &mut RigidBody) {
-    let tm = (impact_volume * rigid_body.velocity).abs();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    println!("restrict {}", tm);
+    /// the velocity after a second of frames with the same force
+    fn velocity_after_a_second(frames_per_second: u32) -> Vec3 {
+        let mut settings = PhysicsSettings::default();
+        //exact in binary, so both frame rates run the same number of steps
+        settings.timestep = 1.0 / 64.0;
+        let (mut engine, _, _) = PhysicsEngine::new(settings);
+        let handle = engine.create_rigid_body(Vec3::zero(), 1.0, true, AAQuader::new(Vec3::zero(), Vec3::one()));
+        for _ in 0..frames_per_second {
+            engine.get_mut(handle).unwrap().add_force(Vec3::new(10.0, 0.0, 0.0));
+            engine.advance(1.0 / frames_per_second as f32, |engine|{
+                let (delta, settings) = (engine.settings.timestep, engine.settings.clone());
+                for rigid_body in engine.rigid_bodies.iter_mut() {
+                    rigid_body.apply_force(delta, &settings);
+                }
+            });
+        }
+        engine.get(handle).unwrap().velocity()
+    }
 
-    let impact_translation;
+    #[test]
+    fn forces_dont_depend_on_the_frame_rate() {
+        let slow = velocity_after_a_second(64);
+        let fast = velocity_after_a_second(256);
+        assert!(slow.x > 0.0);
+        assert!((slow - fast).length() < 0.0001, "{} != {}", slow, fast);
+    }
+
+    #[test]
+    fn ghosts_dont_push_bodies() {
+        let (mut engine, _, _) = PhysicsEngine::new(PhysicsSettings::default());
+        let collider = AAQuader::new(Vec3::zero(), Vec3::one());
+        engine.create_rigid_body(Vec3::zero(), 1.0, false, collider);
+        let ghost = engine.create_rigid_body(Vec3::new(0.5, 0.0, 0.0), 1.0, false, collider);
+        engine.get_mut(ghost).unwrap().set_ghost(true);
+        assert!(engine.resolve_body_collisions(&mut Vec::new()).is_empty());
 
-    if tm.x > tm.y && tm.x > tm.z {
-        impact_translation = Vec3::new(impact_volume.x, 0.0, 0.0);
-    } else if tm.y >= tm.z {
-        impact_translation = Vec3::new(0.0, impact_volume.y, 0.0);
-    } else {
-        impact_translation = Vec3::new(0.0, 0.0, impact_volume.z);
+        engine.get_mut(ghost).unwrap().set_ghost(false);
+        assert_eq!(engine.resolve_body_collisions(&mut Vec::new()).len(), 2);
     }
-    rigid_body.position += impact_translation;
-    let impact_normal = impact_translation.normalize();
-    rigid_body.velocity += impact_normal * (impact_normal.dot(rigid_body.velocity)).max(0.0)
-}
\ No newline at end of file
+}
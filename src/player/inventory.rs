@@ -0,0 +1,248 @@
+use bevy::prelude::*;
+use bevy::input::mouse::MouseWheel;
+use serde::{Serialize, Deserialize};
+use crate::world::block_inner::{BlockInner, AIR};
+use crate::world::chunk::ChunkManager;
+use crate::player::player::MouseState;
+use crate::input::actions::{Actions, HOTBAR, HOTBAR_NEXT, HOTBAR_PREVIOUS};
+
+pub const HOTBAR_SIZE: usize = 9;
+pub const INVENTORY_SIZE: usize = 4 * HOTBAR_SIZE;
+pub const MAX_STACK_SIZE: u8 = 64;
+
+/// A number of equal blocks occupying one inventory slot
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ItemStack {
+    pub block: BlockInner,
+    pub count: u8,
+}
+
+impl ItemStack {
+    pub const fn new(block: BlockInner, count: u8) -> Self {
+        ItemStack {
+            block,
+            count,
+        }
+    }
+    pub const fn single(block: BlockInner) -> Self {
+        Self::new(block, 1)
+    }
+    /// blocks with the same type and data can share one stack
+    pub fn stacks_with(&self, other: &ItemStack) -> bool {
+        self.block.btype == other.block.btype && self.block.data == other.block.data
+    }
+}
+
+/// The items of a player. The first `HOTBAR_SIZE` slots are the hotbar.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Inventory {
+    slots: Vec<Option<ItemStack>>,
+    selected: usize,
+}
+
+impl Default for Inventory {
+    fn default() -> Self {
+        Inventory {
+            slots: vec![None; INVENTORY_SIZE],
+            selected: 0,
+        }
+    }
+}
+
+impl Inventory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// repairs a deserialised inventory, the slot count and the selection are clamped and empty stacks removed
+    pub fn validated(mut self) -> Self {
+        self.slots.resize(INVENTORY_SIZE, None);
+        for slot in self.slots.iter_mut() {
+            if let Some(stack) = slot {
+                stack.count = stack.count.min(MAX_STACK_SIZE);
+                if stack.count == 0 || stack.block == AIR {
+                    *slot = None;
+                }
+            }
+        }
+        self.selected = self.selected.min(HOTBAR_SIZE - 1);
+        self
+    }
+    pub fn slots(&self) -> &[Option<ItemStack>] {
+        &self.slots
+    }
+    pub fn hotbar(&self) -> &[Option<ItemStack>] {
+        &self.slots[..HOTBAR_SIZE]
+    }
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+    pub fn selected_stack(&self) -> Option<&ItemStack> {
+        self.slots[self.selected].as_ref()
+    }
+    pub fn select(&mut self, slot: usize) {
+        if slot < HOTBAR_SIZE {
+            self.selected = slot;
+        }
+    }
+    /// moves the selection by `delta` slots, wrapping around the hotbar
+    pub fn scroll(&mut self, delta: i64) {
+        let size = HOTBAR_SIZE as i64;
+        self.selected = (((self.selected as i64 + delta) % size + size) % size) as usize;
+    }
+    /// adds the stack to the inventory, existing stacks are filled first.
+    /// Returns what didnt fit.
+    pub fn add(&mut self, mut stack: ItemStack) -> Option<ItemStack> {
+        if stack.block == AIR {
+            return None;
+        }
+        for slot in self.slots.iter_mut().filter_map(|slot|slot.as_mut()) {
+            if slot.stacks_with(&stack) && slot.count < MAX_STACK_SIZE {
+                let moved = stack.count.min(MAX_STACK_SIZE - slot.count);
+                slot.count += moved;
+                stack.count -= moved;
+                if stack.count == 0 {
+                    return None;
+                }
+            }
+        }
+        for slot in self.slots.iter_mut().filter(|slot|slot.is_none()) {
+            let moved = stack.count.min(MAX_STACK_SIZE);
+            *slot = Some(ItemStack::new(stack.block, moved));
+            stack.count -= moved;
+            if stack.count == 0 {
+                return None;
+            }
+        }
+        Some(stack)
+    }
+    /// removes a single block from the selected stack
+    pub fn take_selected(&mut self) -> Option<BlockInner> {
+        let slot = &mut self.slots[self.selected];
+        let stack = slot.as_mut()?;
+        let block = stack.block;
+        stack.count -= 1;
+        if stack.count == 0 {
+            *slot = None;
+        }
+        Some(block)
+    }
+}
+
+pub fn hotbar_selection_system(
+    actions: Res<Actions>,
+    mut state: ResMut<MouseState>,
+    mouse_wheel_events: Res<Events<MouseWheel>>,
+    mut query: Query<&mut Inventory>,
+) {
+    let mut scroll = 0.0;
+    for event in state.mouse_wheel_event_reader.iter(&mouse_wheel_events) {
+        scroll += event.y;
+    }
+    if actions.just_pressed(HOTBAR_NEXT) {
+        scroll -= 1.0;
+    }
+    if actions.just_pressed(HOTBAR_PREVIOUS) {
+        scroll += 1.0;
+    }
+    let key = HOTBAR.iter().position(|action|actions.just_pressed(action));
+
+    for mut inventory in query.iter_mut() {
+        if let Some(slot) = key {
+            inventory.select(slot);
+        }
+        if scroll != 0.0 {
+            //scrolling up moves to the left
+            inventory.scroll(-scroll.signum() as i64);
+        }
+    }
+}
+
+/// hands changed inventories to the provider, so they survive a save and load of the world
+pub fn save_inventory(
+    mut chunk_manager: ResMut<ChunkManager>,
+    query: Query<&Inventory, Changed<Inventory>>,
+) {
+    for inventory in query.iter() {
+        chunk_manager.provider_mut().save_inventory(inventory);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::block_inner::{DIRT, STONE};
+
+    #[test]
+    fn stacks_fill_up_to_the_maximum() {
+        let mut inventory = Inventory::new();
+        assert_eq!(inventory.add(ItemStack::new(DIRT, 100)), None);
+        assert_eq!(inventory.slots()[0], Some(ItemStack::new(DIRT, MAX_STACK_SIZE)));
+        assert_eq!(inventory.slots()[1], Some(ItemStack::new(DIRT, 100 - MAX_STACK_SIZE)));
+        assert_eq!(inventory.add(ItemStack::single(AIR)), None);
+        assert_eq!(inventory.slots()[2], None);
+    }
+
+    #[test]
+    fn existing_stacks_are_filled_first() {
+        let mut inventory = Inventory::new();
+        inventory.add(ItemStack::new(STONE, 10));
+        inventory.add(ItemStack::new(DIRT, 10));
+        inventory.add(ItemStack::new(STONE, 5));
+        assert_eq!(inventory.slots()[0], Some(ItemStack::new(STONE, 15)));
+        assert_eq!(inventory.slots()[1], Some(ItemStack::new(DIRT, 10)));
+        assert_eq!(inventory.slots()[2], None);
+
+        //different data doesnt stack
+        let mut other = STONE;
+        other.data = 1;
+        inventory.add(ItemStack::single(other));
+        assert_eq!(inventory.slots()[2], Some(ItemStack::single(other)));
+    }
+
+    #[test]
+    fn full_inventories_return_the_rest() {
+        let mut inventory = Inventory::new();
+        let capacity = INVENTORY_SIZE as u32 * MAX_STACK_SIZE as u32;
+        for _ in 0..capacity / 200 {
+            assert_eq!(inventory.add(ItemStack::new(DIRT, 200)), None);
+        }
+        let left = (capacity % 200) as u8;
+        assert_eq!(inventory.add(ItemStack::new(DIRT, left + 3)), Some(ItemStack::new(DIRT, 3)));
+    }
+
+    #[test]
+    fn taking_empties_the_selected_slot() {
+        let mut inventory = Inventory::new();
+        assert_eq!(inventory.take_selected(), None);
+        inventory.add(ItemStack::new(STONE, 2));
+        assert_eq!(inventory.take_selected(), Some(STONE));
+        assert_eq!(inventory.selected_stack(), Some(&ItemStack::single(STONE)));
+        assert_eq!(inventory.take_selected(), Some(STONE));
+        assert_eq!(inventory.selected_stack(), None);
+        assert_eq!(inventory.take_selected(), None);
+    }
+
+    #[test]
+    fn selection_wraps_around_the_hotbar() {
+        let mut inventory = Inventory::new();
+        inventory.scroll(-1);
+        assert_eq!(inventory.selected(), HOTBAR_SIZE - 1);
+        inventory.scroll(2);
+        assert_eq!(inventory.selected(), 1);
+        inventory.select(HOTBAR_SIZE);
+        assert_eq!(inventory.selected(), 1);
+    }
+
+    #[test]
+    fn loaded_inventories_are_validated() {
+        let mut inventory = Inventory::new();
+        inventory.slots = vec![Some(ItemStack::new(DIRT, 0)), Some(ItemStack::new(STONE, 200))];
+        inventory.selected = 50;
+        let inventory = inventory.validated();
+        assert_eq!(inventory.slots().len(), INVENTORY_SIZE);
+        assert_eq!(inventory.slots()[0], None);
+        assert_eq!(inventory.slots()[1], Some(ItemStack::new(STONE, MAX_STACK_SIZE)));
+        assert_eq!(inventory.selected(), HOTBAR_SIZE - 1);
+        assert_eq!(inventory.selected_stack(), None);
+    }
+}
@@ -0,0 +1,147 @@
+use bevy::prelude::*;
+use crate::world::chunk::{Chunk, ChunkManager};
+use crate::world::block_inner::{AIR, Sides};
+use crate::world::block_types::StaticBlocksRes;
+use crate::world::chunk_mesh::{BevyChunkMeshBuilder, ChunkMeshBuilder, ChunkMesh, add_cube};
+use crate::world::coordinates::BlockPosition;
+use crate::player::inventory::ItemStack;
+use crate::entities::item::DropItem;
+use crate::input::actions::{Actions, BREAK};
+use crate::player::target::Targeting;
+
+pub const CRACK_STAGES: u32 = 10;
+/// the atlas index of the first crack stage, the other stages follow in the same row
+const CRACK_TEXTURE: u32 = 240;
+/// the overlay is slightly larger than the block to avoid z-fighting
+const CRACK_SCALE: f32 = 1.01;
+
+/// The block the player is currently breaking
+#[derive(Default)]
+pub struct Mining {
+    target: Option<BlockPosition>,
+    /// the fraction of the block which is already broken
+    progress: f32,
+}
+
+impl Mining {
+    pub fn target(&self) -> Option<BlockPosition> {
+        self.target
+    }
+    pub fn progress(&self) -> f32 {
+        self.progress
+    }
+    /// the crack stage to display, None if nothing is broken yet
+    pub fn stage(&self) -> Option<u32> {
+        if self.target.is_some() && self.progress > 0.0 {
+            Some(((self.progress * CRACK_STAGES as f32) as u32).min(CRACK_STAGES - 1))
+        } else {
+            None
+        }
+    }
+    fn reset(&mut self, target: Option<BlockPosition>) {
+        self.target = target;
+        self.progress = 0.0;
+    }
+}
+
+pub struct CrackOverlay;
+
+/// one mesh per crack stage
+pub struct CrackMeshes(Vec<Handle<Mesh>>);
+
+impl CrackMeshes {
+    pub fn new(meshes: &mut Assets<Mesh>) -> Self {
+        CrackMeshes((0..CRACK_STAGES).map(|stage|{
+            let mut builder = BevyChunkMeshBuilder::empty();
+            add_cube(&mut builder, &Sides::filled(CRACK_TEXTURE + stage), Vec3::splat(-0.5));
+            meshes.add(Mesh::from_builder(builder).unwrap())
+        }).collect())
+    }
+}
+
+pub fn spawn_crack_overlay(commands: &mut Commands, material: Handle<StandardMaterial>) {
+    commands
+        .spawn(PbrBundle {
+            material,
+            visible: Visible {
+                is_visible: false,
+                is_transparent: true,
+            },
+            ..Default::default()
+        })
+        .with(CrackOverlay);
+}
+
+pub fn mining_system(
+    time: Res<Time>,
+    actions: Res<Actions>,
+    static_blocks: Res<StaticBlocksRes>,
+    mut chunk_manager: ResMut<ChunkManager>,
+    mut drops: ResMut<Events<DropItem>>,
+    mut player: Query<(&Targeting, &mut Mining)>,
+    mut chunks: Query<(&mut Chunk,)>,
+) {
+    for (targeting, mut mining) in player.iter_mut() {
+        if !actions.pressed(BREAK) {
+            mining.reset(None);
+            continue;
+        }
+
+        let target = targeting.hit().map(|hit|hit.position);
+
+        if target != mining.target {
+            mining.reset(target);
+        }
+
+        let position = match target {
+            Some(position) => position,
+            None => continue,
+        };
+        let block = match chunk_manager.get_with_mut(position, &mut chunks) {
+            Some(block) => *block,
+            None => continue,
+        };
+
+        match static_blocks[block.btype as usize].2.hardness() {
+            Some(hardness) if hardness > 0.0 => {
+                mining.progress += time.delta_seconds() / hardness;
+            }
+            Some(_) => {
+                mining.progress = 1.0;
+            }
+            //unbreakable
+            None => continue,
+        }
+
+        if mining.progress >= 1.0 {
+            chunk_manager.set(position, AIR, &mut chunks);
+            drops.send(DropItem {
+                stack: ItemStack::single(block),
+                position: position.block_center(),
+                velocity: Vec3::new(0.0, 3.0, 0.0),
+            });
+            mining.reset(None);
+        }
+    }
+}
+
+pub fn crack_overlay_system(
+    crack_meshes: Res<CrackMeshes>,
+    player: Query<&Mining>,
+    mut overlay: Query<(&CrackOverlay, &mut Transform, &mut Handle<Mesh>, &mut Visible)>,
+) {
+    let current = player.iter()
+        .filter_map(|mining|Some((mining.target()?, mining.stage()?)))
+        .next();
+
+    for (_, mut transform, mut mesh, mut visible) in overlay.iter_mut() {
+        if let Some((position, stage)) = current {
+            *transform = Transform::from_translation(position.block_center());
+            transform.scale = Vec3::splat(CRACK_SCALE);
+            *mesh = crack_meshes.0[stage as usize].clone();
+            visible.is_visible = true;
+        } else {
+            visible.is_visible = false;
+        }
+    }
+}
@@ -1,63 +1,106 @@
 use bevy::prelude::*;
 use player::{PlayerMovement, camera_movement_system, mouse_motion_system, MouseState};
 use crate::settings::Settings;
-use crate::util::{print_fps, FPS};
+use crate::util::{update_fps, FPS};
 use crate::player::player::player_interact;
 use crate::physics::rigid_body::PhysicsEngine;
 use crate::physics::collider::AAQuader;
+use crate::player::inventory::{Inventory, hotbar_selection_system, save_inventory};
+use crate::world::chunk::ChunkManager;
+use crate::player::mining::{Mining, CrackMeshes, mining_system, crack_overlay_system, spawn_crack_overlay};
+use crate::player::target::{Targeting, targeting_system, block_highlight_system, spawn_block_highlight};
+use crate::state::{AppState, APP_STATE_STAGE, GAME_UPDATE};
+use crate::player::survival::{Health, Fall, SpawnPoint, game_mode_system, apply_game_mode, fall_damage_system, drowning_system, respawn_system};
+
+/// the height of the camera above the feet of the player
+pub const EYE_HEIGHT: f32 = 1.5;
 
 pub mod player;
+pub mod inventory;
+pub mod mining;
+pub mod target;
+pub mod survival;
 
-pub fn init_player(builder: &mut AppBuilder, settings: &Settings) {
-    builder.add_system(camera_movement_system.system())
-        .add_system(mouse_motion_system.system())
-        .add_startup_system(setup.system())
-        .add_system(player_interact.system());
+/// the collider of a standing player, relative to the eyes
+pub const PLAYER_COLLIDER: AAQuader = AAQuader::new(Vec3{x: -0.4, y: -EYE_HEIGHT, z: -0.4}, Vec3{x: 0.4, y: 0.3, z: 0.4});
+/// sneaking players are lower
+pub const SNEAKING_COLLIDER: AAQuader = AAQuader::new(Vec3{x: -0.4, y: -EYE_HEIGHT, z: -0.4}, Vec3{x: 0.4, y: 0.0, z: 0.4});
 
-    if settings.game_settings.print_fps {
-        builder.add_system(print_fps.system());
-    }
+/// the position new players start at
+const SPAWN_POSITION: Vec3 = Vec3{x: -4.0, y: 24.0, z: -4.0};
+
+pub fn init_player(builder: &mut AppBuilder, settings: &Settings) {
+    builder.add_system_to_stage(GAME_UPDATE, camera_movement_system.system())
+        .add_system_to_stage(GAME_UPDATE, mouse_motion_system.system())
+        .on_state_enter(APP_STATE_STAGE, AppState::Loading, setup.system())
+        .add_system_to_stage(GAME_UPDATE, player_interact.system())
+        .add_system_to_stage(GAME_UPDATE, hotbar_selection_system.system())
+        .add_system_to_stage(GAME_UPDATE, save_inventory.system())
+        .add_system_to_stage(GAME_UPDATE, mining_system.system())
+        .add_system_to_stage(GAME_UPDATE, crack_overlay_system.system())
+        .add_system_to_stage(GAME_UPDATE, targeting_system.system())
+        .add_system_to_stage(GAME_UPDATE, block_highlight_system.system())
+        .add_system_to_stage(GAME_UPDATE, game_mode_system.system())
+        .add_system_to_stage(GAME_UPDATE, apply_game_mode.system())
+        .add_system_to_stage(GAME_UPDATE, fall_damage_system.system())
+        .add_system_to_stage(GAME_UPDATE, drowning_system.system())
+        .add_system_to_stage(GAME_UPDATE, respawn_system.system())
+        .add_resource(SpawnPoint(SPAWN_POSITION))
+        .add_resource(FPS::default())
+        .add_system(update_fps.system());
 }
 
 /// set up a simple scene with a "parent" cube and a "child" cube
 //TODO: mut 'commands: Commands' only works on 0.3 (change to 'commands: &mut Commands' otherwise)!
 fn setup(
     commands: &mut Commands,
+    settings: Res<Settings>,
     mut physics: ResMut<PhysicsEngine>,
-    meshes: ResMut<Assets<Mesh>>,
+    mut chunk_manager: ResMut<ChunkManager>,
+    server: Res<AssetServer>,
+    mut meshes: ResMut<Assets<Mesh>>,
     textures: ResMut<Assets<Texture>>,
-    materials: ResMut<Assets<StandardMaterial>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
 ) {
+    let game_mode = settings.game_settings.game_mode;
     let handle = physics.create_rigid_body(
-        Vec3::new(-4.0, 24.0, -4.0),
+        SPAWN_POSITION,
         1.0,
-        false,
-        AAQuader::new(Vec3::new(-0.4, -1.5, -0.4), Vec3::new(0.4, 0.3, 0.4))
+        game_mode.is_flying(),
+        PLAYER_COLLIDER,
     );
 
+    let inventory = chunk_manager.provider_mut()
+        .load_inventory()
+        .unwrap_or_default();
+
+    let overlay_material = materials.add(StandardMaterial {
+        albedo: Default::default(),
+        albedo_texture: Some(server.load(chunk_manager.texture_path())),
+        shaded: true,
+    });
+    spawn_crack_overlay(commands, overlay_material);
+    spawn_block_highlight(commands, &mut meshes, &mut materials);
+
     commands
+        .insert_resource(CrackMeshes::new(&mut meshes))
         .insert_resource(MouseState::default())
-        .insert_resource(FPS::default())
-        // light
-        .spawn(LightBundle {
-            transform: Transform::from_translation(Vec3::new(-2.0, 16.0, -1.0)),
-            ..Default::default()
-        })
         // camera
         .spawn(Camera3dBundle {
-            transform: Transform::from_translation(Vec3::new(-4.0, 24.0, -4.0))
+            transform: Transform::from_translation(SPAWN_POSITION)
                 .looking_at(Vec3::default(), Vec3::unit_y()),
             ..Default::default()
         })
         .with_bundle((
-            PlayerMovement::new(false),
-            Light{
-                color: Color::WHITE,
-                fov: 120.0f32.to_radians(),
-                depth: 1.0..100.0
-            },
+            PlayerMovement::new(),
             GlobalTransform::default(),
             handle,
+            inventory,
+            Mining::default(),
+            Targeting::new(settings.game_settings.reach),
+            game_mode,
+            Health::default(),
+            Fall::default(),
         ));
 }
 
@@ -71,7 +114,7 @@ impl PlayerBundle {
     pub fn new(transform: Transform) -> Self {
         PlayerBundle {
             transform,
-            movement: PlayerMovement::new(false),
+            movement: PlayerMovement::new(),
         }
     }
 }
@@ -1,18 +1,25 @@
 use bevy::prelude::*;
-use bevy::input::mouse::{MouseMotion, MouseButtonInputState};
+use bevy::input::mouse::{MouseMotion, MouseButtonInputState, MouseWheel};
 use crate::world::chunk::{Chunk, ChunkManager};
-use crate::world::block_inner::{AIR, WOOD};
+use crate::world::block_inner::AIR;
+use crate::world::block_types::{StaticBlocksRes, BlockFeel};
 use crate::world::coordinates::BlockPosition;
-use crate::physics::ray::Ray;
 use crate::physics::rigid_body::{PhysicsEngine, RigidBodyHandle};
+use crate::physics::collision::fits;
+use crate::player::inventory::Inventory;
+use crate::player::target::Targeting;
+use crate::player::{PLAYER_COLLIDER, SNEAKING_COLLIDER};
+use crate::input::actions::{Actions, FORWARD, BACKWARD, LEFT, RIGHT, JUMP, SNEAK, SPRINT, PLACE, LOOK_LEFT, LOOK_RIGHT, LOOK_UP, LOOK_DOWN};
+use crate::settings::Settings;
+
+/// pressing forward twice within this time (in seconds) starts sprinting
+const DOUBLE_TAP_TIME: f32 = 0.3;
 
 pub struct PlayerMovement {
-    /// The speed the FlyCamera moves at. Defaults to `1.0`
+    /// The acceleration of the player in blocks per second squared. Defaults to `60.0`
     pub speed: f32,
     /// The maximum speed the FlyCamera can move at. Defaults to `0.5`
     pub max_speed: f32,
-    /// The sensitivity of the FlyCamera's motion based on mouse movement. Defaults to `3.0`
-    pub sensitivity: f32,
     /// The amount of deceleration to apply to the camera's motion. Defaults to `1.0`
     pub friction: f32,
     /// The current pitch of the FlyCamera in degrees. This value is always up-to-date, enforced by [FlyCameraPlugin](struct.FlyCameraPlugin.html)
@@ -21,49 +28,46 @@ pub struct PlayerMovement {
     pub yaw: f32,
     /// The current velocity of the FlyCamera. This value is always up-to-date, enforced by [FlyCameraPlugin](struct.FlyCameraPlugin.html)
     pub velocity: Vec3,
-    /// Key used to move forward. Defaults to `W`
-    pub key_forward: KeyCode,
-    /// Key used to move backward. Defaults to `S
-    pub key_backward: KeyCode,
-    /// Key used to move left. Defaults to `A`
-    pub key_left: KeyCode,
-    /// Key used to move right. Defaults to `D`
-    pub key_right: KeyCode,
-    /// Key used to move up. Defaults to `Space`
-    pub key_up: KeyCode,
-    /// Key used to move forward. Defaults to `LShift`
-    pub key_down: KeyCode,
+    /// The upwards velocity of a jump in blocks per second. Defaults to `9.0`
+    pub jump_speed: f32,
+    /// The factor applied to the speed while sprinting. Defaults to `1.6`
+    pub sprint_factor: f32,
+    /// The factor applied to the speed while sneaking. Defaults to `0.3`
+    pub sneak_factor: f32,
+    pub sprinting: bool,
+    pub sneaking: bool,
+    /// The time since the forward key was last pressed, used to detect a double tap
+    pub since_forward: f32,
     /// If `false`, disable keyboard control of the camera. Defaults to `true`
     pub enabled: bool,
-
-    pub print_position: bool,
-
-    pub frames: u64,
 }
 
 impl PlayerMovement {
-    pub fn new(print_position: bool) -> Self {
+    pub fn new() -> Self {
         PlayerMovement {
-            speed: 7.0,
+            speed: 60.0,
             max_speed: 8.0,
-            sensitivity: 8.0,
             friction: 2.2,
             pitch: 0.0,
             yaw: 0.0,
             velocity: Vec3::zero(),
-            key_forward: KeyCode::W,
-            key_backward: KeyCode::S,
-            key_left: KeyCode::A,
-            key_right: KeyCode::D,
-            key_up: KeyCode::Space,
-            key_down: KeyCode::LShift,
+            jump_speed: 9.0,
+            sprint_factor: 1.6,
+            sneak_factor: 0.3,
+            sprinting: false,
+            sneaking: false,
+            since_forward: f32::INFINITY,
             enabled: true,
-            print_position,
-            frames: 0,
         }
     }
 }
 
+impl Default for PlayerMovement {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 fn forward_vector(rotation: &Quat) -> Vec3 {
     rotation.mul_vec3(Vec3::unit_z()).normalize()
 }
@@ -81,97 +85,99 @@ fn strafe_vector(rotation: &Quat) -> Vec3 {
         .normalize()
 }
 
-fn movement_axis(
-    input: &Res<Input<KeyCode>>,
-    plus: KeyCode,
-    minus: KeyCode,
-) -> f32 {
-    let mut axis = 0.0;
-    if input.pressed(plus) {
-        axis += 1.0;
-    }
-    if input.pressed(minus) {
-        axis -= 1.0;
-    }
-    axis
-}
-
 pub fn camera_movement_system(
     time: Res<Time>,
-    keyboard_input: Res<Input<KeyCode>>,
+    actions: Res<Actions>,
     mut physics: ResMut<PhysicsEngine>,
+    chunk_manager: Res<ChunkManager>,
+    static_blocks: Res<StaticBlocksRes>,
+    chunks: Query<(&Chunk,)>,
     mut query: Query<(&mut PlayerMovement, &mut Transform, &RigidBodyHandle)>,
 ) {
+    let mut block_colliders = |position: BlockPosition| {
+        let block = chunk_manager.get(position, &chunks)?;
+        Some(match static_blocks[block.btype as usize].1 {
+            BlockFeel::ColliderSet(colliders) => colliders,
+            BlockFeel::Empty | BlockFeel::Fluid | BlockFeel::Custom => &[][..],
+        })
+    };
     for (mut options, mut transform, handle) in query.iter_mut() {
+        let body = match physics.get_mut(*handle) {
+            Some(body) => body,
+            None => continue,
+        };
         let (axis_h, axis_v, axis_float) = if options.enabled {
             (
-
-                movement_axis(&keyboard_input, options.key_right, options.key_left),
-                movement_axis(
-                    &keyboard_input,
-                    options.key_backward,
-                    options.key_forward,
-                ),
-                movement_axis(&keyboard_input, options.key_up, options.key_down),
+                actions.axis(RIGHT, LEFT),
+                actions.axis(BACKWARD, FORWARD),
+                actions.axis(JUMP, SNEAK),
             )
         } else {
             (0.0, 0.0, 0.0)
         };
 
+        let flying = body.is_flying();
+        let forward = options.enabled && actions.pressed(FORWARD);
+        options.since_forward += time.delta_seconds();
+        if options.enabled && actions.just_pressed(FORWARD) {
+            if options.since_forward < DOUBLE_TAP_TIME {
+                options.sprinting = true;
+            }
+            options.since_forward = 0.0;
+        }
+        let sneaking = !flying && options.enabled && actions.pressed(SNEAK);
+        //the player keeps sneaking below low ceilings, until there is room to stand up
+        let sneaking = sneaking || options.sneaking && !fits(PLAYER_COLLIDER.translated(body.position()), &mut block_colliders);
+        if options.enabled && actions.pressed(SPRINT) {
+            options.sprinting = true;
+        }
+        if !forward || sneaking {
+            options.sprinting = false;
+        }
+        if sneaking != options.sneaking {
+            options.sneaking = sneaking;
+            body.set_sneaking(sneaking);
+            body.set_collider(if sneaking { SNEAKING_COLLIDER } else { PLAYER_COLLIDER });
+        }
+
         let rotation = transform.rotation;
-        let accel: Vec3 = (strafe_vector(&rotation) * axis_h)
-            + (forward_walk_vector(&rotation) * axis_v)
-            + (Vec3::unit_y() * axis_float);
-        let accel: Vec3 = if accel.length() != 0.0 {
-            accel.normalize() * options.speed
+        let walk: Vec3 = (strafe_vector(&rotation) * axis_h)
+            + (forward_walk_vector(&rotation) * axis_v);
+        //walking bodies only move vertically by jumping
+        let accel: Vec3 = if flying { walk + Vec3::unit_y() * axis_float } else { walk };
+        let speed = if options.sprinting {
+            options.speed * options.sprint_factor
+        } else if options.sneaking {
+            options.speed * options.sneak_factor
         } else {
-            Vec3::zero()
+            options.speed
         };
-
-        let friction: Vec3 = if options.velocity.length() != 0.0 {
-            options.velocity.normalize() * -1.0 * options.friction
+        let accel: Vec3 = if accel.length() != 0.0 {
+            accel.normalize() * speed
         } else {
             Vec3::zero()
         };
 
-        options.frames += 1;
-        if options.frames % 30 == 0 && options.print_position {
-            println!("position: {}", transform.translation);
+        body.add_force(accel);
+        //the velocity check avoids jumping twice, if no physics step happened since the last jump
+        let jump = options.enabled && actions.pressed(JUMP);
+        if !flying && jump && body.is_grounded() && body.velocity().y <= 0.0 {
+            body.apply_impulse(Vec3::new(0.0, options.jump_speed, 0.0));
         }
-
-        physics.get_mut(*handle).unwrap().add_force(accel * time.delta_seconds());
-        /*
-        options.velocity += accel * time.delta_seconds();
-
-        // clamp within max speed
-        if options.velocity.length() > options.max_speed {
-            options.velocity = options.velocity.normalize() * options.max_speed;
-        }
-
-        let delta_friction = friction * time.delta_seconds();
-
-        options.velocity = if (options.velocity + delta_friction).signum()
-            != options.velocity.signum()
-        {
-            Vec3::zero()
-        } else {
-            options.velocity + delta_friction
-        };
-
-        transform.translation += options.velocity;
-
-         */
     }
 }
 
 #[derive(Default)]
 pub struct MouseState {
     mouse_motion_event_reader: EventReader<MouseMotion>,
+    pub(crate) mouse_wheel_event_reader: EventReader<MouseWheel>,
     press: MouseButtonInputState
 }
 
 pub fn mouse_motion_system(
     time: Res<Time>,
+    settings: Res<Settings>,
+    actions: Res<Actions>,
     mut state: ResMut<MouseState>,
     mouse_motion_events: Res<Events<MouseMotion>>,
     mut query: Query<(&mut PlayerMovement, &mut Transform)>,
@@ -180,8 +186,11 @@ pub fn mouse_motion_system(
     for event in state.mouse_motion_event_reader.iter(&mouse_motion_events) {
         delta += event.delta;
     }
+    //the stick turns with a constant speed, independent of the mouse sensitivity
+    let stick = Vec2::new(actions.axis(LOOK_RIGHT, LOOK_LEFT), actions.axis(LOOK_UP, LOOK_DOWN))
+        * settings.controls.gamepad_look_sensitivity * time.delta_seconds();
 
-    if delta == Vec2::zero() {
+    if delta == Vec2::zero() && stick == Vec2::zero() {
         return;
     }
 
@@ -191,8 +200,10 @@ pub fn mouse_motion_system(
         }
 
 
-        options.yaw -= delta.x * options.sensitivity * time.delta_seconds();
-        options.pitch += delta.y * options.sensitivity * time.delta_seconds();
+        options.yaw -= delta.x * settings.controls.mouse_sensitivity * time.delta_seconds();
+        options.pitch += delta.y * settings.controls.mouse_sensitivity * time.delta_seconds();
+        options.yaw -= stick.x;
+        options.pitch -= stick.y;
 
         if options.pitch > 89.9 {
             options.pitch = 89.9;
@@ -200,7 +211,6 @@ pub fn mouse_motion_system(
         if options.pitch < -89.9 {
             options.pitch = -89.9;
         }
-        // println!("pitch: {}, yaw: {}", options.pitch, options.yaw);
 
         let yaw_radians = options.yaw.to_radians();
         let pitch_radians = options.pitch.to_radians();
@@ -211,45 +221,23 @@ pub fn mouse_motion_system(
 }
 
 pub fn player_interact(
-    mouse_motion_events: Res<Input<MouseButton>>,
+    actions: Res<Actions>,
     mut chunk_manager: ResMut<ChunkManager>,
-    player: Query<(&PlayerMovement, &Transform)>,
+    mut player: Query<(&PlayerMovement, &Targeting, &mut Inventory)>,
     mut chunks: Query<(&mut Chunk,)>
 ) {
-    if mouse_motion_events.just_released(MouseButton::Left) {
-        for (player, transform) in player.iter() {
-            let ray = Ray::from_global_transform(transform);
-
-            let block = ray.grid_snap().take(100)
-                .filter_map(|position|{
-                    if chunk_manager.get_with_mut(position, &mut chunks)? != &AIR {
-                        Some(position)
-                    } else {
-                        None
-                    }
-                }).next();
-            if let Some(block) = block {
-                println!("update chunk!");
-                chunk_manager.set(block, AIR, &mut chunks);
+    if actions.just_released(PLACE) {
+        for (player, targeting, mut inventory) in player.iter_mut() {
+            let position = match targeting.hit() {
+                Some(hit) => hit.adjacent(),
+                None => continue,
+            };
+            if chunk_manager.get_with_mut(position, &mut chunks) != Some(&AIR) {
+                continue;
             }
-        }
-    }
-    if mouse_motion_events.just_released(MouseButton::Right) {
-        for (player, transform) in player.iter() {
-            let ray = Ray::from_global_transform(transform);
-
-            let block = ray.grid_snap().take(100)
-                .fold((BlockPosition::from_vector(transform.translation), false), |(old, set), position|{
-                    if set || chunk_manager.get_with_mut(position, &mut chunks) != Some(&AIR) {
-                        (old, true)
-                    } else {
-                        (position, false)
-                    }
-                });
-            if block.1 {
-                println!("update chunk!");
-                chunk_manager.set(block.0, WOOD, &mut chunks);
+            if let Some(placed) = inventory.take_selected() {
+                chunk_manager.set(position, placed, &mut chunks);
             }
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,243 @@
+use bevy::prelude::*;
+use serde::{Serialize, Deserialize};
+use crate::physics::rigid_body::{PhysicsEngine, PhysicsEvent, PhysicsCommands, PhysicsCommand, RigidBodyHandle};
+use crate::world::block_types::{StaticBlocksRes, BlockFeel};
+use crate::world::chunk::{Chunk, ChunkManager};
+use crate::world::coordinates::BlockPosition;
+use crate::input::actions::{Actions, TOGGLE_GAME_MODE};
+
+pub const MAX_HEALTH: f32 = 20.0;
+/// the time in seconds the player can stay under water without taking damage
+pub const MAX_AIR: f32 = 10.0;
+/// falls up to this height (in blocks) are harmless, every further block costs one health point
+const SAFE_FALL_HEIGHT: f32 = 3.0;
+/// the damage per second while drowning
+const DROWNING_DAMAGE: f32 = 2.0;
+/// the air regained per second above the water
+const AIR_REGENERATION: f32 = 5.0;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub enum GameMode {
+    /// the player takes damage and walks
+    Survival,
+    /// the player is invulnerable and flies
+    Creative,
+}
+
+impl Default for GameMode {
+    fn default() -> Self {
+        GameMode::Survival
+    }
+}
+
+impl GameMode {
+    pub fn is_flying(&self) -> bool {
+        *self == GameMode::Creative
+    }
+}
+
+/// The health and the remaining air of the player
+#[derive(Copy, Clone, Debug)]
+pub struct Health {
+    current: f32,
+    air: f32,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Health {
+            current: MAX_HEALTH,
+            air: MAX_AIR,
+        }
+    }
+}
+
+impl Health {
+    pub fn current(&self) -> f32 {
+        self.current
+    }
+    pub fn air(&self) -> f32 {
+        self.air
+    }
+    pub fn damage(&mut self, amount: f32) {
+        self.current = (self.current - amount).max(0.0);
+    }
+    pub fn heal(&mut self, amount: f32) {
+        self.current = (self.current + amount).min(MAX_HEALTH);
+    }
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0.0
+    }
+}
+
+/// The position the player respawns at after dying
+pub struct SpawnPoint(pub Vec3);
+
+/// The highest point of the player since leaving the ground.
+///
+/// The height is tracked instead of derived from the landing velocity, as the air drag limits the falling speed.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct Fall {
+    highest: Option<f32>,
+}
+
+impl Fall {
+    /// follows the height of the body, returns the height of the fall when the body lands.
+    /// Flying or swimming bodies dont fall, the fall starts again when they stop.
+    pub fn update(&mut self, y: f32, grounded: bool, cushioned: bool) -> Option<f32> {
+        if cushioned {
+            self.highest = None;
+            None
+        } else if grounded {
+            self.highest.take().map(|highest|highest - y)
+        } else {
+            self.highest = Some(self.highest.map_or(y, |highest|highest.max(y)));
+            None
+        }
+    }
+}
+
+/// the damage of a fall of the given height
+pub fn fall_damage(height: f32) -> f32 {
+    (height - SAFE_FALL_HEIGHT).ceil().max(0.0)
+}
+
+/// switches between survival and creative mode
+pub fn game_mode_system(actions: Res<Actions>, mut players: Query<&mut GameMode>) {
+    for mut game_mode in players.iter_mut() {
+        if actions.just_pressed(TOGGLE_GAME_MODE) {
+            *game_mode = match *game_mode {
+                GameMode::Survival => GameMode::Creative,
+                GameMode::Creative => GameMode::Survival,
+            };
+        }
+    }
+}
+
+/// applies changed game modes to the rigid bodies, this also covers modes set by other systems
+pub fn apply_game_mode(
+    physics_commands: Res<PhysicsCommands>,
+    players: Query<(&GameMode, &RigidBodyHandle), Changed<GameMode>>,
+) {
+    for (game_mode, handle) in players.iter() {
+        physics_commands.send(PhysicsCommand::SetFlying(*handle, game_mode.is_flying()));
+    }
+}
+
+pub fn fall_damage_system(
+    mut reader: Local<EventReader<PhysicsEvent>>,
+    events: Res<Events<PhysicsEvent>>,
+    physics: Res<PhysicsEngine>,
+    mut players: Query<(&GameMode, &RigidBodyHandle, &mut Fall, &mut Health)>,
+) {
+    for (game_mode, handle, mut fall, mut health) in players.iter_mut() {
+        if let Some(body) = physics.get(*handle) {
+            let cushioned = *game_mode == GameMode::Creative || body.is_flying() || body.is_in_fluid();
+            if let Some(height) = fall.update(body.position().y, body.is_grounded(), cushioned) {
+                health.damage(fall_damage(height));
+            }
+        }
+    }
+    for event in reader.iter(&events) {
+        for (_, handle, _, mut health) in players.iter_mut() {
+            //also in creative mode, the player would fall forever otherwise
+            if matches!(event, PhysicsEvent::FellOutOfWorld { body } if body == handle) {
+                health.damage(MAX_HEALTH);
+            }
+        }
+    }
+}
+
+/// the player loses air while the head is in a fluid and takes damage without air
+pub fn drowning_system(
+    time: Res<Time>,
+    chunk_manager: Res<ChunkManager>,
+    static_blocks: Res<StaticBlocksRes>,
+    chunks: Query<(&Chunk,)>,
+    mut players: Query<(&GameMode, &Transform, &mut Health)>,
+) {
+    let delta = time.delta_seconds();
+    for (game_mode, transform, mut health) in players.iter_mut() {
+        //the transform of the player is at the eyes
+        let head = BlockPosition::from_vector(transform.translation);
+        let under_water = chunk_manager.get(head, &chunks)
+            .map_or(false, |block|matches!(static_blocks[block.btype as usize].1, BlockFeel::Fluid));
+
+        if !under_water || *game_mode == GameMode::Creative {
+            health.air = (health.air + AIR_REGENERATION * delta).min(MAX_AIR);
+            continue;
+        }
+        health.air = (health.air - delta).max(0.0);
+        if health.air == 0.0 {
+            health.damage(DROWNING_DAMAGE * delta);
+        }
+    }
+}
+
+/// dead players are moved to the spawn point with full health
+pub fn respawn_system(
+    spawn_point: Res<SpawnPoint>,
+    physics_commands: Res<PhysicsCommands>,
+    mut players: Query<(&RigidBodyHandle, &mut Health, &mut Fall)>,
+) {
+    for (handle, mut health, mut fall) in players.iter_mut() {
+        if health.is_dead() {
+            physics_commands.send(PhysicsCommand::Teleport(*handle, spawn_point.0));
+            *health = Health::default();
+            *fall = Fall::default();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_falls_are_harmless() {
+        assert_eq!(fall_damage(0.0), 0.0);
+        assert_eq!(fall_damage(SAFE_FALL_HEIGHT), 0.0);
+        assert_eq!(fall_damage(SAFE_FALL_HEIGHT + 0.5), 1.0);
+        assert_eq!(fall_damage(20.0), 17.0);
+    }
+
+    #[test]
+    fn falls_are_measured_from_the_highest_point() {
+        let mut fall = Fall::default();
+        assert_eq!(fall.update(10.0, true, false), None);
+        //a jump off a tower
+        assert_eq!(fall.update(11.0, false, false), None);
+        assert_eq!(fall.update(11.2, false, false), None);
+        for step in 0..100 {
+            assert_eq!(fall.update(11.0 - step as f32 * 0.1, false, false), None);
+        }
+        let height = fall.update(0.0, true, false).unwrap();
+        assert!((height - 11.2).abs() < 1e-4);
+        assert_eq!(fall_damage(height), 9.0);
+        //standing doesnt fall again
+        assert_eq!(fall.update(0.0, true, false), None);
+    }
+
+    #[test]
+    fn long_falls_hurt_at_terminal_speed() {
+        //the air drag of the default settings stops the acceleration at 16 blocks per second,
+        //a landing velocity would estimate every fall at 4 blocks at most
+        let mut fall = Fall::default();
+        let mut y = 40.0;
+        while y > 0.0 {
+            assert_eq!(fall.update(y, false, false), None);
+            y -= 16.0 / 60.0;
+        }
+        assert!(fall_damage(fall.update(0.0, true, false).unwrap()) >= 36.0);
+    }
+
+    #[test]
+    fn water_and_flying_stop_the_fall() {
+        let mut fall = Fall::default();
+        fall.update(30.0, false, false);
+        assert_eq!(fall.update(5.0, false, true), None);
+        fall.update(5.0, false, false);
+        let height = fall.update(4.0, true, false).unwrap();
+        assert!((height - 1.0).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,108 @@
+use bevy::prelude::*;
+use bevy::render::mesh::Indices;
+use bevy::render::pipeline::PrimitiveTopology;
+use crate::world::chunk::{Chunk, ChunkManager, RaycastHit};
+use crate::world::block_types::StaticBlocksRes;
+use crate::physics::ray::Ray;
+
+/// the outline is slightly larger than the block to avoid z-fighting
+const HIGHLIGHT_SCALE: f32 = 1.005;
+
+/// The block the player is looking at
+pub struct Targeting {
+    /// the maximal distance of a targeted block
+    pub reach: f32,
+    hit: Option<RaycastHit>,
+}
+
+impl Targeting {
+    pub fn new(reach: f32) -> Self {
+        Targeting {
+            reach,
+            hit: None,
+        }
+    }
+    pub fn hit(&self) -> Option<&RaycastHit> {
+        self.hit.as_ref()
+    }
+}
+
+pub struct BlockHighlight;
+
+fn create_outline_mesh() -> Mesh {
+    let corners: Vec<[f32; 3]> = (0..8)
+        .map(|index|[
+            (index & 1) as f32 - 0.5,
+            ((index >> 1) & 1) as f32 - 0.5,
+            ((index >> 2) & 1) as f32 - 0.5,
+        ])
+        .collect();
+
+    //every edge connects two corners differing in exactly one axis
+    let mut indices = Vec::new();
+    for index in 0..8u16 {
+        for axis in [1u16, 2, 4].iter() {
+            if index & axis == 0 {
+                indices.push(index);
+                indices.push(index | axis);
+            }
+        }
+    }
+
+    let mut mesh = Mesh::new(PrimitiveTopology::LineList);
+    mesh.set_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 1.0, 0.0]; corners.len()]);
+    mesh.set_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.0]; corners.len()]);
+    mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, corners);
+    mesh.set_indices(Some(Indices::U16(indices)));
+    mesh
+}
+
+pub fn spawn_block_highlight(commands: &mut Commands, meshes: &mut Assets<Mesh>, materials: &mut Assets<StandardMaterial>) {
+    commands
+        .spawn(PbrBundle {
+            mesh: meshes.add(create_outline_mesh()),
+            material: materials.add(StandardMaterial {
+                albedo: Color::BLACK,
+                albedo_texture: None,
+                shaded: false,
+            }),
+            visible: Visible {
+                is_visible: false,
+                is_transparent: false,
+            },
+            ..Default::default()
+        })
+        .with(BlockHighlight);
+}
+
+pub fn targeting_system(
+    chunk_manager: Res<ChunkManager>,
+    static_blocks: Res<StaticBlocksRes>,
+    chunks: Query<(&Chunk,)>,
+    mut player: Query<(&Transform, &mut Targeting)>,
+) {
+    for (transform, mut targeting) in player.iter_mut() {
+        let ray = Ray::from_global_transform(transform);
+        targeting.hit = chunk_manager.raycast(ray, targeting.reach, &static_blocks, &chunks);
+    }
+}
+
+pub fn block_highlight_system(
+    player: Query<&Targeting>,
+    mut highlight: Query<(&BlockHighlight, &mut Transform, &mut Visible)>,
+) {
+    let target = player.iter()
+        .filter_map(|targeting|targeting.hit())
+        .next()
+        .map(|hit|hit.position);
+
+    for (_, mut transform, mut visible) in highlight.iter_mut() {
+        if let Some(position) = target {
+            *transform = Transform::from_translation(position.block_center());
+            transform.scale = Vec3::splat(HIGHLIGHT_SCALE);
+            visible.is_visible = true;
+        } else {
+            visible.is_visible = false;
+        }
+    }
+}
@@ -1,5 +0,0 @@
-use bevy::app::AppBuilder;
-use crate::settings::Settings;
-
-pub fn init_rendering(builder: &mut AppBuilder, settings: &Settings) {
-}
\ No newline at end of file
@@ -0,0 +1,53 @@
+#version 450
+
+const int MAX_LIGHTS = 10;
+// the light lost per occluding block at a corner
+const float OCCLUSION = 0.2;
+
+struct Light {
+    mat4 proj;
+    vec4 pos;
+    vec4 color;
+};
+
+layout(location = 0) in vec3 v_Position;
+layout(location = 1) in vec3 v_Normal;
+layout(location = 2) in vec2 v_Uv;
+layout(location = 3) flat in uint v_Layer;
+layout(location = 4) in float v_Occlusion;
+layout(location = 5) in float v_SkyLight;
+layout(location = 6) in float v_Distance;
+
+layout(location = 0) out vec4 o_Target;
+
+layout(set = 1, binding = 0) uniform Lights {
+    vec3 AmbientColor;
+    uvec4 NumLights;
+    Light SceneLights[MAX_LIGHTS];
+};
+
+layout(set = 3, binding = 0) uniform texture2DArray ChunkMaterial_textures;
+layout(set = 3, binding = 1) uniform sampler ChunkMaterial_textures_sampler;
+layout(set = 3, binding = 2) uniform ChunkMaterial_fog_color {
+    vec4 FogColor;
+};
+layout(set = 3, binding = 3) uniform ChunkMaterial_fog_density {
+    float FogDensity;
+};
+
+void main() {
+    vec4 color = texture(sampler2DArray(ChunkMaterial_textures, ChunkMaterial_textures_sampler), vec3(v_Uv, float(v_Layer)));
+
+    // the same lighting as the standard material, the sun only reaches faces open to the sky
+    vec3 normal = normalize(v_Normal);
+    vec3 light = vec3(0.0);
+    for (int i = 0; i < int(NumLights.x) && i < MAX_LIGHTS; ++i) {
+        vec3 direction = normalize(SceneLights[i].pos.xyz - v_Position);
+        light += max(0.0, dot(normal, direction)) * SceneLights[i].color.xyz;
+    }
+    light = AmbientColor + light * v_SkyLight;
+    color.rgb *= light * (1.0 - OCCLUSION * v_Occlusion);
+
+    float visibility = exp(-FogDensity * v_Distance);
+    o_Target = vec4(mix(FogColor.rgb, color.rgb, visibility), color.a);
+}
@@ -0,0 +1,53 @@
+#version 450
+
+// see PackedChunkMeshBuilder
+layout(location = 0) in uvec2 Vertex_Packed;
+
+layout(location = 0) out vec3 v_Position;
+layout(location = 1) out vec3 v_Normal;
+layout(location = 2) out vec2 v_Uv;
+layout(location = 3) flat out uint v_Layer;
+layout(location = 4) out float v_Occlusion;
+layout(location = 5) out float v_SkyLight;
+layout(location = 6) out float v_Distance;
+
+layout(set = 0, binding = 0) uniform Camera {
+    mat4 ViewProj;
+};
+
+layout(set = 2, binding = 0) uniform Transform {
+    mat4 Model;
+};
+
+// in the order of the sides
+const vec3 NORMALS[6] = vec3[6](
+    vec3(0.0, 1.0, 0.0),
+    vec3(1.0, 0.0, 0.0),
+    vec3(-1.0, 0.0, 0.0),
+    vec3(0.0, 0.0, 1.0),
+    vec3(0.0, 0.0, -1.0),
+    vec3(0.0, -1.0, 0.0)
+);
+
+const vec2 CORNERS[4] = vec2[4](
+    vec2(0.0, 0.0),
+    vec2(1.0, 0.0),
+    vec2(0.0, 1.0),
+    vec2(1.0, 1.0)
+);
+
+void main() {
+    uint first = Vertex_Packed.x;
+    uint second = Vertex_Packed.y;
+    vec3 position = vec3(first & 0xffu, (first >> 8) & 0xffu, (first >> 16) & 0xffu) / 8.0 - 8.0;
+
+    v_Position = (Model * vec4(position, 1.0)).xyz;
+    v_Normal = mat3(Model) * NORMALS[(first >> 24) & 0x7u];
+    v_Occlusion = float((first >> 27) & 0x3u);
+    v_Uv = CORNERS[(first >> 29) & 0x3u];
+    v_Layer = second & 0xffffu;
+    v_SkyLight = float((second >> 16) & 0xfu) / 15.0;
+    gl_Position = ViewProj * vec4(v_Position, 1.0);
+    // the distance in front of the camera
+    v_Distance = gl_Position.w;
+}
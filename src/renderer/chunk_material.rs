@@ -0,0 +1,141 @@
+use bevy::prelude::*;
+use bevy::asset::HandleId;
+use bevy::reflect::TypeUuid;
+use bevy::render::pipeline::{PipelineDescriptor, RenderPipeline};
+use bevy::render::render_graph::{base, AssetRenderResourcesNode, RenderGraph};
+use bevy::render::renderer::RenderResources;
+use bevy::render::shader::{ShaderStage, ShaderStages};
+use bevy::render::texture::{AddressMode, Extent3d, FilterMode, SamplerDescriptor, TextureDimension};
+
+pub const CHUNK_PIPELINE_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(PipelineDescriptor::TYPE_UUID, 6254907234186412637);
+
+const CHUNK_MATERIAL_NODE: &str = "chunk_material";
+/// the number of textures in a row of the block texture atlas, see `cube_face`
+pub const ATLAS_RESOLUTION: u32 = 16;
+
+/// The material of the terrain, the lit block textures fade into the fog with the distance
+#[derive(RenderResources, TypeUuid)]
+#[uuid = "4d6a2f0e-93b1-4c7e-8f25-1b0c9e7a3d52"]
+pub struct ChunkMaterial {
+    /// the layers are the textures of the atlas, it is created once the atlas is loaded
+    pub textures: Handle<Texture>,
+    /// the texture atlas of the blocks
+    #[render_resources(ignore)]
+    pub atlas: Handle<Texture>,
+    /// the color of the sky behind the fog
+    pub fog_color: Color,
+    /// the fraction of the light lost per block between the camera and a face, see `fog::visibility`
+    pub fog_density: f32,
+}
+
+impl ChunkMaterial {
+    pub fn new(atlas: Handle<Texture>) -> Self {
+        ChunkMaterial {
+            textures: Handle::default(),
+            atlas,
+            fog_color: Color::WHITE,
+            fog_density: 0.0,
+        }
+    }
+}
+
+/// Splits an atlas of `resolution` times `resolution` textures into the layers of a texture array,
+/// the layer of a texture is its index in the atlas.
+///
+/// Every texture is sampled on its own, so neighbouring textures dont bleed into the edges of the faces.
+///
+/// The array has no mip levels: bevy 0.4 creates the gpu texture of a `Texture` with a single mip level
+/// and only uploads `data` into it, so there is no way to hand it smaller levels.
+/// The min filter is `Nearest` for that reason, distant faces shimmer instead of blurring the pixel art.
+pub fn texture_array(atlas: &Texture, resolution: u32) -> Texture {
+    let (width, height) = (atlas.size.width / resolution, atlas.size.height / resolution);
+    let pixel = atlas.data.len() / (atlas.size.width * atlas.size.height) as usize;
+    let row = atlas.size.width as usize * pixel;
+    let mut data = Vec::with_capacity(atlas.data.len());
+    for layer in 0..resolution * resolution {
+        let (x, y) = (layer % resolution, layer / resolution);
+        for line in 0..height {
+            let start = (y * height + line) as usize * row + (x * width) as usize * pixel;
+            data.extend_from_slice(&atlas.data[start..start + width as usize * pixel]);
+        }
+    }
+    let mut array = Texture::new(
+        Extent3d::new(width, height, resolution * resolution),
+        TextureDimension::D2,
+        data,
+        atlas.format,
+    );
+    array.sampler = SamplerDescriptor {
+        address_mode_u: AddressMode::Repeat,
+        address_mode_v: AddressMode::Repeat,
+        mag_filter: FilterMode::Nearest,
+        min_filter: FilterMode::Nearest,
+        ..SamplerDescriptor::default()
+    };
+    array
+}
+
+/// creates the texture arrays of the chunk materials with a loaded atlas
+pub fn create_texture_arrays(mut textures: ResMut<Assets<Texture>>, mut materials: ResMut<Assets<ChunkMaterial>>) {
+    let waiting: Vec<(HandleId, Handle<Texture>)> = materials.iter()
+        .filter(|(_, material)|material.textures == Handle::default())
+        .map(|(id, material)|(id, material.atlas.clone()))
+        .collect();
+    for (id, atlas) in waiting {
+        let array = match textures.get(&atlas) {
+            Some(atlas) => texture_array(atlas, ATLAS_RESOLUTION),
+            None => continue,
+        };
+        let array = textures.add(array);
+        if let Some(material) = materials.get_mut(id) {
+            material.textures = array;
+        }
+    }
+}
+
+/// the render pipelines of an entity drawn with a `ChunkMaterial`
+pub fn chunk_pipelines() -> RenderPipelines {
+    RenderPipelines::from_pipelines(vec![RenderPipeline::new(CHUNK_PIPELINE_HANDLE.typed())])
+}
+
+pub fn setup_chunk_pipeline(
+    mut pipelines: ResMut<Assets<PipelineDescriptor>>,
+    mut shaders: ResMut<Assets<Shader>>,
+    mut render_graph: ResMut<RenderGraph>,
+) {
+    let stages = ShaderStages {
+        vertex: shaders.add(Shader::from_glsl(ShaderStage::Vertex, include_str!("chunk.vert"))),
+        fragment: Some(shaders.add(Shader::from_glsl(ShaderStage::Fragment, include_str!("chunk.frag")))),
+    };
+    pipelines.set_untracked(CHUNK_PIPELINE_HANDLE, PipelineDescriptor::default_config(stages));
+
+    //uploads the materials before the main pass draws the chunks
+    render_graph.add_system_node(CHUNK_MATERIAL_NODE, AssetRenderResourcesNode::<ChunkMaterial>::new(true));
+    render_graph.add_node_edge(CHUNK_MATERIAL_NODE, base::node::MAIN_PASS).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::render::texture::TextureFormat;
+
+    #[test]
+    fn layers_follow_the_atlas_index() {
+        //an atlas of 2 by 2 textures with 2 by 1 pixels, the red channel is the index of the texture
+        let mut data = Vec::new();
+        for line in 0..2u8 {
+            for x in 0..4u8 {
+                data.extend_from_slice(&[line * 2 + x / 2, x % 2, 0, 255]);
+            }
+        }
+        let atlas = Texture::new(Extent3d::new(4, 2, 1), TextureDimension::D2, data, TextureFormat::Rgba8UnormSrgb);
+        let array = texture_array(&atlas, 2);
+        assert_eq!(array.size, Extent3d::new(2, 1, 4));
+        for layer in 0..4 {
+            assert_eq!(array.data[layer * 8], layer as u8);
+            assert_eq!(array.data[layer * 8 + 4], layer as u8);
+            assert_eq!(array.data[layer * 8 + 5], 1);
+        }
+    }
+}
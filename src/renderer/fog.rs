@@ -0,0 +1,55 @@
+use bevy::prelude::*;
+use bevy::asset::HandleId;
+use crate::settings::Settings;
+use crate::renderer::chunk_material::ChunkMaterial;
+use crate::world::coordinates::CHUNK_SIZE;
+
+/// the fraction of the light of a face at the edge of the loaded chunks reaching the camera
+const EDGE_VISIBILITY: f32 = 0.6;
+
+/// the fog density of a load distance (in chunks), the fog hides the far terrain beyond the loaded chunks
+pub fn fog_density(load_distance: f32) -> f32 {
+    -EDGE_VISIBILITY.ln() / (load_distance.max(1.0) * CHUNK_SIZE as f32)
+}
+
+/// the fraction of the light of a face at the distance (in blocks) reaching the camera, the rest is fog
+pub fn visibility(fog_density: f32, distance: f32) -> f32 {
+    (-fog_density * distance).exp()
+}
+
+/// the fog has the color of the sky and fits the load distance
+pub fn update_fog(
+    settings: Res<Settings>,
+    clear_color: Res<ClearColor>,
+    mut materials: ResMut<Assets<ChunkMaterial>>,
+) {
+    let density = fog_density(settings.game_settings.load_distance);
+    let ids: Vec<HandleId> = materials.ids().collect();
+    for id in ids {
+        //changed materials are uploaded again
+        let changed = materials.get(id)
+            .map_or(false, |material|material.fog_color != clear_color.0 || material.fog_density != density);
+        if changed {
+            if let Some(material) = materials.get_mut(id) {
+                material.fog_color = clear_color.0;
+                material.fog_density = density;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fog_fits_the_load_distance() {
+        let density = fog_density(4.0);
+        let edge = visibility(density, 4.0 * CHUNK_SIZE as f32);
+        assert!((edge - EDGE_VISIBILITY).abs() < 0.001);
+        assert!(visibility(density, 0.0) > 0.999);
+        //the far terrain fades out
+        assert!(visibility(density, 16.0 * CHUNK_SIZE as f32) < 0.2);
+        assert!(fog_density(8.0) < density);
+    }
+}
@@ -0,0 +1,16 @@
+use bevy::app::AppBuilder;
+use bevy::prelude::*;
+use crate::settings::Settings;
+use crate::state::GAME_POST_UPDATE;
+use crate::renderer::chunk_material::{ChunkMaterial, setup_chunk_pipeline, create_texture_arrays};
+use crate::renderer::fog::update_fog;
+
+pub mod chunk_material;
+pub mod fog;
+
+pub fn init_rendering(builder: &mut AppBuilder, _settings: &Settings) {
+    builder.add_asset::<ChunkMaterial>()
+        .add_startup_system(setup_chunk_pipeline.system())
+        .add_system_to_stage(GAME_POST_UPDATE, create_texture_arrays.system())
+        .add_system_to_stage(GAME_POST_UPDATE, update_fog.system());
+}
@@ -1,47 +1,217 @@
-use serde::{Serialize, Deserialize};
+use serde::{Serialize, Deserialize, Deserializer};
 use std::path::PathBuf;
+use crate::player::survival::GameMode;
+use crate::input::binding::{Binding, AxisDirection};
+use crate::input::actions::{FORWARD, BACKWARD, LEFT, RIGHT, JUMP, SNEAK, SPRINT, BREAK, PLACE, TOGGLE_GAME_MODE, LOOK_LEFT, LOOK_RIGHT, LOOK_UP, LOOK_DOWN, HOTBAR_NEXT, HOTBAR_PREVIOUS, HOTBAR, PAUSE, TOGGLE_DEBUG, OPEN_CHAT, OPEN_COMMAND};
+use bevy::input::keyboard::KeyCode;
+use bevy::input::mouse::MouseButton;
+use bevy::input::gamepad::{GamepadAxisType, GamepadButtonType};
+use std::collections::BTreeMap;
+use std::io::BufWriter;
+use std::fs::File;
+use anyhow::Result;
+
+/// the settings are loaded from this file and changes in the game are saved to it
+pub const SETTINGS_PATH: &str = "./settings.json";
 
 #[derive(Default, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Settings{
     pub render_settings: RenderSettings,
     pub game_settings: GameSettings,
+    pub physics_settings: PhysicsSettings,
+    pub controls: Controls,
+}
+
+impl Settings {
+    pub fn save(&self) -> Result<()> {
+        serde_json::to_writer_pretty(
+            BufWriter::new(File::with_options()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(SETTINGS_PATH)?),
+            self,
+        )?;
+        Ok(())
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct RenderSettings {
-    pub msaa_samples: u32
+    /// only read at startup, the render pipelines dont follow later changes of the `Msaa` resource
+    pub msaa_samples: u32,
+    /// the distance (in chunks) up to which the far terrain is drawn beyond the loaded chunks
+    pub far_terrain_distance: f32,
 }
 
 impl Default for RenderSettings {
     fn default() -> Self {
         RenderSettings {
             msaa_samples: 4,
+            far_terrain_distance: 16.0,
         }
     }
 }
 
+/// The bindings of the named actions in `input::actions`
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Controls {
+    /// actions missing in the saved settings keep their default bindings
+    #[serde(deserialize_with = "deserialize_bindings")]
+    pub bindings: BTreeMap<String, Vec<Binding>>,
+    /// stick deflections below this are ignored
+    pub gamepad_dead_zone: f32,
+    /// the turning speed at full deflection of the look stick, in degrees per second
+    pub gamepad_look_sensitivity: f32,
+    /// the factor applied to the mouse motion
+    pub mouse_sensitivity: f32,
+}
+
+impl Default for Controls {
+    fn default() -> Self {
+        use GamepadAxisType::*;
+        use AxisDirection::*;
+
+        let mut bindings = BTreeMap::new();
+        let mut bind = |action: &str, action_bindings: Vec<Binding>| {
+            bindings.insert(action.to_string(), action_bindings);
+        };
+        bind(FORWARD, vec![Binding::key(KeyCode::W), Binding::gamepad_axis(LeftStickY, Positive)]);
+        bind(BACKWARD, vec![Binding::key(KeyCode::S), Binding::gamepad_axis(LeftStickY, Negative)]);
+        bind(LEFT, vec![Binding::key(KeyCode::A), Binding::gamepad_axis(LeftStickX, Negative)]);
+        bind(RIGHT, vec![Binding::key(KeyCode::D), Binding::gamepad_axis(LeftStickX, Positive)]);
+        bind(LOOK_LEFT, vec![Binding::gamepad_axis(RightStickX, Negative)]);
+        bind(LOOK_RIGHT, vec![Binding::gamepad_axis(RightStickX, Positive)]);
+        bind(LOOK_UP, vec![Binding::gamepad_axis(RightStickY, Positive)]);
+        bind(LOOK_DOWN, vec![Binding::gamepad_axis(RightStickY, Negative)]);
+        bind(JUMP, vec![Binding::key(KeyCode::Space), Binding::gamepad_button(GamepadButtonType::South)]);
+        bind(SNEAK, vec![Binding::key(KeyCode::LShift), Binding::gamepad_button(GamepadButtonType::East)]);
+        bind(SPRINT, vec![Binding::key(KeyCode::LControl), Binding::gamepad_button(GamepadButtonType::LeftThumb)]);
+        bind(BREAK, vec![Binding::mouse(MouseButton::Left), Binding::gamepad_button(GamepadButtonType::RightTrigger2)]);
+        bind(PLACE, vec![Binding::mouse(MouseButton::Right), Binding::gamepad_button(GamepadButtonType::LeftTrigger2)]);
+        bind(PAUSE, vec![Binding::key(KeyCode::Escape), Binding::gamepad_button(GamepadButtonType::Start)]);
+        bind(TOGGLE_DEBUG, vec![Binding::key(KeyCode::F3)]);
+        bind(OPEN_CHAT, vec![Binding::key(KeyCode::T)]);
+        bind(OPEN_COMMAND, vec![Binding::key(KeyCode::Slash)]);
+        bind(TOGGLE_GAME_MODE, vec![Binding::key(KeyCode::F4), Binding::gamepad_button(GamepadButtonType::Select)]);
+        bind(HOTBAR_NEXT, vec![Binding::gamepad_button(GamepadButtonType::RightTrigger)]);
+        bind(HOTBAR_PREVIOUS, vec![Binding::gamepad_button(GamepadButtonType::LeftTrigger)]);
+        let hotbar_keys = [
+            KeyCode::Key1, KeyCode::Key2, KeyCode::Key3,
+            KeyCode::Key4, KeyCode::Key5, KeyCode::Key6,
+            KeyCode::Key7, KeyCode::Key8, KeyCode::Key9,
+        ];
+        for (action, key) in HOTBAR.iter().zip(hotbar_keys.iter()) {
+            bind(action, vec![Binding::key(*key)]);
+        }
+        Controls {
+            bindings,
+            gamepad_dead_zone: 0.15,
+            gamepad_look_sensitivity: 180.0,
+            mouse_sensitivity: 8.0,
+        }
+    }
+}
+
+/// the saved bindings replace the defaults of their actions, so actions added since the settings were saved are bound
+fn deserialize_bindings<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BTreeMap<String, Vec<Binding>>, D::Error> {
+    let mut bindings = Controls::default().bindings;
+    bindings.extend(BTreeMap::<String, Vec<Binding>>::deserialize(deserializer)?);
+    Ok(bindings)
+}
 
+impl Controls {
+    /// the pairs of actions sharing a binding
+    pub fn conflicts(&self) -> Vec<(String, String, Binding)> {
+        let all: Vec<(&String, &Binding)> = self.bindings.iter()
+            .flat_map(|(action, bindings)|bindings.iter().map(move|binding|(action, binding)))
+            .collect();
+        let mut conflicts = Vec::new();
+        for (index, (action, binding)) in all.iter().enumerate() {
+            for (other_action, other_binding) in all[index + 1..].iter() {
+                if action != other_action && binding.conflicts_with(other_binding) {
+                    conflicts.push((action.to_string(), other_action.to_string(), (*binding).clone()));
+                }
+            }
+        }
+        conflicts
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct GameSettings {
     pub print_fps: bool,
-    pub print_position: bool,
     pub load_distance: f32,
     pub unload_distance: f32,
     pub asset_path: PathBuf,
+    /// the folder containing a folder for every saved world
+    pub save_path: PathBuf,
+    /// the maximal distance of a targeted block
+    pub reach: f32,
+    /// the game mode of new players
+    pub game_mode: GameMode,
+    /// the number of chunks after which the detail of the chunk meshes halves
+    pub lod_distance: f32,
 }
 
 impl Default for GameSettings {
     fn default() -> Self {
         GameSettings {
-            print_position: false,
             load_distance: 2.0,
             print_fps: false,
             unload_distance: 4.0,
             asset_path: PathBuf::from("/data/Workspaces/Rust/mine_clone/assets"),
+            save_path: PathBuf::from("./saves"),
+            reach: 5.0,
+            game_mode: GameMode::Survival,
+            lod_distance: 4.0,
+        }
+    }
+}
+/// All values are in blocks and seconds
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PhysicsSettings {
+    /// the duration of one physics step
+    pub timestep: f32,
+    /// the downwards acceleration of non flying bodies
+    pub gravity: f32,
+    /// the decay rate of the horizontal velocity of a grounded body (also used while flying),
+    /// every step multiplies the velocity by `exp(-ground_friction * timestep)`
+    pub ground_friction: f32,
+    /// the decay rate of the velocity of a body in the air, every step multiplies it by `exp(-air_friction * timestep)`
+    pub air_friction: f32,
+}
+
+impl Default for PhysicsSettings {
+    fn default() -> Self {
+        PhysicsSettings {
+            timestep: 1.0 / 60.0,
+            gravity: 32.0,
+            ground_friction: 12.0,
+            air_friction: 2.0,
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn old_bindings_keep_the_new_actions() {
+        //saved before the pause action existed, with a changed jump key
+        let controls: Controls = serde_json::from_value(serde_json::json!({
+            "bindings": {
+                JUMP: [Binding::key(KeyCode::J)],
+            },
+        })).unwrap();
+        assert_eq!(controls.bindings[JUMP], vec![Binding::key(KeyCode::J)]);
+        assert_eq!(controls.bindings[PAUSE], Controls::default().bindings[PAUSE]);
+        assert_eq!(controls.bindings.len(), Controls::default().bindings.len());
+    }
+}
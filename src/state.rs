@@ -0,0 +1,53 @@
+use bevy::prelude::*;
+use bevy::ecs::ShouldRun;
+
+/// the stage running the enter, update and exit systems of the app states
+pub const APP_STATE_STAGE: &str = "app_state";
+/// the systems simulating the loaded world, they only run in game
+pub const GAME_UPDATE: &str = "game_update";
+/// runs after the post update stage as long as a world is loaded, so chunks are still meshed while paused
+pub const GAME_POST_UPDATE: &str = "game_post_update";
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum AppState {
+    MainMenu,
+    /// the saved worlds
+    WorldList,
+    /// the form for the name and seed of a new or renamed world
+    EditWorld,
+    /// the world is opened, the player and the chunks are set up
+    Loading,
+    InGame,
+    /// the world is loaded but not simulated, the pause menu is shown
+    Paused,
+}
+
+impl AppState {
+    /// a world is loaded in this state
+    pub fn has_world(&self) -> bool {
+        matches!(self, AppState::Loading | AppState::InGame | AppState::Paused)
+    }
+}
+
+fn in_game(state: Res<State<AppState>>) -> ShouldRun {
+    if *state.current() == AppState::InGame {
+        ShouldRun::Yes
+    } else {
+        ShouldRun::No
+    }
+}
+
+fn world_loaded(state: Res<State<AppState>>) -> ShouldRun {
+    if state.current().has_world() {
+        ShouldRun::Yes
+    } else {
+        ShouldRun::No
+    }
+}
+
+pub fn init_state(builder: &mut AppBuilder) {
+    builder.add_resource(State::new(AppState::MainMenu))
+        .add_stage_after(stage::UPDATE, APP_STATE_STAGE, StateStage::<AppState>::default())
+        .add_stage_after(APP_STATE_STAGE, GAME_UPDATE, SystemStage::parallel().with_run_criteria(in_game.system()))
+        .add_stage_after(stage::POST_UPDATE, GAME_POST_UPDATE, SystemStage::parallel().with_run_criteria(world_loaded.system()));
+}
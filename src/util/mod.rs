@@ -1,4 +1,5 @@
-use bevy::ecs::ResMut;
+use bevy::ecs::{Res, ResMut};
+use crate::settings::Settings;
 use std::time::Instant;
 
 impl Default for FPS {
@@ -8,6 +9,7 @@ impl Default for FPS {
             passed_frames: 0,
             start_time: now,
             last_time: now,
+            current: 0.0,
         }
     }
 }
@@ -16,16 +18,30 @@ pub struct FPS{
     passed_frames: u64,
     start_time: Instant,
     last_time: Instant,
+    /// the frame rate of the last 60 frames
+    current: f32,
 }
 
-pub fn print_fps(mut time: ResMut<FPS>) {
+impl FPS {
+    pub fn current(&self) -> f32 {
+        self.current
+    }
+    /// the average frame rate since the start
+    pub fn average(&self) -> f32 {
+        self.passed_frames as f32 / (Instant::now() - self.start_time).as_secs_f32()
+    }
+}
+
+/// measures the frame rate and prints it, if `print_fps` is set
+pub fn update_fps(settings: Res<Settings>, mut time: ResMut<FPS>) {
     time.passed_frames += 1;
     if time.passed_frames % 60 == 0 {
         let now = Instant::now();
 
-        let current_fps = 60.0 / (now - time.last_time).as_secs_f32();
-        let fps = time.passed_frames as f32 / (now - time.start_time).as_secs_f32();
+        time.current = 60.0 / (now - time.last_time).as_secs_f32();
         time.last_time = now;
-        println!("{:.1} FPS ({:.1})", current_fps, fps);
+        if settings.game_settings.print_fps {
+            println!("{:.1} FPS ({:.1})", time.current, time.average());
+        }
     }
-}
\ No newline at end of file
+}
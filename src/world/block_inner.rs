@@ -3,8 +3,9 @@ use std::fmt::Debug;
 use std::ops::BitOr;
 use std::fmt::Formatter;
 use std::ops::IndexMut;
+use serde::{Serialize, Deserialize};
 
-#[derive(Copy, Clone, Eq, PartialEq)]
+#[derive(Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct BlockInfo(u8);
 
 pub const EMPTY: BlockInfo = BlockInfo(0);
@@ -57,8 +58,12 @@ pub const GRASS: BlockInner = BlockInner{btype: 3, data: 0, info: BLOCK_MESH};
 pub const STONE: BlockInner = BlockInner{btype: 1, data: 0, info: BLOCK_MESH};
 pub const WOOD: BlockInner = BlockInner{btype: 4, data: 0, info: BLOCK_MESH};
 pub const LOG: BlockInner = BlockInner{btype: 5, data: 0, info: BLOCK_MESH};
+pub const BEDROCK: BlockInner = BlockInner{btype: 6, data: 0, info: BLOCK_MESH};
+pub const WATER: BlockInner = BlockInner{btype: 7, data: 0, info: EMPTY};
+/// a single layer of snow, the data is the number of layers
+pub const SNOW: BlockInner = BlockInner{btype: 8, data: 1, info: EMPTY};
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
 pub struct BlockInner {
     pub btype: u16,
     pub data: u8,
@@ -68,6 +73,7 @@ pub struct BlockInner {
 struct Faces([Range<usize>;6]);
 
 #[repr(C)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub enum Side {
     Top = 0,
     Front = 1,
@@ -77,6 +83,8 @@ pub enum Side {
     Bottom = 5,
 }
 
+pub static SIDES: [Side; 6] = [Side::Top, Side::Front, Side::Back, Side::Left, Side::Right, Side::Bottom];
+
 #[derive(Copy, Clone)]
 pub struct Sides<T> {
     values: [T; 6],
@@ -1,6 +1,6 @@
 use bevy::prelude::*;
 
-use crate::world::block_inner::{Sides, BlockInfo, EMPTY, BLOCK_MESH};
+use crate::world::block_inner::{Sides, BlockInfo, BlockInner, EMPTY, BLOCK_MESH};
 use crate::world::chunk_mesh::{VisibleDirection, Face};
 use crate::physics::collider::AAQuader;
 
@@ -12,11 +12,14 @@ pub type StaticBlocksRes = Vec<(BlockLook, BlockFeel, Box<dyn BlockPersonality +
 pub fn get_block_types() -> StaticBlocksRes {
     let mut block_types: StaticBlocksRes = vec![
         (BlockLook::Empty, BlockFeel::Empty, Box::new(Air)),
-        (BlockLook::Empty, BlockFeel::Empty, Box::new(Cube::uniform("stone", 1))),
-        (BlockLook::Empty, BlockFeel::Empty, Box::new(Cube::uniform("dirt", 2))),
-        (BlockLook::Empty, BlockFeel::Empty, Box::new(Cube::top_side_bottom("grass", 0, 3, 2))),
-        (BlockLook::Empty, BlockFeel::Empty, Box::new(Cube::top_side_bottom("log", 21, 20, 21))),
-        (BlockLook::Empty, BlockFeel::Empty, Box::new(Cube::uniform("wood", 4))),
+        (BlockLook::Empty, BlockFeel::Empty, Box::new(Cube::uniform("stone", 1).with_hardness(1.5))),
+        (BlockLook::Empty, BlockFeel::Empty, Box::new(Cube::uniform("dirt", 2).with_hardness(0.5))),
+        (BlockLook::Empty, BlockFeel::Empty, Box::new(Cube::top_side_bottom("grass", 0, 3, 2).with_hardness(0.6))),
+        (BlockLook::Empty, BlockFeel::Empty, Box::new(Cube::top_side_bottom("log", 21, 20, 21).with_hardness(2.0))),
+        (BlockLook::Empty, BlockFeel::Empty, Box::new(Cube::uniform("wood", 4).with_hardness(2.0))),
+        (BlockLook::Empty, BlockFeel::Empty, Box::new(Cube::uniform("bedrock", 17).unbreakable())),
+        (BlockLook::Empty, BlockFeel::Empty, Box::new(Fluid::new("water", 205))),
+        (BlockLook::Empty, BlockFeel::Empty, Box::new(Layers::new("snow", 66).with_hardness(0.1))),
     ];
 
     block_types.iter_mut().for_each(|(look, feel, block)|{
@@ -27,16 +30,31 @@ pub fn get_block_types() -> StaticBlocksRes {
     block_types
 }
 
+/// the block with the given name and the default data
+pub fn block_by_name(blocks: &StaticBlocks, name: &str) -> Option<BlockInner> {
+    blocks.iter()
+        .position(|(_, _, block)|block.name() == name)
+        .map(|btype|BlockInner {
+            btype: btype as u16,
+            data: 0,
+            info: blocks[btype].2.info(0),
+        })
+}
+
 pub enum BlockLook {
     Empty,
     DynamicBlockMesh,
     CustomMesh,
     Cube{textures: Sides<u32>},
+    /// a box filling the lower part of the block, one eighth per layer (the data)
+    Layers{texture: u32},
 }
 
 pub enum BlockFeel {
     Empty,
     ColliderSet(&'static [AAQuader]),
+    /// bodies move through the block, but are inside a fluid
+    Fluid,
     Custom,
 }
 
@@ -54,6 +72,9 @@ pub trait BlockPersonality {
     fn get_feel(&self) -> BlockFeel;
     fn get_collider(&self, data: u8) -> &[AAQuader];
 
+    /// The time in seconds it takes to break the block, None if it cant be broken
+    fn hardness(&self) -> Option<f32>;
+
 }
 
 pub struct Air;
@@ -86,24 +107,82 @@ impl BlockPersonality for Air {
     fn get_collider(&self, data: u8) -> &[AAQuader] {
         &[]
     }
+
+    fn hardness(&self) -> Option<f32> {
+        None
+    }
+}
+
+
+
+pub struct Fluid {
+    name: &'static str,
+    texture: u32,
 }
 
+impl Fluid {
+    pub const fn new(name: &'static str, texture: u32) -> Self {
+        Self {name, texture}
+    }
+}
+
+impl BlockPersonality for Fluid {
+    fn get_block_look(&self) -> BlockLook {
+        BlockLook::Cube { textures: Sides::filled(self.texture) }
+    }
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn info(&self, data: u8) -> BlockInfo {
+        EMPTY
+    }
+
+    fn get_faces(&self, data: u8) -> &[(Face, VisibleDirection)] {
+        &[]
+    }
+
+    fn get_mesh(&self, data: u8) -> Option<(Handle<Mesh>, Handle<StandardMaterial>)> {
+        None
+    }
 
+    fn get_feel(&self) -> BlockFeel {
+        BlockFeel::Fluid
+    }
+
+    fn get_collider(&self, data: u8) -> &[AAQuader] {
+        &[]
+    }
+
+    fn hardness(&self) -> Option<f32> {
+        None
+    }
+}
 
 pub struct Cube{
     textures: Sides<u32>,
     name: &'static str,
+    hardness: Option<f32>,
 }
 
 impl Cube {
     pub const fn uniform(name: &'static str, texture: u32) -> Self {
-        Self {name, textures: Sides::filled(texture)}
+        Self {name, textures: Sides::filled(texture), hardness: Some(1.0)}
     }
     pub const fn top_side_bottom(name: &'static str, top: u32, side: u32, bottom: u32) -> Self {
-        Self {name, textures: Sides::new([top, side, side, side, side, bottom])}
+        Self {name, textures: Sides::new([top, side, side, side, side, bottom]), hardness: Some(1.0)}
     }
     pub fn new(name: &'static str, textures: Sides<u32>) -> Self {
-        Self {name, textures}
+        Self {name, textures, hardness: Some(1.0)}
+    }
+    pub fn with_hardness(mut self, hardness: f32) -> Self {
+        self.hardness = Some(hardness);
+        self
+    }
+    pub fn unbreakable(mut self) -> Self {
+        self.hardness = None;
+        self
     }
 }
 
@@ -138,4 +217,79 @@ impl BlockPersonality for Cube{
     fn get_collider(&self, data: u8) -> &[AAQuader] {
         QUADER_COLLIDERS
     }
-}
\ No newline at end of file
+
+    fn hardness(&self) -> Option<f32> {
+        self.hardness
+    }
+}
+/// the maximal number of layers of a layered block, a full stack is as high as a block
+pub const MAX_LAYERS: u8 = 8;
+
+static LAYER_COLLIDERS: [[AAQuader; 1]; MAX_LAYERS as usize] = [
+    [AAQuader::new(Vec3{x:0.0, y:0.0, z:0.0}, Vec3{x:1.0, y:0.125, z:1.0})],
+    [AAQuader::new(Vec3{x:0.0, y:0.0, z:0.0}, Vec3{x:1.0, y:0.25, z:1.0})],
+    [AAQuader::new(Vec3{x:0.0, y:0.0, z:0.0}, Vec3{x:1.0, y:0.375, z:1.0})],
+    [AAQuader::new(Vec3{x:0.0, y:0.0, z:0.0}, Vec3{x:1.0, y:0.5, z:1.0})],
+    [AAQuader::new(Vec3{x:0.0, y:0.0, z:0.0}, Vec3{x:1.0, y:0.625, z:1.0})],
+    [AAQuader::new(Vec3{x:0.0, y:0.0, z:0.0}, Vec3{x:1.0, y:0.75, z:1.0})],
+    [AAQuader::new(Vec3{x:0.0, y:0.0, z:0.0}, Vec3{x:1.0, y:0.875, z:1.0})],
+    [AAQuader::new(Vec3{x:0.0, y:0.0, z:0.0}, Vec3{x:1.0, y:1.0, z:1.0})],
+];
+
+/// the number of layers of a layered block, the data is clamped to a valid count
+pub fn layer_count(data: u8) -> u8 {
+    data.max(1).min(MAX_LAYERS)
+}
+
+/// Thin layers like snow, the data is the number of layers
+pub struct Layers {
+    name: &'static str,
+    texture: u32,
+    hardness: Option<f32>,
+}
+
+impl Layers {
+    pub const fn new(name: &'static str, texture: u32) -> Self {
+        Self {name, texture, hardness: Some(1.0)}
+    }
+    pub fn with_hardness(mut self, hardness: f32) -> Self {
+        self.hardness = Some(hardness);
+        self
+    }
+}
+
+impl BlockPersonality for Layers {
+    fn get_block_look(&self) -> BlockLook {
+        BlockLook::Layers { texture: self.texture }
+    }
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn info(&self, data: u8) -> BlockInfo {
+        //the faces of the adjacent blocks stay visible
+        EMPTY
+    }
+
+    fn get_faces(&self, data: u8) -> &[(Face, VisibleDirection)] {
+        &[]
+    }
+
+    fn get_mesh(&self, data: u8) -> Option<(Handle<Mesh>, Handle<StandardMaterial>)> {
+        None
+    }
+
+    fn get_feel(&self) -> BlockFeel {
+        //bodies walk through the layers, only the ray casts hit them
+        BlockFeel::Empty
+    }
+
+    fn get_collider(&self, data: u8) -> &[AAQuader] {
+        &LAYER_COLLIDERS[layer_count(data) as usize - 1]
+    }
+
+    fn hardness(&self) -> Option<f32> {
+        self.hardness
+    }
+}
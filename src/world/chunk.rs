@@ -1,10 +1,11 @@
 use super::block_inner::BlockInner;
-use super::chunk_mesh::{create_chunk_mesh, BevyChunkMeshBuilder, ChunkMeshBuilder, ChunkMesh};
+use super::chunk_mesh::{create_chunk_mesh, PackedChunkMeshBuilder, ChunkMeshBuilder};
 use super::coordinates::{ChunkPosition, BlockVector, CHUNK_SIZE, BlockPosition, MAX_CHILD};
 use crate::player::player::PlayerMovement;
 
 use crate::world::block_inner::AIR;
-use crate::world::block_types::StaticBlocksRes;
+use crate::world::block_types::{StaticBlocksRes, StaticBlocks};
+use crate::physics::ray::Ray;
 
 use bevy::prelude::*;
 
@@ -16,11 +17,18 @@ use std::collections::HashSet;
 use std::ops::{Deref, DerefMut};
 use itertools::Itertools;
 use std::path::PathBuf;
+use std::time::{Duration, Instant};
 use crate::content::provider::{Provider, ChunkUpdate};
+use crate::world::culling::Connectivity;
+use crate::world::lod::{lod_level, create_lod_mesh};
+use crate::settings::Settings;
+use crate::renderer::chunk_material::{ChunkMaterial, chunk_pipelines};
 
 pub struct Chunk {
     pub position: ChunkPosition,
     pub data: ChunkData,
+    /// the level of detail of the mesh, see `lod_level`
+    pub lod: u8,
 
     //Adjacent chunks
     pub x_positive: Option<Entity>,
@@ -118,13 +126,23 @@ impl<T: Into<BlockVector>> IndexMut<T> for ChunkData {
     }
 }
 
+/// Changes of the loaded world, sent after the systems changing the world
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ChunkEvent {
+    Loaded(ChunkPosition),
+    Unloaded(ChunkPosition),
+    BlockChanged(BlockPosition, BlockInner),
+}
+
 pub struct ChunkManager {
     chunks: HashMap<ChunkPosition, Entity>,
+    events: Vec<ChunkEvent>,
     provider: Box<dyn Provider + Send + Sync>,
     player_chunk: ChunkPosition,
     chunk_loading_distance: f32,
     chunk_discard_distance: f32,
     texture_atlas: Option<Handle<StandardMaterial>>,
+    chunk_material: Option<Handle<ChunkMaterial>>,
     chunk_rerender: HashSet<ChunkPosition>,
     current_meshes: isize,
     asset_folder: PathBuf,
@@ -135,10 +153,12 @@ impl ChunkManager {
         Self {
             provider,
             chunks: HashMap::new(),
+            events: Vec::new(),
             player_chunk: current_position,
             chunk_loading_distance: chunk_loading_distance as f32,
             chunk_discard_distance: chunk_discard_distance as f32,
             texture_atlas: None,
+            chunk_material: None,
             chunk_rerender: HashSet::new(),
             current_meshes: 0,
             asset_folder,
@@ -147,29 +167,57 @@ impl ChunkManager {
     fn init(&mut self, commands: &mut Commands,
             server: Res<AssetServer>,
             mut materials: ResMut<Assets<StandardMaterial>>,
+            mut chunk_materials: ResMut<Assets<ChunkMaterial>>,
             mut meshes: ResMut<Assets<Mesh>>,
     ) {
         println!("init registry!");
 
-        let mut path = self.asset_folder.clone();
-        path.push("textures.png");
-        let texture: Handle<Texture> = server.load(path);
+        let texture: Handle<Texture> = server.load(self.texture_path());
         let material = StandardMaterial{
             albedo: Default::default(),
-            albedo_texture: Some(texture),
+            albedo_texture: Some(texture.clone()),
             shaded: true
         };
 
         let material_handle = materials.add(material);
 
         self.texture_atlas = Some(material_handle);
+        self.chunk_material = Some(chunk_materials.add(ChunkMaterial::new(texture)));
+    }
+    fn load_chunk(&mut self, commands: &mut Commands, chunk_position: ChunkPosition, lod: u8) -> Entity {
+        let chunk = self.load_chunk_data(chunk_position, lod);
+
+        commands
+            .spawn(MeshBundle{
+                render_pipelines: chunk_pipelines(),
+                transform: Transform::from_translation(chunk_position.center()),
+                ..MeshBundle::default()
+            })
+            .with(self.chunk_material.as_ref().unwrap().clone())
+            .with(chunk)
+            .with(Connectivity::default());
+        let entity = commands.current_entity().unwrap();
+        self.chunks.insert(chunk_position, entity);
+        self.events.push(ChunkEvent::Loaded(chunk_position));
+
+        entity
     }
-    fn load_chunk(&mut self, commands: &mut Commands, chunk_position: ChunkPosition) -> Entity {
+    /// loads a chunk without a mesh, for apps without rendering
+    #[cfg(test)]
+    pub(crate) fn load_chunk_in_world(&mut self, world: &mut World, chunk_position: ChunkPosition) -> Entity {
+        let chunk = self.load_chunk_data(chunk_position, 0);
+        let entity = world.spawn((chunk, Connectivity::default()));
+        self.chunks.insert(chunk_position, entity);
+        self.events.push(ChunkEvent::Loaded(chunk_position));
+        entity
+    }
+    fn load_chunk_data(&mut self, chunk_position: ChunkPosition, lod: u8) -> Chunk {
         let chunk_data = self.provider.load_chunk(chunk_position);
 
-        let chunk = Chunk {
+        Chunk {
             position: chunk_position,
             data: chunk_data,
+            lod,
 
             /*x_positive: self.chunks.get(&chunk_position.with_x( 1)).cloned(),
             x_negative: self.chunks.get(&chunk_position.with_x(-1)).cloned(),
@@ -183,22 +231,53 @@ impl ChunkManager {
             y_negative: None,
             z_positive: None,
             z_negative: None
-        };
-
-        commands
-            .spawn(PbrBundle{
-                material: self.texture_atlas.as_ref().unwrap().clone(),
-                transform: Transform::from_translation(chunk_position.center()),
-                ..PbrBundle::default()
-            })
-            .with(chunk);
-        let entity = commands.current_entity().unwrap();
-        self.chunks.insert(chunk_position, entity);
-
-        entity
+        }
+    }
+    /// chunks within the load distance are loaded, chunks beyond the unload distance are unloaded (in chunks)
+    pub fn set_distances(&mut self, load_distance: f32, unload_distance: f32) {
+        self.chunk_loading_distance = load_distance;
+        self.chunk_discard_distance = unload_distance;
+    }
+    pub fn texture_path(&self) -> PathBuf {
+        let mut path = self.asset_folder.clone();
+        path.push("textures.png");
+        path
+    }
+    /// the material of the block textures, None until the chunk manager is initialized
+    pub fn texture_atlas(&self) -> Option<Handle<StandardMaterial>> {
+        self.texture_atlas.clone()
+    }
+    /// the material of the terrain, None until the chunk manager is initialized
+    pub fn chunk_material(&self) -> Option<Handle<ChunkMaterial>> {
+        self.chunk_material.clone()
+    }
+    pub fn provider(&self) -> &(dyn Provider + Send + Sync) {
+        &*self.provider
+    }
+    pub fn provider_mut(&mut self) -> &mut (dyn Provider + Send + Sync) {
+        &mut *self.provider
     }
     pub fn update(&mut self, commands: &mut Commands, meshes: ResMut<Assets<Mesh>>, new_position: ChunkPosition) {
 
+    }
+    pub fn is_loaded(&self, position: ChunkPosition) -> bool {
+        self.chunks.contains_key(&position)
+    }
+    pub fn loaded_chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+    /// the number of chunk meshes in the mesh assets
+    pub fn current_meshes(&self) -> isize {
+        self.current_meshes
+    }
+    pub fn loaded_chunks(&self) -> impl Iterator<Item=ChunkPosition> + '_ {
+        self.chunks.keys().cloned()
+    }
+    pub fn player_chunk(&self) -> ChunkPosition {
+        self.player_chunk
+    }
+    pub fn chunk_data<'a>(&self, position: ChunkPosition, query: &'a Query<(&Chunk,)>) -> Option<&'a ChunkData> {
+        Some(&query.get_component::<Chunk>(*self.chunks.get(&position)?).ok()?.data)
     }
     pub fn get<'a>(&self, position: BlockPosition, query: &'a Query<(&Chunk,)>) -> Option<&'a BlockInner> {
         let (block, chunk) = position.local();
@@ -208,6 +287,13 @@ impl ChunkManager {
         let (block, chunk) = position.local();
         unsafe {Some(query.get_component::<Chunk>(*self.chunks.get(&chunk)?).ok()?.data.get_unchecked(block))}
     }
+    /// the first block hit by the ray within the given reach, tested against the colliders of the blocks
+    pub fn raycast(&self, ray: Ray, reach: f32, blocks: &StaticBlocks, query: &Query<(&Chunk,)>) -> Option<RaycastHit> {
+        raycast_by(ray, reach, blocks, |position|self.get(position, query).cloned())
+    }
+    pub fn raycast_with_mut(&self, ray: Ray, reach: f32, blocks: &StaticBlocks, query: &mut Query<(&mut Chunk,)>) -> Option<RaycastHit> {
+        raycast_by(ray, reach, blocks, |position|self.get_with_mut(position, query).cloned())
+    }
     /// the caller must ensure that blockupdates are handled correctly!
     fn get_mut<'a>(&self, position: BlockPosition, query: &'a mut Query<(&mut Chunk,)>) -> Option<MutBlock<'a>> {
         let (block, chunk) = position.local();
@@ -221,34 +307,50 @@ impl ChunkManager {
 
         if let Some(mut block_ref) = self.get_mut(position, query) {
             *block_ref = block;
-
-
-
-            let (block_position, chunk) = position.local();
-            self.chunk_rerender.insert(chunk);
-            if block_position.x == 0 {
-                self.chunk_rerender.insert(position.with_x(-1).chunk());
-            }
-            if block_position.x == MAX_CHILD {
-                self.chunk_rerender.insert(position.with_x(1).chunk());
-            }
-            if block_position.y == 0 {
-                self.chunk_rerender.insert(position.with_y(-1).chunk());
-            }
-            if block_position.y == MAX_CHILD {
-                self.chunk_rerender.insert(position.with_y(1).chunk());
-            }
-            if block_position.z == 0 {
-                self.chunk_rerender.insert(position.with_z(-1).chunk());
-            }
-            if block_position.z == MAX_CHILD {
-                self.chunk_rerender.insert(position.with_z(1).chunk());
-            }
-
-            self.provider.apply_chunk_update(ChunkUpdate::BlockUpdate(position, block));
+            self.block_changed(position, block);
         }
         //self.block_updates.extend(position.adjacent());
     }
+    /// like `set`, but for exclusive systems, returns false if the chunk isnt loaded
+    pub fn set_in_world(&mut self, position: BlockPosition, block: BlockInner, world: &mut World) -> bool {
+        let (local, chunk) = position.local();
+        let entity = match self.chunks.get(&chunk) {
+            Some(entity) => *entity,
+            None => return false,
+        };
+        match world.get_mut::<Chunk>(entity) {
+            Ok(mut chunk) => chunk.data[local] = block,
+            Err(_) => return false,
+        }
+        self.block_changed(position, block);
+        true
+    }
+    /// rerenders the chunk and its neighbours at the block, saves and announces the change
+    fn block_changed(&mut self, position: BlockPosition, block: BlockInner) {
+        let (block_position, chunk) = position.local();
+        self.chunk_rerender.insert(chunk);
+        if block_position.x == 0 {
+            self.chunk_rerender.insert(position.with_x(-1).chunk());
+        }
+        if block_position.x == MAX_CHILD {
+            self.chunk_rerender.insert(position.with_x(1).chunk());
+        }
+        if block_position.y == 0 {
+            self.chunk_rerender.insert(position.with_y(-1).chunk());
+        }
+        if block_position.y == MAX_CHILD {
+            self.chunk_rerender.insert(position.with_y(1).chunk());
+        }
+        if block_position.z == 0 {
+            self.chunk_rerender.insert(position.with_z(-1).chunk());
+        }
+        if block_position.z == MAX_CHILD {
+            self.chunk_rerender.insert(position.with_z(1).chunk());
+        }
+
+        self.provider.apply_chunk_update(ChunkUpdate::BlockUpdate(position, block));
+        self.events.push(ChunkEvent::BlockChanged(position, block));
+    }
     /*pub fn get_present_blocks<'a>(&'a self, positions: GridSnap, query: &'a Query<'a, (&'a Chunk,)>) -> Blocks<'a> {
         Blocks {
             chunks: self,
@@ -283,6 +385,58 @@ impl<'a> Iterator for Blocks<'a> {
     }
 }*/
 
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct RaycastHit {
+    pub position: BlockPosition,
+    /// the normal of the face that was hit
+    pub normal: BlockVector,
+    pub point: Vec3,
+    pub distance: f32,
+}
+
+impl RaycastHit {
+    /// the block in front of the hit face
+    pub fn adjacent(&self) -> BlockPosition {
+        self.position + self.normal
+    }
+}
+
+fn raycast_by(ray: Ray, reach: f32, blocks: &StaticBlocks, mut get: impl FnMut(BlockPosition) -> Option<BlockInner>) -> Option<RaycastHit> {
+    let mut grid_snap = ray.grid_snap();
+
+    while let Some(position) = grid_snap.next() {
+        if grid_snap.entry_distance() > reach {
+            return None;
+        }
+        let block = match get(position) {
+            Some(block) => block,
+            //unloaded chunk
+            None => continue,
+        };
+        let lower = position.lower_corner();
+
+        let hit = blocks[block.btype as usize].2.get_collider(block.data).iter()
+            .filter_map(|collider|ray.hit_info(&collider.translated(lower)))
+            .filter(|info|info.end() >= 0.0)
+            .min_by(|a, b|a.start().total_cmp(&b.start()));
+
+        if let Some(info) = hit {
+            let distance = info.start().max(0.0);
+            return if distance <= reach {
+                Some(RaycastHit {
+                    position,
+                    normal: info.entering_face_normal(),
+                    point: ray.at(distance),
+                    distance,
+                })
+            } else {
+                None
+            };
+        }
+    }
+    None
+}
+
 pub struct MutBlock<'a> {
     inner: Mut<'a, Chunk>,
     position: BlockVector,
@@ -306,9 +460,10 @@ pub fn init_chunks(commands: &mut Commands,
                    resources: Res<AssetServer>,
                    mut registry: ResMut<ChunkManager>,
                    mut textures: ResMut<Assets<StandardMaterial>>,
+                   mut chunk_materials: ResMut<Assets<ChunkMaterial>>,
                    mut meshes: ResMut<Assets<Mesh>>,
                    ) {
-    registry.init(commands, resources, textures, meshes);
+    registry.init(commands, resources, textures, chunk_materials, meshes);
 }
 
 pub fn update_chunk_scope(
@@ -316,6 +471,7 @@ pub fn update_chunk_scope(
     mut manager: ResMut<ChunkManager>,
     mut chunks: Query<(&mut Chunk,)>,
     player: Query<(&Transform, &PlayerMovement)>,
+    settings: Res<Settings>,
 ) {
     for (transform, options) in player.iter() {
         manager.player_chunk = ChunkPosition::from(transform.translation);
@@ -338,8 +494,10 @@ pub fn update_chunk_scope(
             }
         });
 
+        let lod_distance = settings.game_settings.lod_distance;
         for position in load.iter() {
-            manager.load_chunk(commands, *position);
+            let lod = lod_level(*position, manager.player_chunk, lod_distance);
+            manager.load_chunk(commands, *position, lod);
         }
 
 
@@ -353,6 +511,8 @@ pub fn update_chunk_scope(
 
             if chunk.position.center().distance_squared(transform.translation) >= unload_dist_square {
                 commands.despawn(manager.chunks.remove(&chunk.position).unwrap());
+                manager.provider.unload_chunk(chunk.position);
+                manager.events.push(ChunkEvent::Unloaded(chunk.position));
             } else {
                 let mut changed = false;
                 let mut complete = true;
@@ -419,20 +579,59 @@ pub fn update_chunk_scope(
                 if changed && complete {
                     manager.chunk_rerender.insert(chunk.position);
                 }
+
+                let lod = lod_level(chunk.position, manager.player_chunk, lod_distance);
+                if chunk.lod != lod {
+                    chunk.lod = lod;
+                    //the neighbours add or remove their border faces
+                    for side in [(1, 0, 0), (-1, 0, 0), (0, 1, 0), (0, -1, 0), (0, 0, 1), (0, 0, -1)].iter() {
+                        let neighbour = chunk.position.with_x(side.0).with_y(side.1).with_z(side.2);
+                        if manager.chunks.contains_key(&neighbour) {
+                            manager.chunk_rerender.insert(neighbour);
+                        }
+                    }
+                    manager.chunk_rerender.insert(chunk.position);
+                }
             }
         }
     }
 }
 
+pub fn send_chunk_events(mut manager: ResMut<ChunkManager>, mut events: ResMut<Events<ChunkEvent>>) {
+    for event in manager.events.drain(..) {
+        events.send(event);
+    }
+}
+
+/// The chunk meshes built on the CPU in the last frame that built meshes.
+///
+/// The upload of the meshes to the GPU happens later in the render stage and isnt part of these numbers.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct ChunkMeshStats {
+    pub meshes: usize,
+    /// the size of the vertices and indices, calculated from their counts
+    pub bytes: usize,
+    /// the time to mesh the chunks and add the meshes to the assets
+    pub cpu_time: Duration,
+}
+
+impl ChunkMeshStats {
+    pub fn bytes_per_mesh(&self) -> usize {
+        self.bytes / self.meshes.max(1)
+    }
+}
+
 pub fn update_chunk_mesh(
     mut meshes: ResMut<Assets<Mesh>>,
     mut manager: ResMut<ChunkManager>,
-    mut chunks: Query<(&Chunk, &mut Handle<Mesh>)>,
+    mut stats: ResMut<ChunkMeshStats>,
+    mut chunks: Query<(&Chunk, &mut Handle<Mesh>, &mut Connectivity)>,
     res: Res<StaticBlocksRes>,
     time: Res<Time>,
     adjacent: Query<(&Chunk,)>,
 ) {
     let mut change = 0;
+    let mut built = ChunkMeshStats::default();
     /*if ((time.delta_seconds_f64() + time.time_since_startup().as_secs_f64()) as u64) > time.time_since_startup().as_secs_f64() as u64 {
         //Print numbers:
         println!("{} Chunks, {} Meshes, {} Chunk Entities!", manager.chunks.len(), meshes.len(), chunks.iter_mut().len());
@@ -442,20 +641,29 @@ pub fn update_chunk_mesh(
 
         if let Some(entity) = manager.chunks
             .get(position) {
-            if let Ok((chunk, mut handle)) = chunks.get_mut(*entity) {
+            if let Ok((chunk, mut handle, mut connectivity)) = chunks.get_mut(*entity) {
                 //This is ok, we are only accsessing further Chunks immutably
                 if handle.is_strong() {
                     meshes.remove(handle.clone());
                     change -= 1;
                 }
-                let mut builder = BevyChunkMeshBuilder::empty();
-                create_chunk_mesh(chunk, &adjacent, &**res, &mut builder);
-
+                let start = Instant::now();
+                let mut builder = PackedChunkMeshBuilder::empty();
+                if chunk.lod > 0 {
+                    create_lod_mesh(chunk, &adjacent, &**res, &mut builder);
+                } else {
+                    create_chunk_mesh(chunk, &adjacent, &**res, &mut builder);
+                }
+                *connectivity = Connectivity::of(&chunk.data);
 
-                if let Some(mesh) = Mesh::from_builder(builder) {
+                let bytes = builder.bytes();
+                if let Some(mesh) = builder.build() {
                     // Some -> Some just update the mesh
                     *handle = meshes.add(mesh);
                     change += 1;
+                    built.meshes += 1;
+                    built.bytes += bytes;
+                    built.cpu_time += start.elapsed();
                 } else {
                     // Some -> None remove the mesh
                     *handle = Handle::default();
@@ -468,4 +676,53 @@ pub fn update_chunk_mesh(
     }
     manager.current_meshes += change;
     manager.chunk_rerender.clear();
-}
\ No newline at end of file
+    if built.meshes > 0 {
+        *stats = built;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::block_inner::STONE;
+    use crate::world::block_types::get_block_types;
+
+    fn raycast(origin: Vec3, direction: Vec3, reach: f32, stone: &[(i64, i64, i64)]) -> Option<RaycastHit> {
+        let stone: HashSet<BlockPosition> = stone.iter().map(|(x, y, z)|BlockPosition::new(*x, *y, *z)).collect();
+        raycast_by(Ray::new(origin, direction), reach, &get_block_types(), |position|{
+            Some(if stone.contains(&position) { STONE } else { AIR })
+        })
+    }
+
+    #[test]
+    fn raycast_hits_along_an_axis() {
+        let hit = raycast(Vec3::new(0.5, 0.5, 0.5), Vec3::unit_x(), 5.0, &[(3, 0, 0)]).unwrap();
+        assert_eq!(hit.position, BlockPosition::new(3, 0, 0));
+        assert_eq!(hit.normal, BlockVector::new(-1, 0, 0));
+        assert_eq!(hit.adjacent(), BlockPosition::new(2, 0, 0));
+        assert!((hit.distance - 2.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn raycast_hits_diagonally() {
+        let hit = raycast(Vec3::new(0.2, 0.5, 0.5), Vec3::new(1.0, 0.5, 0.0), 5.0, &[(3, 1, 0)]).unwrap();
+        assert_eq!(hit.position, BlockPosition::new(3, 1, 0));
+        assert_eq!(hit.normal, BlockVector::new(-1, 0, 0));
+        assert!((hit.point - Vec3::new(3.0, 1.9, 0.5)).length() < 1e-4);
+    }
+
+    #[test]
+    fn raycast_hits_in_negative_direction() {
+        let hit = raycast(Vec3::new(0.5, 0.5, 0.5), -Vec3::unit_y(), 5.0, &[(0, -2, 0)]).unwrap();
+        assert_eq!(hit.position, BlockPosition::new(0, -2, 0));
+        assert_eq!(hit.normal, BlockVector::new(0, 1, 0));
+        assert!((hit.distance - 1.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn raycast_stops_at_the_reach() {
+        assert!(raycast(Vec3::new(0.5, 0.5, 0.5), Vec3::unit_x(), 2.0, &[(3, 0, 0)]).is_none());
+        assert!(raycast(Vec3::new(0.5, 0.5, 0.5), Vec3::unit_x(), 2.5, &[(3, 0, 0)]).is_some());
+        assert!(raycast(Vec3::new(0.5, 0.5, 0.5), Vec3::unit_x(), 5.0, &[]).is_none());
+    }
+}
@@ -1,10 +1,11 @@
-use bevy::render::mesh::{Mesh, Indices};
+use bevy::render::mesh::{Mesh, Indices, VertexAttributeValues};
 use crate::world::chunk::Chunk;
 use bevy::render::pipeline::PrimitiveTopology;
 use bevy::prelude::*;
-use crate::world::block_inner::{Side, BLOCK_MESH};
-use crate::world::coordinates::BlockVector;
-use crate::world::block_types::{BlockLook, StaticBlocks};
+use crate::world::block_inner::{BlockInner, Side, Sides, SIDES, BLOCK_MESH};
+use crate::world::coordinates::{BlockVector, CHUNK_SIZE};
+use crate::world::block_types::{BlockLook, StaticBlocks, MAX_LAYERS, layer_count};
+use crate::entities::spawning::SKY_LIGHT;
 
 pub const EMPTY: VisibleDirection = VisibleDirection(0);
 pub const Y_POS: VisibleDirection = VisibleDirection(1);
@@ -56,6 +57,11 @@ impl ChunkMesh for Mesh {
 pub trait ChunkMeshBuilder {
     fn empty() -> Self;
     fn add_face(&mut self, face: Face, position: Vec3);
+    /// adds a face with the ambient occlusion of its corners (0 is open, 3 is enclosed) and its sky light,
+    /// builders without shading add a plain face
+    fn add_shaded_face(&mut self, face: Face, position: Vec3, occlusion: [u8; 4], light: u8) {
+        self.add_face(face, position);
+    }
 }
 
 pub struct Face {
@@ -80,6 +86,19 @@ impl Face{
             uv_index,
         }
     }
+    /// the face of a cube with the given size instead of a unit cube, the texture is stretched
+    pub fn scaled(self, size: f32) -> Self {
+        self.stretched(Vec3::splat(size))
+    }
+    /// the face of a box with the given size instead of a unit cube, the texture is stretched
+    pub fn stretched(self, size: Vec3) -> Self {
+        Face {
+            start: self.start * size,
+            ax1: self.ax1 * size,
+            ax2: self.ax2 * size,
+            ..self
+        }
+    }
 }
 
 pub struct BevyChunkMeshBuilder {
@@ -89,6 +108,15 @@ pub struct BevyChunkMeshBuilder {
     pub(crate) indices: Vec<u16>,
 }
 
+impl BevyChunkMeshBuilder {
+    /// the size of the vertices and indices in bytes
+    pub fn bytes(&self) -> usize {
+        (self.verticies.len() + self.normals.len()) * std::mem::size_of::<[f32; 3]>()
+            + self.uvs.len() * std::mem::size_of::<[f32; 2]>()
+            + self.indices.len() * std::mem::size_of::<u16>()
+    }
+}
+
 impl ChunkMeshBuilder for BevyChunkMeshBuilder {
     fn empty() -> Self {
         BevyChunkMeshBuilder {
@@ -144,6 +172,180 @@ impl ChunkMeshBuilder for BevyChunkMeshBuilder {
     }
 }
 
+/// the vertex attribute of the `PackedChunkMeshBuilder`
+pub const ATTRIBUTE_PACKED: &str = "Vertex_Packed";
+
+/// A chunk mesh builder for the chunk pipeline with 8 bytes per vertex.
+///
+/// The first word holds the position in eighth blocks from -8 to 24 (8 bits per axis), the side (3 bits),
+/// the ambient occlusion (2 bits) and the corner of the texture (2 bits).
+/// The second word holds the layer of the texture array (16 bits) and the sky light (4 bits).
+pub struct PackedChunkMeshBuilder {
+    vertices: Vec<[u32; 2]>,
+    indices: Vec<u16>,
+}
+
+fn pack_position(position: Vec3) -> u32 {
+    let axis = |value: f32|((value + 8.0) * 8.0).round().max(0.0).min(255.0) as u32;
+    axis(position.x) | axis(position.y) << 8 | axis(position.z) << 16
+}
+
+fn pack_side(normal: Vec3) -> u32 {
+    SIDES.iter()
+        .find(|side|cube_face(**side, 0).normal == normal)
+        .map_or(0, |side|*side as u32)
+}
+
+impl PackedChunkMeshBuilder {
+    /// the size of the vertices and indices in bytes
+    pub fn bytes(&self) -> usize {
+        self.vertices.len() * std::mem::size_of::<[u32; 2]>() + self.indices.len() * std::mem::size_of::<u16>()
+    }
+    pub fn build(self) -> Option<Mesh> {
+        if self.indices.is_empty() {
+            return None;
+        }
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.set_attribute(ATTRIBUTE_PACKED, VertexAttributeValues::Uint2(self.vertices));
+        mesh.set_indices(Some(Indices::U16(self.indices)));
+        Some(mesh)
+    }
+}
+
+impl ChunkMeshBuilder for PackedChunkMeshBuilder {
+    fn empty() -> Self {
+        PackedChunkMeshBuilder {
+            vertices: Vec::new(),
+            indices: Vec::new(),
+        }
+    }
+
+    fn add_face(&mut self, face: Face, position: Vec3) {
+        self.add_shaded_face(face, position, [0; 4], SKY_LIGHT);
+    }
+
+    fn add_shaded_face(&mut self, face: Face, position: Vec3, occlusion: [u8; 4], light: u8) {
+        let next_index = self.vertices.len() as u16;
+        let start = face.start + position;
+        let side = pack_side(face.normal) << 24;
+        //the same corners as in the BevyChunkMeshBuilder
+        let corners = [start, start + face.ax1, start + face.ax2, start + face.ax1 + face.ax2];
+        for (corner, (vertex, occlusion)) in corners.iter().zip(occlusion.iter()).enumerate() {
+            self.vertices.push([
+                pack_position(*vertex) | side | (*occlusion as u32).min(3) << 27 | (corner as u32) << 29,
+                face.uv_index & 0xffff | (light as u32).min(15) << 16,
+            ]);
+        }
+
+        //the quad is split along the darker diagonal, so the occlusion is interpolated symmetrically
+        if occlusion[0] + occlusion[3] > occlusion[1] + occlusion[2] {
+            self.indices.extend_from_slice(&[next_index, next_index + 2, next_index + 3, next_index, next_index + 3, next_index + 1]);
+        } else {
+            self.indices.extend_from_slice(&[next_index, next_index + 2, next_index + 1, next_index + 1, next_index + 2, next_index + 3]);
+        }
+    }
+}
+
+/// the occlusion of the corners of a face of the block, blocking is true for the blocks around it with a block mesh
+pub fn corner_occlusion(face: &Face, block: BlockVector, blocking: impl Fn(BlockVector) -> bool) -> [u8; 4] {
+    let to_vector = |vector: Vec3|BlockVector::new(vector.x.round() as i64, vector.y.round() as i64, vector.z.round() as i64);
+    let front = block + to_vector(face.normal);
+    let mut occlusion = [0; 4];
+    let corners = [face.start, face.start + face.ax1, face.start + face.ax2, face.start + face.ax1 + face.ax2];
+    for (corner, occlusion) in corners.iter().zip(occlusion.iter_mut()) {
+        //the corner is at 0 or 1 on both axes of the face, the blocks next to it are in that direction
+        let direction = |value: f32|if value > 0.5 { 1 } else { -1 };
+        let (first, second) = if face.normal.x != 0.0 {
+            (BlockVector::new(0, direction(corner.y), 0), BlockVector::new(0, 0, direction(corner.z)))
+        } else if face.normal.y != 0.0 {
+            (BlockVector::new(direction(corner.x), 0, 0), BlockVector::new(0, 0, direction(corner.z)))
+        } else {
+            (BlockVector::new(direction(corner.x), 0, 0), BlockVector::new(0, direction(corner.y), 0))
+        };
+        let (first, second, diagonal) = (blocking(front + first), blocking(front + second), blocking(front + first + second));
+        *occlusion = if first && second {
+            3
+        } else {
+            first as u8 + second as u8 + diagonal as u8
+        };
+    }
+    occlusion
+}
+
+/// the face of a unit cube with its lower corner at the origin
+pub fn cube_face(side: Side, texture: u32) -> Face {
+    match side {
+        Side::Top => Face::new(
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(1.0, 1.0, 1.0),
+            Vec3::new(-1.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, -1.0),
+            16, texture
+        ),
+        Side::Front => Face::new(
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(1.0, 1.0, 1.0),
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, -1.0, 0.0),
+            16, texture
+        ),
+        Side::Left => Face::new(
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(0.0, 1.0, 1.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, -1.0, 0.0),
+            16, texture
+        ),
+        Side::Bottom => Face::new(
+            Vec3::new(0.0, -1.0, 0.0),
+            Vec3::zero(),
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            16, texture
+        ),
+        Side::Back => Face::new(
+            Vec3::new(-1.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(0.0, -1.0, 0.0),
+            16, texture
+        ),
+        Side::Right => Face::new(
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec3::new(1.0, 1.0, 0.0),
+            Vec3::new(-1.0, 0.0, 0.0),
+            Vec3::new(0.0, -1.0, 0.0),
+            16, texture
+        ),
+    }
+}
+
+/// the face of a box as high as the layers, the side textures are squeezed
+pub fn layer_face(side: Side, texture: u32, height: f32) -> Face {
+    let mut face = cube_face(side, texture);
+    match side {
+        Side::Top => face.start.y = height,
+        Side::Bottom => {}
+        _ => {
+            face.start.y = height;
+            face.ax2.y = -height;
+        }
+    }
+    face
+}
+
+/// adds all six faces of a unit cube to the builder
+pub fn add_cube<M: ChunkMeshBuilder>(mesh_builder: &mut M, textures: &Sides<u32>, lower: Vec3) {
+    for side in SIDES.iter() {
+        mesh_builder.add_face(cube_face(*side, textures[*side]), lower);
+    }
+}
+
+/// solid blocks hide the faces next to them, blocks without a block mesh (like water) only hide the faces of their own kind
+pub fn hides_face(block: &BlockInner, next: &BlockInner) -> bool {
+    next.info.contains(BLOCK_MESH) || next.btype == block.btype
+}
+
 /// creates a mesh representing the solid blocks of a given chunk
 /// The coordinates are relative to chunk.position.center()
 pub fn create_chunk_mesh<M: ChunkMeshBuilder>(chunk: &Chunk, query: &Query<(&Chunk,)>, blocks: &StaticBlocks, mesh_builder: &mut M) {
@@ -156,16 +358,62 @@ pub fn create_chunk_mesh<M: ChunkMeshBuilder>(chunk: &Chunk, query: &Query<(&Chu
     let z_positive = chunk.z_positive.and_then(|entity|Some(query.get_component(entity).ok()?));
     let z_negative = chunk.z_negative.and_then(|entity|Some(query.get_component(entity).ok()?));
 
-    let check_block_face = |position: BlockVector, adjacent: Option<&Chunk>| -> bool {
+    let neighbour = |position: BlockVector, adjacent: Option<&Chunk>| -> Option<BlockInner> {
         unsafe {
             if position.fits() {
-                !chunk_data.get_unchecked(position).info.contains(BLOCK_MESH)
+                Some(*chunk_data.get_unchecked(position))
             } else {
-                adjacent.map(|chunk| chunk.data.get_unchecked(position.chunk_relative()))
-                    .map_or(true, |block|!block.info.contains(BLOCK_MESH))
+                //chunks with another level of detail dont hide the faces, so there are no cracks between them
+                adjacent.filter(|adjacent|adjacent.lod == chunk.lod)
+                    .map(|chunk| *chunk.data.get_unchecked(position.chunk_relative()))
             }
         }
     };
+    let check_block_face = |position: BlockVector, adjacent: Option<&Chunk>| -> bool {
+        neighbour(position, adjacent).map_or(true, |block|!block.info.contains(BLOCK_MESH))
+    };
+
+    //blocks in the corners of the adjacent chunks dont occlude
+    let blocking = |position: BlockVector| -> bool {
+        let outside = [position.x, position.y, position.z].iter()
+            .filter(|value|**value < 0 || **value >= CHUNK_SIZE)
+            .count();
+        let adjacent = match outside {
+            0 => return chunk_data[position].info.contains(BLOCK_MESH),
+            1 if position.x >= CHUNK_SIZE => x_positive,
+            1 if position.x < 0 => x_negative,
+            1 if position.y >= CHUNK_SIZE => y_positive,
+            1 if position.y < 0 => y_negative,
+            1 if position.z >= CHUNK_SIZE => z_positive,
+            1 => z_negative,
+            _ => None,
+        };
+        adjacent.map_or(false, |adjacent|adjacent.data[position.chunk_relative()].info.contains(BLOCK_MESH))
+    };
+
+    //the lowest block of every column open to the sky, the columns of the chunk above are in the way if they have a solid block
+    let mut open_from = [[0; CHUNK_SIZE as usize]; CHUNK_SIZE as usize];
+    for x in 0..CHUNK_SIZE {
+        for z in 0..CHUNK_SIZE {
+            let covered = y_positive.map_or(false, |above|(0..CHUNK_SIZE)
+                .any(|y|above.data[BlockVector::new(x, y, z)].info.contains(BLOCK_MESH)));
+            open_from[x as usize][z as usize] = if covered {
+                CHUNK_SIZE + 1
+            } else {
+                (0..CHUNK_SIZE).rev()
+                    .find(|y|chunk_data[BlockVector::new(x, *y, z)].info.contains(BLOCK_MESH))
+                    .map_or(0, |y|y + 1)
+            };
+        }
+    }
+    let sky_light = |front: BlockVector| -> u8 {
+        if front.x < 0 || front.z < 0 || front.x >= CHUNK_SIZE || front.z >= CHUNK_SIZE
+            || front.y >= open_from[front.x as usize][front.z as usize] {
+            SKY_LIGHT
+        } else {
+            0
+        }
+    };
 
     let center = chunk.position.center();
 
@@ -179,73 +427,95 @@ pub fn create_chunk_mesh<M: ChunkMeshBuilder>(chunk: &Chunk, query: &Query<(&Chu
             BlockLook::CustomMesh => {
                 unimplemented!()
             }
+            BlockLook::Layers{texture} => {
+                let lower = (chunk.position + position).lower_corner() - center;
+                let height = layer_count(block.data) as f32 / MAX_LAYERS as f32;
+
+                for (side, offset, adjacent) in [
+                    (Side::Front, BlockVector::new(1, 0, 0), x_positive),
+                    (Side::Left, BlockVector::new(0, 0, 1), z_positive),
+                    (Side::Bottom, BlockVector::new(0, -1, 0), y_negative),
+                    (Side::Back, BlockVector::new(-1, 0, 0), x_negative),
+                    (Side::Right, BlockVector::new(0, 0, -1), z_negative),
+                ].iter() {
+                    if check_block_face(position + offset, *adjacent) {
+                        mesh_builder.add_face(layer_face(*side, *texture, height), lower);
+                    }
+                }
+                //the top is below the next block, unless the layers are full
+                if height < 1.0 || check_block_face(position + BlockVector::new(0, 1, 0), y_positive) {
+                    mesh_builder.add_face(layer_face(Side::Top, *texture, height), lower);
+                }
+            }
             BlockLook::Cube{ref textures } => {
                 let global_position = chunk.position + position;
                 let lower = global_position.lower_corner() - center;
 
                 //println!("build block: {:?}", position);
 
-                //Up
-                if check_block_face(position.with_y(1), y_positive) {
-                    mesh_builder.add_face(Face::new(
-                        Vec3::new(0.0, 1.0, 0.0),
-                        Vec3::new(1.0, 1.0, 1.0),
-                        Vec3::new(-1.0, 0.0, 0.0),
-                        Vec3::new(0.0, 0.0, -1.0),
-                        16, textures[Side::Top]
-                    ), lower);
-                }
-                //side 1
-                if check_block_face(position.with_x(1), x_positive) {
-                    mesh_builder.add_face(Face::new(
-                        Vec3::new(1.0, 0.0, 0.0),
-                        Vec3::new(1.0, 1.0, 1.0),
-                        Vec3::new(0.0, 0.0, -1.0),
-                        Vec3::new(0.0, -1.0, 0.0),
-                        16, textures[Side::Front]
-                    ), lower);
-                }
-                //side 2
-                if check_block_face(position.with_z(1), z_positive) {
-                    mesh_builder.add_face(Face::new(
-                        Vec3::new(0.0, 0.0, 1.0),
-                        Vec3::new(0.0, 1.0, 1.0),
-                        Vec3::new(1.0, 0.0, 0.0),
-                        Vec3::new(0.0, -1.0, 0.0),
-                        16, textures[Side::Left]
-                    ), lower);
-                }
-                //Down
-                if check_block_face(position.with_y(-1), y_negative) {
-                    mesh_builder.add_face(Face::new(
-                        Vec3::new(0.0, -1.0, 0.0),
-                        Vec3::zero(),
-                        Vec3::new(0.0, 0.0, 1.0),
-                        Vec3::new(1.0, 0.0, 0.0),
-                        16, textures[Side::Bottom]
-                    ), lower);
-                }
-                //side -1
-                if check_block_face(position.with_x(-1), x_negative) {
-                    mesh_builder.add_face(Face::new(
-                        Vec3::new(-1.0, 0.0, 0.0),
-                        Vec3::new(0.0, 1.0, 0.0),
-                        Vec3::new(0.0, 0.0, 1.0),
-                        Vec3::new(0.0, -1.0, 0.0),
-                        16, textures[Side::Back]
-                    ), lower);
-                }
-                //side -2
-                if check_block_face(position.with_z(-1), z_negative) {
-                    mesh_builder.add_face(Face::new(
-                        Vec3::new(0.0, 0.0, -1.0),
-                        Vec3::new(1.0, 1.0, 0.0),
-                        Vec3::new(-1.0, 0.0, 0.0),
-                        Vec3::new(0.0, -1.0, 0.0),
-                        16, textures[Side::Right]
-                    ), lower);
+                for (side, offset, adjacent) in [
+                    (Side::Top, BlockVector::new(0, 1, 0), y_positive),
+                    (Side::Front, BlockVector::new(1, 0, 0), x_positive),
+                    (Side::Left, BlockVector::new(0, 0, 1), z_positive),
+                    (Side::Bottom, BlockVector::new(0, -1, 0), y_negative),
+                    (Side::Back, BlockVector::new(-1, 0, 0), x_negative),
+                    (Side::Right, BlockVector::new(0, 0, -1), z_negative),
+                ].iter() {
+                    if neighbour(position + offset, *adjacent).map_or(true, |next|!hides_face(block, &next)) {
+                        let face = cube_face(*side, textures[*side]);
+                        let occlusion = corner_occlusion(&face, position, &blocking);
+                        mesh_builder.add_shaded_face(face, lower, occlusion, sky_light(position + offset));
+                    }
                 }
             }
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packed_vertices_are_smaller() {
+        let textures = Sides::new([3, 4, 4, 4, 4, 5]);
+        let mut full = BevyChunkMeshBuilder::empty();
+        let mut packed = PackedChunkMeshBuilder::empty();
+        add_cube(&mut full, &textures, Vec3::new(-8.0, 7.0, 0.5));
+        add_cube(&mut packed, &textures, Vec3::new(-8.0, 7.0, 0.5));
+        //32 bytes per vertex before, 8 bytes after, the indices stay the same
+        assert_eq!(full.bytes(), 6 * (4 * 32 + 6 * 2));
+        assert_eq!(packed.bytes(), 6 * (4 * 8 + 6 * 2));
+
+        //the first face is the top, its first corner is at the upper corner of the cube
+        let [first, second] = packed.vertices[0];
+        assert_eq!(first & 0xffffff, 8 | 128 << 8 | 76 << 16);
+        assert_eq!(first >> 24 & 0x7, Side::Top as u32);
+        assert_eq!(second, 3 | (SKY_LIGHT as u32) << 16);
+    }
+
+    #[test]
+    fn blocks_in_front_of_corners_occlude() {
+        let block = BlockVector::new(1, 1, 1);
+        let face = cube_face(Side::Top, 0);
+        assert_eq!(corner_occlusion(&face, block, |_|false), [0; 4]);
+        //a block above the positive x edge
+        let occlusion = corner_occlusion(&face, block, |position|position == BlockVector::new(2, 2, 1));
+        assert_eq!(occlusion, [1, 0, 1, 0]);
+        //two blocks next to a corner enclose it
+        let occlusion = corner_occlusion(&face, block, |position|{
+            position == BlockVector::new(2, 2, 1) || position == BlockVector::new(1, 2, 2)
+        });
+        assert_eq!(occlusion[0], 3);
+    }
+
+    #[test]
+    fn water_hides_only_water() {
+        use crate::world::block_inner::{AIR, STONE, WATER};
+        assert!(hides_face(&WATER, &WATER));
+        assert!(hides_face(&WATER, &STONE));
+        assert!(!hides_face(&WATER, &AIR));
+        assert!(!hides_face(&STONE, &WATER));
+        assert!(hides_face(&STONE, &STONE));
+    }
+}
@@ -5,7 +5,7 @@ use std::fmt::Formatter;
 
 /// A relative position of a block, (mostly relative to a chunk)
 /// This is useful to iterate over a chunk, independent from its position
-#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash)]
 pub struct BlockVector {
     pub(crate) x: i64,
     pub(crate) y: i64,
@@ -138,7 +138,7 @@ impl Neg for BlockVector {
 }
 
 ///The absolute position of a Block
-#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash)]
 pub struct BlockPosition (BlockVector);
 
 impl Display for BlockPosition {
@@ -162,10 +162,10 @@ impl BlockPosition {
         self.0.x
     }
     pub const fn y(&self) -> i64 {
-        self.0.x
+        self.0.y
     }
     pub const fn z(&self) -> i64 {
-        self.0.x
+        self.0.z
     }
     ///adds the given value the the x coordinate of this vector
     pub const fn with_x(&self, x: i64) -> BlockPosition {
@@ -0,0 +1,259 @@
+use bevy::prelude::*;
+use bevy::render::camera::Camera;
+use std::collections::{HashMap, HashSet, VecDeque};
+use crate::player::player::PlayerMovement;
+use crate::world::block_inner::{Side, SIDES, BLOCK_MESH};
+use crate::world::chunk::{Chunk, ChunkData};
+use crate::world::coordinates::{BlockPosition, BlockVector, ChunkPosition, CHUNK_SIZE, MAX_CHILD};
+
+fn offset(side: Side) -> BlockVector {
+    match side {
+        Side::Top => BlockVector::new(0, 1, 0),
+        Side::Front => BlockVector::new(1, 0, 0),
+        Side::Left => BlockVector::new(0, 0, 1),
+        Side::Bottom => BlockVector::new(0, -1, 0),
+        Side::Back => BlockVector::new(-1, 0, 0),
+        Side::Right => BlockVector::new(0, 0, -1),
+    }
+}
+
+fn opposite(side: Side) -> Side {
+    match side {
+        Side::Top => Side::Bottom,
+        Side::Front => Side::Back,
+        Side::Left => Side::Right,
+        Side::Bottom => Side::Top,
+        Side::Back => Side::Front,
+        Side::Right => Side::Left,
+    }
+}
+
+fn neighbour(position: ChunkPosition, side: Side) -> ChunkPosition {
+    let offset = offset(side);
+    position.with_x(offset.x).with_y(offset.y).with_z(offset.z)
+}
+
+/// the sides of the chunk the block touches as a bit set
+fn touched_sides(position: BlockVector) -> u8 {
+    SIDES.iter()
+        .filter(|side|!(position + offset(**side)).fits())
+        .fold(0, |sides, side|sides | 1 << *side as u8)
+}
+
+/// Which sides of a chunk are connected through blocks without a block mesh.
+///
+/// Computed when the chunk is meshed, chunks without a mesh yet connect all sides.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct Connectivity([u8; 6]);
+
+impl Default for Connectivity {
+    fn default() -> Self {
+        Connectivity([0b111111; 6])
+    }
+}
+
+impl Connectivity {
+    pub fn of(data: &ChunkData) -> Self {
+        let mut connectivity = Connectivity([0; 6]);
+        let mut visited = vec![false; (CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE) as usize];
+        let index = |position: BlockVector|((position.x * CHUNK_SIZE + position.y) * CHUNK_SIZE + position.z) as usize;
+        let mut stack = Vec::new();
+
+        //every group of connected open blocks connects all sides it touches
+        for (start, block) in data.iter() {
+            if block.info.contains(BLOCK_MESH) || visited[index(start)] {
+                continue;
+            }
+            visited[index(start)] = true;
+            stack.push(start);
+            let mut sides = 0;
+            while let Some(position) = stack.pop() {
+                sides |= touched_sides(position);
+                for side in SIDES.iter() {
+                    let next = position + offset(*side);
+                    if next.fits() && !visited[index(next)] && !data[next].info.contains(BLOCK_MESH) {
+                        visited[index(next)] = true;
+                        stack.push(next);
+                    }
+                }
+            }
+            for side in SIDES.iter() {
+                if sides & 1 << *side as u8 != 0 {
+                    connectivity.0[*side as usize] |= sides;
+                }
+            }
+        }
+        connectivity
+    }
+    /// true if the view can pass the chunk from one side to the other
+    pub fn connects(&self, from: Side, to: Side) -> bool {
+        self.0[from as usize] & 1 << to as u8 != 0
+    }
+}
+
+/// The chunks reachable from the camera chunk through connected sides.
+///
+/// The search never turns back, so a chunk is only reached through open sides facing away from the camera.
+/// If the camera chunk isnt loaded, every chunk is reachable.
+pub fn reachable_chunks(camera: ChunkPosition, chunks: &HashMap<ChunkPosition, Connectivity>) -> HashSet<ChunkPosition> {
+    if !chunks.contains_key(&camera) {
+        return chunks.keys().cloned().collect();
+    }
+    let mut reached = HashSet::new();
+    reached.insert(camera);
+    //the chunk, the side it was entered through and the directions of the path to it
+    let mut queue = VecDeque::new();
+    queue.push_back((camera, None, 0u8));
+    while let Some((position, entered, directions)) = queue.pop_front() {
+        let connectivity: &Connectivity = &chunks[&position];
+        for side in SIDES.iter() {
+            if directions & 1 << opposite(*side) as u8 != 0 {
+                continue;
+            }
+            if let Some(entered) = entered {
+                if !connectivity.connects(entered, *side) {
+                    continue;
+                }
+            }
+            let next = neighbour(position, *side);
+            if chunks.contains_key(&next) && reached.insert(next) {
+                queue.push_back((next, Some(opposite(*side)), directions | 1 << *side as u8));
+            }
+        }
+    }
+    reached
+}
+
+/// The planes of the view frustum, the normals point inside
+pub struct Frustum {
+    planes: [(Vec3, f32); 6],
+}
+
+impl Frustum {
+    /// the frustum of a view projection matrix with a depth range from 0 to 1
+    pub fn from_matrix(view_projection: Mat4) -> Self {
+        let m = view_projection.to_cols_array();
+        let row = |i: usize|[m[i], m[4 + i], m[8 + i], m[12 + i]];
+        let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+        let plane = |a: [f32; 4], sign: f32, b: [f32; 4]|{
+            let normal = Vec3::new(a[0] + sign * b[0], a[1] + sign * b[1], a[2] + sign * b[2]);
+            let length = normal.length();
+            (normal / length, (a[3] + sign * b[3]) / length)
+        };
+        Frustum {
+            planes: [
+                plane(w, 1.0, x),
+                plane(w, -1.0, x),
+                plane(w, 1.0, y),
+                plane(w, -1.0, y),
+                plane(z, 0.0, z),
+                plane(w, -1.0, z),
+            ],
+        }
+    }
+    /// false if the box is completely outside of one plane
+    pub fn intersects(&self, lower: Vec3, higher: Vec3) -> bool {
+        self.planes.iter().all(|(normal, distance)|{
+            //the corner furthest inside
+            let corner = Vec3::new(
+                if normal.x >= 0.0 { higher.x } else { lower.x },
+                if normal.y >= 0.0 { higher.y } else { lower.y },
+                if normal.z >= 0.0 { higher.z } else { lower.z },
+            );
+            normal.dot(corner) + distance >= 0.0
+        })
+    }
+}
+
+/// The results of the culling in the last frame
+#[derive(Copy, Clone, Default, Debug)]
+pub struct CullingStats {
+    pub drawn: usize,
+    pub frustum_culled: usize,
+    /// chunks in the frustum hidden behind solid chunks
+    pub cave_culled: usize,
+}
+
+/// hides the chunks outside of the view and the chunks not reachable from the camera through open blocks
+pub fn cull_chunks(
+    mut stats: ResMut<CullingStats>,
+    cameras: Query<(&Camera, &GlobalTransform), With<PlayerMovement>>,
+    mut chunks: Query<(&Chunk, &Connectivity, &mut Visible)>,
+) {
+    let (camera, transform) = match cameras.iter().next() {
+        Some(camera) => camera,
+        None => return,
+    };
+    let frustum = Frustum::from_matrix(camera.projection_matrix * transform.compute_matrix().inverse());
+    let connectivity: HashMap<ChunkPosition, Connectivity> = chunks.iter_mut()
+        .map(|(chunk, connectivity, _)|(chunk.position, *connectivity))
+        .collect();
+    let reachable = reachable_chunks(BlockPosition::from_vector(transform.translation).chunk(), &connectivity);
+
+    *stats = CullingStats::default();
+    for (chunk, _, mut visible) in chunks.iter_mut() {
+        let lower = chunk.position.lowest().lower_corner();
+        let higher = (chunk.position + BlockVector::new(MAX_CHILD, MAX_CHILD, MAX_CHILD)).higher_corner();
+        let shown = if !frustum.intersects(lower, higher) {
+            stats.frustum_culled += 1;
+            false
+        } else if !reachable.contains(&chunk.position) {
+            stats.cave_culled += 1;
+            false
+        } else {
+            stats.drawn += 1;
+            true
+        };
+        if visible.is_visible != shown {
+            visible.is_visible = shown;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::block_inner::{AIR, STONE};
+
+    #[test]
+    fn tunnels_connect_their_ends() {
+        assert!(Connectivity::of(&ChunkData::filled(AIR)).connects(Side::Top, Side::Bottom));
+        assert!(!Connectivity::of(&ChunkData::filled(STONE)).connects(Side::Front, Side::Back));
+
+        let mut data = ChunkData::filled(STONE);
+        for x in 0..CHUNK_SIZE {
+            data[BlockVector::new(x, 5, 5)] = AIR;
+        }
+        let connectivity = Connectivity::of(&data);
+        assert!(connectivity.connects(Side::Front, Side::Back));
+        assert!(connectivity.connects(Side::Back, Side::Front));
+        assert!(!connectivity.connects(Side::Top, Side::Bottom));
+        assert!(!connectivity.connects(Side::Front, Side::Top));
+    }
+
+    #[test]
+    fn solid_chunks_hide_the_chunks_behind() {
+        let open = Connectivity::default();
+        let solid = Connectivity([0; 6]);
+        let mut chunks = HashMap::new();
+        for x in 0..4 {
+            chunks.insert(ChunkPosition::new(x, 0, 0), if x == 1 { solid } else { open });
+        }
+        let reachable = reachable_chunks(ChunkPosition::new(0, 0, 0), &chunks);
+        assert!(reachable.contains(&ChunkPosition::new(1, 0, 0)));
+        assert!(!reachable.contains(&ChunkPosition::new(2, 0, 0)));
+
+        //unknown camera chunks dont hide anything
+        assert_eq!(reachable_chunks(ChunkPosition::new(0, 5, 0), &chunks).len(), 4);
+    }
+
+    #[test]
+    fn chunks_behind_the_camera_are_outside_of_the_frustum() {
+        let projection = Mat4::perspective_rh(std::f32::consts::FRAC_PI_4, 1.0, 0.1, 1000.0);
+        //the camera is at the origin looking along -z
+        let frustum = Frustum::from_matrix(projection);
+        assert!(frustum.intersects(Vec3::new(-8.0, -8.0, -40.0), Vec3::new(8.0, 8.0, -24.0)));
+        assert!(!frustum.intersects(Vec3::new(-8.0, -8.0, 24.0), Vec3::new(8.0, 8.0, 40.0)));
+        assert!(!frustum.intersects(Vec3::new(200.0, -8.0, -40.0), Vec3::new(216.0, 8.0, -24.0)));
+    }
+}
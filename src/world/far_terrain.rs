@@ -0,0 +1,183 @@
+use bevy::prelude::*;
+use std::collections::{HashMap, HashSet};
+use crate::settings::Settings;
+use crate::player::player::PlayerMovement;
+use crate::content::world_generation::{height_map, surface_block};
+use crate::renderer::chunk_material::chunk_pipelines;
+use crate::world::block_inner::{BlockInner, Side, AIR};
+use crate::world::block_types::{BlockLook, StaticBlocks, StaticBlocksRes};
+use crate::world::chunk::ChunkManager;
+use crate::world::chunk_mesh::{PackedChunkMeshBuilder, ChunkMeshBuilder, cube_face};
+use crate::world::coordinates::{ChunkPosition, SurfaceCoordinates, CHUNK_SIZE};
+
+/// the width of the cells of the far terrain in blocks
+const CELL_SIZE: i64 = 4;
+const CELLS: i64 = CHUNK_SIZE / CELL_SIZE;
+/// the far terrain is lowered below the generated surface, so loaded chunks cover it where both are drawn
+const SINK: f32 = 0.5;
+/// the number of far terrain columns meshed in one frame
+const COLUMNS_PER_FRAME: usize = 32;
+
+/// A chunk column of the far terrain, drawn instead of the generated terrain while it isnt loaded
+pub struct FarTerrainColumn;
+
+/// The spawned far terrain columns
+#[derive(Default)]
+pub struct FarTerrain {
+    columns: HashMap<SurfaceCoordinates, Entity>,
+}
+
+/// The generated surface of the cells of a column and of the bordering cells of the neighbouring columns,
+/// indexed by the cell coordinates plus one
+pub struct SurfaceCells(Vec<Vec<(i64, BlockInner)>>);
+
+impl SurfaceCells {
+    /// the surface is read from the height noise without generating the chunks, the terrain chunk has y 0
+    pub fn generate(seed: u64, terrain: ChunkPosition) -> Self {
+        let maps: HashMap<(i64, i64), _> = [(0, 0), (1, 0), (-1, 0), (0, 1), (0, -1)].iter()
+            .map(|(x, z)|((*x, *z), height_map(seed, terrain.with_x(*x).with_z(*z))))
+            .collect();
+        let sample = |x: i64, z: i64|{
+            //the corners arent next to a cell of the column
+            maps.get(&(x.div_euclid(CELLS), z.div_euclid(CELLS))).map_or((0, AIR), |map|{
+                let (x, z) = (x.rem_euclid(CELLS) * CELL_SIZE + CELL_SIZE / 2, z.rem_euclid(CELLS) * CELL_SIZE + CELL_SIZE / 2);
+                surface_block(map[x as usize][z as usize])
+            })
+        };
+        SurfaceCells((-1..=CELLS).map(|x|(-1..=CELLS).map(|z|sample(x, z)).collect()).collect())
+    }
+    /// the height (relative to the chunk) and the block of the surface of the cell, the coordinates reach from -1 to `CELLS`
+    pub fn get(&self, x: i64, z: i64) -> (i64, BlockInner) {
+        self.0[(x + 1) as usize][(z + 1) as usize]
+    }
+}
+
+/// creates the mesh of a far terrain column, the top of every cell and the walls down to lower cells
+pub fn create_far_terrain_mesh<M: ChunkMeshBuilder>(cells: &SurfaceCells, blocks: &StaticBlocks, mesh_builder: &mut M) {
+    let size = CELL_SIZE as f32;
+    for x in 0..CELLS {
+        for z in 0..CELLS {
+            let (height, block) = cells.get(x, z);
+            let textures = match &blocks[block.btype as usize].0 {
+                BlockLook::Cube { textures } => textures,
+                _ => continue,
+            };
+            let lower = Vec3::new((x * CELL_SIZE) as f32, 0.0, (z * CELL_SIZE) as f32);
+            let top = height as f32 + 1.0 - SINK;
+            mesh_builder.add_face(cube_face(Side::Top, textures[Side::Top]).stretched(Vec3::new(size, top, size)), lower);
+
+            for (side, dx, dz) in [(Side::Front, 1, 0), (Side::Back, -1, 0), (Side::Left, 0, 1), (Side::Right, 0, -1)].iter() {
+                let (next, _) = cells.get(x + dx, z + dz);
+                if next < height {
+                    let wall = Vec3::new(size, (height - next) as f32, size);
+                    mesh_builder.add_face(
+                        cube_face(*side, textures[*side]).stretched(wall),
+                        lower + Vec3::new(0.0, next as f32 + 1.0 - SINK, 0.0),
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// every world starts without far terrain
+pub fn reset_far_terrain(mut far_terrain: ResMut<FarTerrain>) {
+    *far_terrain = FarTerrain::default();
+}
+
+/// spawns the far terrain columns within the far terrain distance, columns with loaded terrain are removed
+pub fn update_far_terrain(
+    commands: &mut Commands,
+    mut far_terrain: ResMut<FarTerrain>,
+    manager: Res<ChunkManager>,
+    settings: Res<Settings>,
+    blocks: Res<StaticBlocksRes>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    players: Query<&Transform, With<PlayerMovement>>,
+) {
+    let player = match players.iter().next() {
+        Some(transform) => transform.translation,
+        None => return,
+    };
+    let material = match manager.chunk_material() {
+        Some(material) => material,
+        None => return,
+    };
+    let center = ChunkPosition::from(player);
+    let mut load_distance_square = settings.game_settings.load_distance * CHUNK_SIZE as f32;
+    load_distance_square *= load_distance_square;
+    let far_distance = settings.render_settings.far_terrain_distance;
+    let range = far_distance.ceil() as i64;
+
+    let mut wanted = Vec::new();
+    for x in -range..=range {
+        for z in -range..=range {
+            let distance = ((x * x + z * z) as f32).sqrt();
+            //the generator only fills the chunks at y 0, they are loaded like every other chunk
+            let terrain = ChunkPosition::new(center.x + x, 0, center.z + z);
+            if distance <= far_distance && terrain.center().distance_squared(player) >= load_distance_square {
+                wanted.push((distance, SurfaceCoordinates::new(terrain.x, terrain.z)));
+            }
+        }
+    }
+    let wanted_columns: HashSet<SurfaceCoordinates> = wanted.iter().map(|(_, column)|*column).collect();
+    far_terrain.columns.retain(|column, entity|{
+        let keep = wanted_columns.contains(column);
+        if !keep {
+            commands.despawn(*entity);
+        }
+        keep
+    });
+
+    //the nearest columns first
+    wanted.sort_by(|(a, _), (b, _)|a.partial_cmp(b).unwrap());
+    let missing: Vec<SurfaceCoordinates> = wanted.iter()
+        .map(|(_, column)|*column)
+        .filter(|column|!far_terrain.columns.contains_key(column))
+        .take(COLUMNS_PER_FRAME)
+        .collect();
+    let seed = manager.provider().seed();
+    for column in missing {
+        let terrain = column.with_height(0);
+        let mut builder = PackedChunkMeshBuilder::empty();
+        create_far_terrain_mesh(&SurfaceCells::generate(seed, terrain), &**blocks, &mut builder);
+        let mesh = builder.build().map_or(Handle::default(), |mesh|meshes.add(mesh));
+        commands
+            .spawn(MeshBundle {
+                mesh,
+                render_pipelines: chunk_pipelines(),
+                transform: Transform::from_translation(terrain.lowest().lower_corner()),
+                ..MeshBundle::default()
+            })
+            .with(material.clone())
+            .with(FarTerrainColumn);
+        far_terrain.columns.insert(column, commands.current_entity().unwrap());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::world_generation::generate_chunk;
+    use crate::world::coordinates::BlockVector;
+
+    #[test]
+    fn the_surface_matches_the_generated_chunk() {
+        let terrain = ChunkPosition::new(3, 0, -2);
+        let cells = SurfaceCells::generate(42, terrain);
+        let data = generate_chunk(42, terrain);
+        for x in 0..CELLS {
+            for z in 0..CELLS {
+                let (height, block) = cells.get(x, z);
+                let (block_x, block_z) = (x * CELL_SIZE + CELL_SIZE / 2, z * CELL_SIZE + CELL_SIZE / 2);
+                assert_eq!(data[BlockVector::new(block_x, height, block_z)], block);
+                if height < CHUNK_SIZE - 1 {
+                    assert_eq!(data[BlockVector::new(block_x, height + 1, block_z)], AIR);
+                }
+            }
+        }
+        //the border cells belong to the neighbouring columns
+        let next = SurfaceCells::generate(42, terrain.with_x(1));
+        assert_eq!(cells.get(CELLS, 2), next.get(0, 2));
+    }
+}
@@ -0,0 +1,153 @@
+use bevy::prelude::*;
+use std::collections::{HashMap, BTreeMap};
+use rand::Rng;
+use utils::MapData;
+use crate::content::world_generation::temperature_map;
+use crate::world::block_inner::AIR;
+use crate::world::chunk::{Chunk, ChunkData, ChunkEvent, ChunkManager};
+use crate::world::coordinates::{BlockPosition, BlockVector, ChunkPosition, SurfaceCoordinates, CHUNK_SIZE};
+
+/// the highest non air block of every block column in a chunk, None if the column is empty
+type ChunkHeights = [[Option<u8>; CHUNK_SIZE as usize]; CHUNK_SIZE as usize];
+
+struct Column {
+    temperatures: MapData,
+    /// the heights of the loaded chunks of the column by their y coordinate
+    chunks: BTreeMap<i64, ChunkHeights>,
+}
+
+/// The highest blocks and the temperatures of the loaded chunk columns.
+///
+/// Precipitation stops at the highest block, blocks in unloaded chunks above it arent known.
+#[derive(Default)]
+pub struct Heightmap {
+    columns: HashMap<SurfaceCoordinates, Column>,
+}
+
+fn column_height(data: &ChunkData, x: i64, z: i64) -> Option<u8> {
+    (0..CHUNK_SIZE).rev()
+        .find(|y|data[BlockVector::new(x, *y, z)].btype != AIR.btype)
+        .map(|y|y as u8)
+}
+
+fn chunk_heights(data: &ChunkData) -> ChunkHeights {
+    let mut heights = ChunkHeights::default();
+    for x in 0..CHUNK_SIZE {
+        for z in 0..CHUNK_SIZE {
+            heights[x as usize][z as usize] = column_height(data, x, z);
+        }
+    }
+    heights
+}
+
+fn surface(position: ChunkPosition) -> SurfaceCoordinates {
+    SurfaceCoordinates::new(position.x, position.z)
+}
+
+impl Heightmap {
+    pub fn insert_chunk(&mut self, seed: u64, position: ChunkPosition, data: &ChunkData) {
+        self.columns.entry(surface(position))
+            .or_insert_with(||Column {
+                temperatures: temperature_map(seed, position),
+                chunks: BTreeMap::new(),
+            })
+            .chunks.insert(position.y, chunk_heights(data));
+    }
+    pub fn remove_chunk(&mut self, position: ChunkPosition) {
+        if let Some(column) = self.columns.get_mut(&surface(position)) {
+            column.chunks.remove(&position.y);
+            if column.chunks.is_empty() {
+                self.columns.remove(&surface(position));
+            }
+        }
+    }
+    /// updates the block column of the changed block, data is the chunk of the block
+    pub fn update_block(&mut self, position: BlockPosition, data: &ChunkData) {
+        let (local, chunk) = position.local();
+        if let Some(heights) = self.columns.get_mut(&surface(chunk))
+            .and_then(|column|column.chunks.get_mut(&chunk.y))
+        {
+            heights[local.x as usize][local.z as usize] = column_height(data, local.x, local.z);
+        }
+    }
+    /// the y coordinate of the highest loaded non air block of the block column
+    pub fn height(&self, x: i64, z: i64) -> Option<i64> {
+        let (local, chunk) = BlockPosition::new(x, 0, z).local();
+        self.columns.get(&surface(chunk))?.chunks.iter()
+            .rev()
+            .find_map(|(chunk_y, heights)|{
+                heights[local.x as usize][local.z as usize].map(|y|chunk_y * CHUNK_SIZE + y as i64)
+            })
+    }
+    /// the temperature of a loaded block column
+    pub fn temperature(&self, x: i64, z: i64) -> Option<f32> {
+        let (local, chunk) = BlockPosition::new(x, 0, z).local();
+        Some(self.columns.get(&surface(chunk))?.temperatures[local.x as usize][local.z as usize])
+    }
+    /// a random block column of the loaded chunks
+    pub fn random_column(&self, rng: &mut impl Rng) -> Option<(i64, i64)> {
+        if self.columns.is_empty() {
+            return None;
+        }
+        let (column, _) = self.columns.iter().nth(rng.gen_range(0..self.columns.len()))?;
+        let lowest = column.with_height(0).lowest();
+        Some((lowest.x() + rng.gen_range(0..CHUNK_SIZE), lowest.z() + rng.gen_range(0..CHUNK_SIZE)))
+    }
+}
+
+/// every world starts without loaded chunks
+pub fn reset_heightmap(mut heightmap: ResMut<Heightmap>) {
+    *heightmap = Heightmap::default();
+}
+
+pub fn update_heightmap(
+    mut reader: Local<EventReader<ChunkEvent>>,
+    events: Res<Events<ChunkEvent>>,
+    mut heightmap: ResMut<Heightmap>,
+    chunk_manager: Res<ChunkManager>,
+    chunks: Query<(&Chunk,)>,
+) {
+    for event in reader.iter(&events) {
+        match event {
+            ChunkEvent::Loaded(position) => {
+                //the chunk might be unloaded again already
+                if let Some(data) = chunk_manager.chunk_data(*position, &chunks) {
+                    heightmap.insert_chunk(chunk_manager.provider().seed(), *position, data);
+                }
+            }
+            ChunkEvent::Unloaded(position) => heightmap.remove_chunk(*position),
+            ChunkEvent::BlockChanged(position, _) => {
+                if let Some(data) = chunk_manager.chunk_data(position.chunk(), &chunks) {
+                    heightmap.update_block(*position, data);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::block_inner::{STONE, SNOW};
+
+    #[test]
+    fn the_highest_loaded_block_is_found() {
+        let mut heightmap = Heightmap::default();
+        let mut lower = ChunkData::filled(AIR);
+        lower[BlockVector::new(1, 3, 2)] = STONE;
+        let mut upper = ChunkData::filled(AIR);
+        heightmap.insert_chunk(0, ChunkPosition::new(0, 0, 0), &lower);
+        heightmap.insert_chunk(0, ChunkPosition::new(0, 1, 0), &upper);
+        assert_eq!(heightmap.height(1, 2), Some(3));
+        assert_eq!(heightmap.height(2, 2), None);
+
+        upper[BlockVector::new(1, 4, 2)] = SNOW;
+        heightmap.update_block(BlockPosition::new(1, 20, 2), &upper);
+        assert_eq!(heightmap.height(1, 2), Some(20));
+
+        heightmap.remove_chunk(ChunkPosition::new(0, 1, 0));
+        assert_eq!(heightmap.height(1, 2), Some(3));
+        assert!(heightmap.temperature(1, 2).is_some());
+        assert_eq!(heightmap.temperature(-1, 2), None);
+    }
+}
@@ -0,0 +1,175 @@
+use bevy::prelude::*;
+use crate::world::block_inner::{BlockInner, Side, SIDES, BLOCK_MESH};
+use crate::world::block_types::{BlockLook, StaticBlocks};
+use crate::world::chunk::{Chunk, ChunkData};
+use crate::world::chunk_mesh::{ChunkMeshBuilder, cube_face};
+use crate::world::coordinates::{BlockVector, ChunkPosition, CHUNK_SIZE};
+
+/// the coarsest level of detail, its cells are 8 blocks wide
+pub const MAX_LOD: u8 = 3;
+
+/// the level of detail of a chunk, every ring of `lod_distance` chunks around the player halves the detail
+pub fn lod_level(chunk: ChunkPosition, player_chunk: ChunkPosition, lod_distance: f32) -> u8 {
+    let (x, y, z) = (chunk.x - player_chunk.x, chunk.y - player_chunk.y, chunk.z - player_chunk.z);
+    let distance = ((x * x + y * y + z * z) as f32).sqrt();
+    ((distance / lod_distance.max(1.0)) as u8).min(MAX_LOD)
+}
+
+/// the width of a cell in blocks
+pub fn lod_scale(lod: u8) -> i64 {
+    1 << lod
+}
+
+/// the block representing a cell of the downsampled chunk.
+/// A cell is solid if at least half of its blocks are solid, the highest block is used for the texture,
+/// so the surface keeps its color.
+pub fn cell_block(data: &ChunkData, cell: BlockVector, scale: i64, blocks: &StaticBlocks) -> Option<BlockInner> {
+    let lower = cell * scale;
+    let volume = scale * scale * scale;
+    let mut solid = (0, None);
+    let mut fluid = (0, None);
+    for x in 0..scale {
+        for z in 0..scale {
+            //from the top, so the first block of a kind is the highest
+            for y in (0..scale).rev() {
+                let block = data[lower + BlockVector::new(x, y, z)];
+                let count = if block.info.contains(BLOCK_MESH) {
+                    &mut solid
+                } else if let BlockLook::Cube { .. } = blocks[block.btype as usize].0 {
+                    &mut fluid
+                } else {
+                    continue;
+                };
+                count.0 += 1;
+                if count.1.map_or(true, |(height, _)|y > height) {
+                    count.1 = Some((y, block));
+                }
+            }
+        }
+    }
+    if solid.0 * 2 >= volume {
+        solid.1.map(|(_, block)|block)
+    } else if (solid.0 + fluid.0) * 2 >= volume {
+        fluid.1.map(|(_, block)|block)
+    } else {
+        None
+    }
+}
+
+fn adjacent(chunk: &Chunk, side: Side) -> Option<Entity> {
+    match side {
+        Side::Top => chunk.y_positive,
+        Side::Front => chunk.x_positive,
+        Side::Left => chunk.z_positive,
+        Side::Bottom => chunk.y_negative,
+        Side::Back => chunk.x_negative,
+        Side::Right => chunk.z_negative,
+    }
+}
+
+fn offset(side: Side) -> BlockVector {
+    match side {
+        Side::Top => BlockVector::new(0, 1, 0),
+        Side::Front => BlockVector::new(1, 0, 0),
+        Side::Left => BlockVector::new(0, 0, 1),
+        Side::Bottom => BlockVector::new(0, -1, 0),
+        Side::Back => BlockVector::new(-1, 0, 0),
+        Side::Right => BlockVector::new(0, 0, -1),
+    }
+}
+
+/// creates the mesh of a chunk from cells of `lod_scale(chunk.lod)` blocks.
+///
+/// Faces at the border to a chunk with another level of detail are always added,
+/// these walls reach down into the ground and cover the cracks between the different surfaces.
+pub fn create_lod_mesh<M: ChunkMeshBuilder>(chunk: &Chunk, query: &Query<(&Chunk,)>, blocks: &StaticBlocks, mesh_builder: &mut M) {
+    let scale = lod_scale(chunk.lod);
+    let cells = CHUNK_SIZE / scale;
+    let index = |cell: BlockVector|((cell.x * cells + cell.y) * cells + cell.z) as usize;
+    let mut grid = vec![None; (cells * cells * cells) as usize];
+    for x in 0..cells {
+        for y in 0..cells {
+            for z in 0..cells {
+                let cell = BlockVector::new(x, y, z);
+                grid[index(cell)] = cell_block(&chunk.data, cell, scale, blocks);
+            }
+        }
+    }
+    let fits = |cell: BlockVector|cell.x >= 0 && cell.y >= 0 && cell.z >= 0 && cell.x < cells && cell.y < cells && cell.z < cells;
+    let is_solid = |block: Option<BlockInner>|block.map_or(false, |block|block.info.contains(BLOCK_MESH));
+    let covered = |cell: BlockVector, side: Side| -> bool {
+        let next = cell + offset(side);
+        if fits(next) {
+            return is_solid(grid[index(next)]);
+        }
+        adjacent(chunk, side)
+            .and_then(|entity|query.get_component::<Chunk>(entity).ok())
+            .filter(|adjacent|adjacent.lod == chunk.lod)
+            .map_or(false, |adjacent|{
+                let wrapped = BlockVector::new((next.x + cells) % cells, (next.y + cells) % cells, (next.z + cells) % cells);
+                is_solid(cell_block(&adjacent.data, wrapped, scale, blocks))
+            })
+    };
+
+    let center = chunk.position.center();
+    for x in 0..cells {
+        for y in 0..cells {
+            for z in 0..cells {
+                let cell = BlockVector::new(x, y, z);
+                let block = match grid[index(cell)] {
+                    Some(block) => block,
+                    None => continue,
+                };
+                let lower = (chunk.position + cell * scale).lower_corner() - center;
+                for side in SIDES.iter() {
+                    let texture = match &blocks[block.btype as usize].0 {
+                        BlockLook::Cube { textures } => textures[*side],
+                        BlockLook::Layers { texture } => *texture,
+                        _ => continue,
+                    };
+                    if !covered(cell, *side) {
+                        mesh_builder.add_face(cube_face(*side, texture).scaled(scale as f32), lower);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::block_inner::{AIR, STONE, GRASS, WATER};
+    use crate::world::block_types::get_block_types;
+
+    #[test]
+    fn detail_decreases_in_rings() {
+        let player = ChunkPosition::new(0, 0, 0);
+        assert_eq!(lod_level(ChunkPosition::new(3, 0, 0), player, 4.0), 0);
+        assert_eq!(lod_level(ChunkPosition::new(4, 0, 0), player, 4.0), 1);
+        assert_eq!(lod_level(ChunkPosition::new(-9, 0, 0), player, 4.0), 2);
+        assert_eq!(lod_level(ChunkPosition::new(30, 1, 5), player, 4.0), MAX_LOD);
+    }
+
+    #[test]
+    fn cells_keep_the_surface_block() {
+        let blocks = get_block_types();
+        let mut data = ChunkData::filled(AIR);
+        for (position, block) in data.iter_mut() {
+            if position.y < 3 {
+                *block = STONE;
+            } else if position.y == 3 {
+                *block = GRASS;
+            } else if position.y < 6 {
+                *block = WATER;
+            }
+        }
+        //the lower 4 layers are solid
+        assert_eq!(cell_block(&data, BlockVector::new(0, 0, 0), 4, &blocks), Some(GRASS));
+        assert_eq!(cell_block(&data, BlockVector::new(1, 1, 1), 2, &blocks), Some(GRASS));
+        assert_eq!(cell_block(&data, BlockVector::new(0, 2, 0), 2, &blocks), Some(WATER));
+        //half of the cell is water
+        assert_eq!(cell_block(&data, BlockVector::new(0, 1, 0), 4, &blocks), Some(WATER));
+        assert_eq!(cell_block(&data, BlockVector::new(0, 3, 0), 4, &blocks), None);
+    }
+}
@@ -1,11 +1,15 @@
 use bevy::prelude::*;
 use chunk::{init_chunks};
 use crate::settings::Settings;
-use crate::world::coordinates::ChunkPosition;
-use crate::world::chunk::{ChunkManager, update_chunk_mesh, update_chunk_scope};
-use bevy::prelude::stage::POST_UPDATE;
+use crate::world::chunk::{ChunkEvent, ChunkMeshStats, update_chunk_mesh, update_chunk_scope, send_chunk_events};
 use crate::world::block_types::get_block_types;
-use crate::content::in_memory::InMemory;
+use crate::world::time::{WorldTime, advance_time, load_world_time, save_world_time};
+use crate::world::sky::{spawn_sun, update_sky};
+use crate::world::culling::{CullingStats, cull_chunks};
+use crate::world::far_terrain::{FarTerrain, reset_far_terrain, update_far_terrain};
+use crate::world::heightmap::{Heightmap, reset_heightmap, update_heightmap};
+use crate::world::weather::{WeatherState, LocalWeather, setup_precipitation, load_weather, update_weather, weather_ticks, precipitation_particles};
+use crate::state::{AppState, APP_STATE_STAGE, GAME_UPDATE, GAME_POST_UPDATE};
 
 pub mod chunk;
 pub mod block_inner;
@@ -14,17 +18,42 @@ pub mod coordinates;
 mod chunk_manager;
 pub mod block;
 pub mod block_types;
+pub mod time;
+pub mod sky;
+pub mod heightmap;
+pub mod weather;
+pub mod culling;
+pub mod lod;
+pub mod far_terrain;
 
+/// The `ChunkManager` is added by the menu when a world is opened.
 pub fn init_world(builder: &mut AppBuilder, settings: &Settings) {
-    builder.add_resource(ChunkManager::new(
-            Box::new(InMemory::new()),
-            ChunkPosition::new(0, 1, 0),
-            settings.game_settings.load_distance,
-            settings.game_settings.unload_distance,
-            settings.game_settings.asset_path.clone(),
-        ))
-        .add_resource(get_block_types())
-        .add_startup_system(init_chunks.system())
-        .add_system_to_stage(POST_UPDATE, update_chunk_mesh.system())
-        .add_system(update_chunk_scope.system());
+    builder.add_resource(get_block_types())
+        .add_resource(WorldTime::default())
+        .add_resource(Heightmap::default())
+        .add_resource(WeatherState::default())
+        .add_resource(LocalWeather::default())
+        .add_resource(CullingStats::default())
+        .add_resource(FarTerrain::default())
+        .add_resource(ChunkMeshStats::default())
+        .add_startup_system(setup_precipitation.system())
+        .add_event::<ChunkEvent>()
+        .add_system_to_stage(GAME_POST_UPDATE, send_chunk_events.system())
+        .on_state_enter(APP_STATE_STAGE, AppState::Loading, init_chunks.system())
+        .on_state_enter(APP_STATE_STAGE, AppState::Loading, load_world_time.system())
+        .on_state_enter(APP_STATE_STAGE, AppState::Loading, spawn_sun.system())
+        .on_state_enter(APP_STATE_STAGE, AppState::Loading, reset_heightmap.system())
+        .on_state_enter(APP_STATE_STAGE, AppState::Loading, load_weather.system())
+        .on_state_enter(APP_STATE_STAGE, AppState::Loading, reset_far_terrain.system())
+        .add_system_to_stage(GAME_POST_UPDATE, update_chunk_mesh.system())
+        .add_system_to_stage(GAME_UPDATE, update_chunk_scope.system())
+        .add_system_to_stage(GAME_UPDATE, advance_time.system())
+        .add_system_to_stage(GAME_UPDATE, save_world_time.system())
+        .add_system_to_stage(GAME_POST_UPDATE, update_sky.system())
+        .add_system_to_stage(GAME_POST_UPDATE, update_heightmap.system())
+        .add_system_to_stage(GAME_POST_UPDATE, cull_chunks.system())
+        .add_system_to_stage(GAME_POST_UPDATE, update_far_terrain.system())
+        .add_system_to_stage(GAME_UPDATE, update_weather.system())
+        .add_system_to_stage(GAME_UPDATE, weather_ticks.system())
+        .add_system_to_stage(GAME_UPDATE, precipitation_particles.system());
 }
\ No newline at end of file
@@ -0,0 +1,122 @@
+use bevy::prelude::*;
+use std::f32::consts::PI;
+use crate::player::player::PlayerMovement;
+use crate::entities::spawning::SKY_LIGHT;
+use crate::world::time::{WorldTime, DAY_LENGTH};
+use crate::world::weather::LocalWeather;
+
+/// the distance of the sun light to the player, far enough to light every chunk from the same direction
+const SUN_DISTANCE: f32 = 1000.0;
+/// the sun doesnt pass the zenith, so the light isnt parallel to the sides of the blocks at noon
+const SUN_TILT: f32 = 0.3;
+/// the sky light at night
+const MIN_SKY_LIGHT: u8 = 4;
+const DAY_SKY: [f32; 3] = [0.5, 0.7, 1.0];
+const NIGHT_SKY: [f32; 3] = [0.01, 0.01, 0.05];
+const SUNSET_SKY: [f32; 3] = [0.9, 0.5, 0.3];
+const SUN_COLOR: [f32; 3] = [1.0, 0.95, 0.85];
+const MOON_COLOR: [f32; 3] = [0.15, 0.17, 0.25];
+const DAY_AMBIENT: f32 = 0.3;
+const NIGHT_AMBIENT: f32 = 0.05;
+/// the sky turns grey under clouds
+const OVERCAST_SKY: [f32; 3] = [0.45, 0.47, 0.5];
+
+/// The light of the sun at day and the moon at night
+pub struct Sun;
+
+fn mix(a: [f32; 3], b: [f32; 3], t: f32) -> Color {
+    Color::rgb(
+        a[0] + (b[0] - a[0]) * t,
+        a[1] + (b[1] - a[1]) * t,
+        a[2] + (b[2] - a[2]) * t,
+    )
+}
+
+fn scale(color: Color, factor: f32) -> Color {
+    Color::rgb(color.r() * factor, color.g() * factor, color.b() * factor)
+}
+
+/// the direction to the sun, it rises in the east at tick 0 and is highest at noon
+pub fn sun_direction(time_of_day: u64) -> Vec3 {
+    let angle = time_of_day as f32 / DAY_LENGTH as f32 * 2.0 * PI;
+    Vec3::new(angle.cos(), angle.sin(), SUN_TILT).normalize()
+}
+
+/// the brightness of the day between 0.0 at night and 1.0 at day, it changes while the sun is close to the horizon
+pub fn daylight(time_of_day: u64) -> f32 {
+    ((sun_direction(time_of_day).y + 0.1) / 0.3).max(0.0).min(1.0)
+}
+
+/// the sky light of blocks open to the sky
+pub fn sky_light(time_of_day: u64) -> u8 {
+    MIN_SKY_LIGHT + ((SKY_LIGHT - MIN_SKY_LIGHT) as f32 * daylight(time_of_day)).round() as u8
+}
+
+/// the clear color, the sky turns red while the sun is close to the horizon
+pub fn sky_color(time_of_day: u64) -> Color {
+    let sky = mix(NIGHT_SKY, DAY_SKY, daylight(time_of_day));
+    let glow = (1.0 - sun_direction(time_of_day).y.abs() / 0.25).max(0.0) * 0.6;
+    mix([sky.r(), sky.g(), sky.b()], SUNSET_SKY, glow)
+}
+
+/// the direction to the light and its color, the moon is opposite to the sun
+pub fn sun_light(time_of_day: u64) -> (Vec3, Color) {
+    let sun = sun_direction(time_of_day);
+    let direction = if sun.y >= 0.0 { sun } else { -sun };
+    (direction, mix(MOON_COLOR, SUN_COLOR, daylight(time_of_day)))
+}
+
+pub fn spawn_sun(commands: &mut Commands) {
+    commands
+        .spawn(LightBundle::default())
+        .with(Sun);
+}
+
+/// moves the sun with the player and colors the sky, clouds darken it and lightnings brighten it
+pub fn update_sky(
+    world_time: Res<WorldTime>,
+    weather: Res<LocalWeather>,
+    mut clear_color: ResMut<ClearColor>,
+    mut ambient: ResMut<AmbientLight>,
+    players: Query<&GlobalTransform, With<PlayerMovement>>,
+    mut suns: Query<(&mut Transform, &mut Light), With<Sun>>,
+) {
+    let time_of_day = world_time.time_of_day();
+    let overcast = weather.weather.overcast();
+    let daylight = daylight(time_of_day);
+    let brightness = 1.0 - overcast * 0.6 + weather.lightning;
+    let center = players.iter().next().map_or(Vec3::zero(), |transform|transform.translation);
+    let (direction, color) = sun_light(time_of_day);
+    for (mut transform, mut light) in suns.iter_mut() {
+        //the lights are point lights, a far away light is almost directional
+        transform.translation = center + direction * SUN_DISTANCE;
+        light.color = scale(color, brightness);
+    }
+    let sky = sky_color(time_of_day);
+    let clouds = scale(mix([0.0; 3], OVERCAST_SKY, daylight), 1.0 + weather.lightning);
+    clear_color.0 = mix([sky.r(), sky.g(), sky.b()], [clouds.r(), clouds.g(), clouds.b()], overcast);
+    ambient.color = Color::WHITE;
+    ambient.brightness = (NIGHT_AMBIENT + (DAY_AMBIENT - NIGHT_AMBIENT) * daylight) * brightness;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sun_follows_the_time() {
+        assert!(sun_direction(6000).y > 0.9);
+        assert!(sun_direction(18000).y < -0.9);
+        assert!(sun_direction(0).x > 0.9);
+        //the moon lights the night from above
+        assert!(sun_light(18000).0.y > 0.9);
+    }
+
+    #[test]
+    fn nights_are_dark() {
+        assert_eq!(sky_light(6000), SKY_LIGHT);
+        assert_eq!(sky_light(18000), MIN_SKY_LIGHT);
+        assert!(daylight(1000) > daylight(0));
+        assert!(sky_color(6000).b() > sky_color(18000).b());
+    }
+}
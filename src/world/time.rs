@@ -0,0 +1,90 @@
+use bevy::prelude::*;
+use crate::world::chunk::ChunkManager;
+use crate::entities::entity::Object;
+use crate::entities::item::DroppedItem;
+use crate::entities::registry::EntityTypes;
+use crate::entities::spawning::{Spawner, save_all_entities};
+
+/// the length of a day in ticks
+pub const DAY_LENGTH: u64 = 24000;
+pub const TICKS_PER_SECOND: f32 = 20.0;
+/// new worlds start in the morning
+const START_TIME: u64 = 1000;
+/// the time, the changed blocks and the entities are saved once a minute, they are also saved when the world is closed
+const SAVE_INTERVAL: u64 = 1200;
+
+/// The age of the loaded world in ticks
+#[derive(Clone, Debug, Default)]
+pub struct WorldTime {
+    ticks: u64,
+    /// the time which didnt make up a full tick yet, in ticks
+    partial: f32,
+}
+
+impl WorldTime {
+    pub fn new(ticks: u64) -> Self {
+        WorldTime {
+            ticks,
+            partial: 0.0,
+        }
+    }
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+    /// the ticks since the start of the current day
+    pub fn time_of_day(&self) -> u64 {
+        self.ticks % DAY_LENGTH
+    }
+    /// sets the time of the current day, the number of days is kept
+    pub fn set_time_of_day(&mut self, time: u64) {
+        self.ticks = self.ticks - self.time_of_day() + time % DAY_LENGTH;
+        self.partial = 0.0;
+    }
+    pub fn advance(&mut self, seconds: f32) {
+        self.partial += seconds * TICKS_PER_SECOND;
+        let ticks = self.partial.floor();
+        self.ticks += ticks as u64;
+        self.partial -= ticks;
+    }
+}
+
+/// the time of day for a name or a number of ticks
+pub fn parse_time_of_day(time: &str) -> Option<u64> {
+    match time {
+        "day" => Some(1000),
+        "noon" => Some(6000),
+        "night" => Some(13000),
+        "midnight" => Some(18000),
+        _ => time.parse().ok(),
+    }
+}
+
+pub fn advance_time(time: Res<Time>, mut world_time: ResMut<WorldTime>) {
+    world_time.advance(time.delta_seconds());
+}
+
+/// restores the time of the opened world
+pub fn load_world_time(mut chunk_manager: ResMut<ChunkManager>, mut world_time: ResMut<WorldTime>) {
+    *world_time = WorldTime::new(chunk_manager.provider_mut().load_time().unwrap_or(START_TIME));
+}
+
+pub fn save_world_time(
+    mut chunk_manager: ResMut<ChunkManager>,
+    mut spawner: ResMut<Spawner>,
+    world_time: Res<WorldTime>,
+    types: Res<EntityTypes>,
+    mut saved: Local<u64>,
+    objects: Query<(Entity, &Object, &Transform)>,
+    items: Query<(Entity, &DroppedItem, &Transform)>,
+) {
+    let interval = world_time.ticks() / SAVE_INTERVAL;
+    if interval != *saved {
+        chunk_manager.provider_mut().save_time(world_time.ticks());
+        //the saved entities of loaded chunks are only read again after a crash, unloading overwrites them
+        spawner.clear();
+        save_all_entities(&mut chunk_manager, &types, &objects, &items);
+        //the changed blocks are only written on unload otherwise
+        chunk_manager.provider_mut().flush();
+        *saved = interval;
+    }
+}
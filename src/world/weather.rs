@@ -0,0 +1,450 @@
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Serialize, Deserialize};
+use crate::content::world_generation::{SNOW_TEMPERATURE, DESERT_TEMPERATURE};
+use crate::player::player::PlayerMovement;
+use crate::world::block_inner::{BlockInner, AIR, WATER, SNOW, BLOCK_MESH};
+use crate::world::chunk::{Chunk, ChunkManager};
+use crate::world::coordinates::BlockPosition;
+use crate::world::heightmap::Heightmap;
+use crate::world::time::{WorldTime, DAY_LENGTH};
+
+/// the chance that rain turns into a thunderstorm instead of changing to clear weather
+const THUNDERSTORM_CHANCE: f64 = 0.2;
+/// the systems catch up at most this many ticks, so setting the time doesnt change the whole world
+const MAX_CATCH_UP_TICKS: u64 = 20;
+/// the number of random columns hit by rain or snow per tick
+const COLUMNS_PER_TICK: usize = 2;
+/// snowfall stops piling up at this many layers
+const MAX_SNOWFALL_LAYERS: u8 = 4;
+const PARTICLE_COUNT: usize = 400;
+/// the number of particles spawned per frame when the precipitation starts
+const PARTICLE_SPAWN_RATE: usize = 40;
+/// the horizontal distance of the particles to the player
+const PARTICLE_RADIUS: f32 = 16.0;
+/// particles start up to this far above the player
+const PARTICLE_HEIGHT: f32 = 20.0;
+const RAIN_SPEED: f32 = 14.0;
+const SNOW_SPEED: f32 = 2.0;
+/// the chance of a lightning per second in a thunderstorm
+const LIGHTNING_CHANCE: f64 = 0.05;
+/// the flash of a lightning fades in a quarter second
+const LIGHTNING_FADE: f32 = 4.0;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Weather {
+    Clear,
+    Rain,
+    Thunderstorm,
+    /// rain in cold biomes
+    Snow,
+}
+
+impl Weather {
+    /// how much the clouds darken the sky between 0.0 and 1.0
+    pub fn overcast(self) -> f32 {
+        match self {
+            Weather::Clear => 0.0,
+            Weather::Rain | Weather::Snow => 0.4,
+            Weather::Thunderstorm => 0.7,
+        }
+    }
+}
+
+/// the weather for the name used by `/weather`
+pub fn parse_weather(weather: &str) -> Option<Weather> {
+    match weather {
+        "clear" => Some(Weather::Clear),
+        "rain" => Some(Weather::Rain),
+        "thunder" | "thunderstorm" => Some(Weather::Thunderstorm),
+        _ => None,
+    }
+}
+
+/// The weather of the world, it changes between clear weather, rain and thunderstorms.
+///
+/// Rain falls as snow in cold biomes and doesnt reach deserts, see `WeatherState::local`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WeatherState {
+    weather: Weather,
+    /// the ticks until the weather changes
+    remaining: u64,
+}
+
+impl Default for WeatherState {
+    fn default() -> Self {
+        WeatherState {
+            weather: Weather::Clear,
+            remaining: DAY_LENGTH,
+        }
+    }
+}
+
+/// a random duration of the weather in ticks
+fn duration(weather: Weather, rng: &mut impl Rng) -> u64 {
+    match weather {
+        Weather::Clear => rng.gen_range(DAY_LENGTH / 2..=DAY_LENGTH * 2),
+        Weather::Rain | Weather::Snow => rng.gen_range(DAY_LENGTH / 4..=DAY_LENGTH / 2),
+        Weather::Thunderstorm => rng.gen_range(DAY_LENGTH / 8..=DAY_LENGTH / 4),
+    }
+}
+
+fn next_weather(weather: Weather, rng: &mut impl Rng) -> Weather {
+    let thunder = rng.gen_bool(THUNDERSTORM_CHANCE);
+    match weather {
+        Weather::Clear if thunder => Weather::Thunderstorm,
+        Weather::Clear => Weather::Rain,
+        Weather::Rain | Weather::Snow if thunder => Weather::Thunderstorm,
+        Weather::Rain | Weather::Snow => Weather::Clear,
+        Weather::Thunderstorm => Weather::Rain,
+    }
+}
+
+impl WeatherState {
+    pub fn new(weather: Weather, remaining: u64) -> Self {
+        WeatherState {
+            weather,
+            remaining,
+        }
+    }
+    pub fn weather(&self) -> Weather {
+        self.weather
+    }
+    /// changes the weather for a random duration
+    pub fn set(&mut self, weather: Weather, rng: &mut impl Rng) {
+        self.weather = weather;
+        self.remaining = duration(weather, rng);
+    }
+    /// the weather at a column with the given temperature
+    pub fn local(&self, temperature: f32) -> Weather {
+        match self.weather {
+            Weather::Clear => Weather::Clear,
+            _ if temperature >= DESERT_TEMPERATURE => Weather::Clear,
+            _ if temperature < SNOW_TEMPERATURE => Weather::Snow,
+            weather => weather,
+        }
+    }
+    /// advances the weather by the ticks, returns true if the weather changed
+    pub fn advance(&mut self, ticks: u64, rng: &mut impl Rng) -> bool {
+        if ticks < self.remaining {
+            self.remaining -= ticks;
+            return false;
+        }
+        let next = next_weather(self.weather, rng);
+        self.set(next, rng);
+        true
+    }
+}
+
+/// The weather at the player, it is updated every frame
+#[derive(Copy, Clone, Debug)]
+pub struct LocalWeather {
+    pub weather: Weather,
+    /// the brightness of the last lightning, it fades to 0.0
+    pub lightning: f32,
+}
+
+impl Default for LocalWeather {
+    fn default() -> Self {
+        LocalWeather {
+            weather: Weather::Clear,
+            lightning: 0.0,
+        }
+    }
+}
+
+/// A rain drop or snow flake, it is moved back above the player when it reaches the ground
+pub struct Precipitation {
+    speed: f32,
+}
+
+pub struct PrecipitationAssets {
+    rain_mesh: Handle<Mesh>,
+    snow_mesh: Handle<Mesh>,
+    rain_material: Handle<StandardMaterial>,
+    snow_material: Handle<StandardMaterial>,
+}
+
+/// the ticks since the last call, at most `MAX_CATCH_UP_TICKS`
+fn elapsed_ticks(world_time: &WorldTime, last: &mut u64) -> u64 {
+    let elapsed = world_time.ticks().saturating_sub(*last);
+    *last = world_time.ticks();
+    elapsed.min(MAX_CATCH_UP_TICKS)
+}
+
+/// the change of a column hit by precipitation, the position is relative to the highest block.
+/// `walls` is the number of sides of the space above the highest block closed by a block or water.
+fn precipitate(weather: Weather, top: BlockInner, above_is_air: bool, walls: usize) -> Option<(i64, BlockInner)> {
+    match weather {
+        Weather::Snow if top.btype == SNOW.btype => {
+            if top.data < MAX_SNOWFALL_LAYERS {
+                Some((0, BlockInner { data: top.data + 1, ..top }))
+            } else {
+                None
+            }
+        }
+        Weather::Snow if top.info.contains(BLOCK_MESH) && above_is_air => Some((1, SNOW)),
+        //a hole closed on all sides fills up, in the ground or at the water surface
+        Weather::Rain | Weather::Thunderstorm if top.info.contains(BLOCK_MESH) && above_is_air && walls == 4 => {
+            Some((1, WATER))
+        }
+        _ => None,
+    }
+}
+
+/// the change of the column with the given highest block, `get` returns None for blocks which arent loaded
+fn precipitate_column(
+    weather: Weather,
+    top: BlockPosition,
+    mut get: impl FnMut(BlockPosition) -> Option<BlockInner>,
+) -> Option<(BlockPosition, BlockInner)> {
+    let block = get(top)?;
+    let above = top.with_y(1);
+    //the block above is unknown if its chunk isnt loaded
+    let above_is_air = get(above).map_or(false, |block|block.btype == AIR.btype);
+    let walls = [above.with_x(1), above.with_x(-1), above.with_z(1), above.with_z(-1)].iter()
+        .filter(|position|{
+            get(**position).map_or(false, |block|block.info.contains(BLOCK_MESH) || block.btype == WATER.btype)
+        })
+        .count();
+    precipitate(weather, block, above_is_air, walls).map(|(offset, block)|(top.with_y(offset), block))
+}
+
+pub fn setup_precipitation(commands: &mut Commands, mut meshes: ResMut<Assets<Mesh>>, mut materials: ResMut<Assets<StandardMaterial>>) {
+    commands.insert_resource(PrecipitationAssets {
+        rain_mesh: meshes.add(Mesh::from(shape::Box::new(0.03, 0.5, 0.03))),
+        snow_mesh: meshes.add(Mesh::from(shape::Cube { size: 0.1 })),
+        rain_material: materials.add(StandardMaterial {
+            albedo: Color::rgb(0.6, 0.7, 0.9),
+            albedo_texture: None,
+            shaded: false,
+        }),
+        snow_material: materials.add(StandardMaterial {
+            albedo: Color::WHITE,
+            albedo_texture: None,
+            shaded: false,
+        }),
+    });
+}
+
+/// restores the weather of the opened world
+pub fn load_weather(mut chunk_manager: ResMut<ChunkManager>, mut weather: ResMut<WeatherState>, mut local: ResMut<LocalWeather>) {
+    *weather = chunk_manager.provider_mut().load_weather().unwrap_or_default();
+    *local = LocalWeather::default();
+}
+
+/// advances the weather, clients only apply the changes of the server
+pub fn update_weather(
+    mut chunk_manager: ResMut<ChunkManager>,
+    mut weather: ResMut<WeatherState>,
+    mut local: ResMut<LocalWeather>,
+    world_time: Res<WorldTime>,
+    time: Res<Time>,
+    heightmap: Res<Heightmap>,
+    mut last_tick: Local<u64>,
+    players: Query<&GlobalTransform, With<PlayerMovement>>,
+) {
+    let mut rng = rand::thread_rng();
+    let provider = chunk_manager.provider_mut();
+    while let Some(update) = provider.get_weather_update() {
+        *weather = update;
+    }
+    let ticks = elapsed_ticks(&world_time, &mut last_tick);
+    if provider.owns_weather() && weather.advance(ticks, &mut rng) {
+        provider.save_weather(&weather);
+    }
+
+    local.weather = players.iter().next()
+        .map(|transform|BlockPosition::from_vector(transform.translation))
+        .and_then(|position|heightmap.temperature(position.x(), position.z()))
+        .map_or(Weather::Clear, |temperature|weather.local(temperature));
+    let delta = time.delta_seconds();
+    local.lightning = (local.lightning - LIGHTNING_FADE * delta).max(0.0);
+    if local.weather == Weather::Thunderstorm && rng.gen_bool((LIGHTNING_CHANCE * delta as f64).min(1.0)) {
+        local.lightning = 1.0;
+    }
+}
+
+/// rain fills enclosed holes and snow piles up on exposed blocks in random loaded columns
+pub fn weather_ticks(
+    mut chunk_manager: ResMut<ChunkManager>,
+    weather: Res<WeatherState>,
+    world_time: Res<WorldTime>,
+    heightmap: Res<Heightmap>,
+    mut last_tick: Local<u64>,
+    mut chunks: Query<(&mut Chunk,)>,
+) {
+    let ticks = elapsed_ticks(&world_time, &mut last_tick);
+    if weather.weather() == Weather::Clear {
+        return;
+    }
+    let mut rng = rand::thread_rng();
+    for _ in 0..ticks as usize * COLUMNS_PER_TICK {
+        let (x, z) = match heightmap.random_column(&mut rng) {
+            Some(column) => column,
+            None => return,
+        };
+        let (height, temperature) = match (heightmap.height(x, z), heightmap.temperature(x, z)) {
+            (Some(height), Some(temperature)) => (height, temperature),
+            _ => continue,
+        };
+        let top = BlockPosition::new(x, height, z);
+        let change = precipitate_column(weather.local(temperature), top, |position|{
+            chunk_manager.get_with_mut(position, &mut chunks).copied()
+        });
+        if let Some((position, block)) = change {
+            chunk_manager.set(position, block, &mut chunks);
+        }
+    }
+}
+
+/// moves the rain drops and snow flakes around the player, they stop at the highest block of their column
+pub fn precipitation_particles(
+    commands: &mut Commands,
+    time: Res<Time>,
+    assets: Res<PrecipitationAssets>,
+    weather: Res<WeatherState>,
+    heightmap: Res<Heightmap>,
+    players: Query<&GlobalTransform, With<PlayerMovement>>,
+    mut particles: Query<(Entity, &mut Precipitation, &mut Transform, &mut Visible, &mut Handle<Mesh>, &mut Handle<StandardMaterial>)>,
+) {
+    let center = match players.iter().next() {
+        Some(transform) => transform.translation,
+        None => return,
+    };
+    let clear = weather.weather() == Weather::Clear;
+    let mut count = 0;
+    let mut rng = rand::thread_rng();
+    let ground = |position: Vec3|{
+        heightmap.height(position.x.floor() as i64, position.z.floor() as i64)
+            .map_or(center.y - PARTICLE_HEIGHT, |height|height as f32 + 1.0)
+    };
+
+    for (entity, mut precipitation, mut transform, mut visible, mut mesh, mut material) in particles.iter_mut() {
+        count += 1;
+        transform.translation.y -= precipitation.speed * time.delta_seconds();
+        let position = transform.translation;
+        let horizontal = Vec3::new(position.x - center.x, 0.0, position.z - center.z);
+        if visible.is_visible && position.y >= ground(position) && horizontal.length() <= PARTICLE_RADIUS {
+            continue;
+        }
+        if clear {
+            //the particles disappear as they reach the ground
+            commands.despawn(entity);
+            continue;
+        }
+
+        let position = center + Vec3::new(
+            rng.gen_range(-PARTICLE_RADIUS..PARTICLE_RADIUS),
+            rng.gen_range(0.0..PARTICLE_HEIGHT),
+            rng.gen_range(-PARTICLE_RADIUS..PARTICLE_RADIUS),
+        );
+        let local = heightmap.temperature(position.x.floor() as i64, position.z.floor() as i64)
+            .map_or(Weather::Clear, |temperature|weather.local(temperature));
+        transform.translation = position;
+        //particles under a roof or in a desert are hidden until they are moved again
+        visible.is_visible = local != Weather::Clear && position.y >= ground(position);
+        let snow = local == Weather::Snow;
+        *mesh = if snow { assets.snow_mesh.clone() } else { assets.rain_mesh.clone() };
+        *material = if snow { assets.snow_material.clone() } else { assets.rain_material.clone() };
+        precipitation.speed = if snow { SNOW_SPEED } else { RAIN_SPEED };
+    }
+
+    if clear {
+        return;
+    }
+    for _ in count..PARTICLE_COUNT.min(count + PARTICLE_SPAWN_RATE) {
+        //the hidden particles are placed in the next frame
+        commands
+            .spawn(PbrBundle {
+                mesh: assets.rain_mesh.clone(),
+                material: assets.rain_material.clone(),
+                visible: Visible {
+                    is_visible: false,
+                    is_transparent: false,
+                },
+                ..Default::default()
+            })
+            .with(Precipitation { speed: RAIN_SPEED });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::block_inner::STONE;
+    use crate::world::chunk::ChunkData;
+    use crate::world::coordinates::{BlockVector, ChunkPosition, CHUNK_SIZE};
+    use crate::content::world_generation::generate_chunk;
+
+    #[test]
+    fn precipitation_depends_on_the_temperature() {
+        let mut rng = rand::thread_rng();
+        let mut state = WeatherState::default();
+        state.set(Weather::Rain, &mut rng);
+        assert_eq!(state.local(0.1), Weather::Snow);
+        assert_eq!(state.local(0.5), Weather::Rain);
+        assert_eq!(state.local(0.9), Weather::Clear);
+        assert_eq!(WeatherState::default().local(0.1), Weather::Clear);
+    }
+
+    #[test]
+    fn weather_changes_after_its_duration() {
+        let mut rng = rand::thread_rng();
+        let mut state = WeatherState::new(Weather::Thunderstorm, 10);
+        assert!(!state.advance(9, &mut rng));
+        assert!(state.advance(1, &mut rng));
+        assert_eq!(state.weather(), Weather::Rain);
+    }
+
+    #[test]
+    fn snow_piles_up_and_rain_fills_holes() {
+        assert_eq!(precipitate(Weather::Snow, STONE, true, 0), Some((1, SNOW)));
+        assert_eq!(precipitate(Weather::Snow, STONE, false, 0), None);
+        assert_eq!(precipitate(Weather::Snow, SNOW, false, 0), Some((0, BlockInner { data: 2, ..SNOW })));
+        assert_eq!(precipitate(Weather::Snow, BlockInner { data: MAX_SNOWFALL_LAYERS, ..SNOW }, false, 0), None);
+        assert_eq!(precipitate(Weather::Rain, STONE, true, 3), None);
+        assert_eq!(precipitate(Weather::Rain, STONE, true, 4), Some((1, WATER)));
+        assert_eq!(precipitate(Weather::Rain, WATER, true, 4), None);
+    }
+
+    /// the highest block of the column in the generated chunk
+    fn top(data: &ChunkData, x: i64, z: i64) -> BlockPosition {
+        let y = (0..CHUNK_SIZE).rev().find(|y|data[BlockVector::new(x, *y, z)] != AIR).unwrap();
+        BlockPosition::new(x, y, z)
+    }
+
+    fn rain(data: &ChunkData, top: BlockPosition) -> Option<(BlockPosition, BlockInner)> {
+        precipitate_column(Weather::Rain, top, |position|data.get(position.chunk_relative())
+            .copied()
+            .filter(|_|position.chunk() == ChunkPosition::new(0, 0, 0)))
+    }
+
+    #[test]
+    fn rain_fills_holes_in_generated_terrain() {
+        let mut data = generate_chunk(7, ChunkPosition::new(0, 0, 0));
+        let inner = 1..CHUNK_SIZE - 1;
+        //the generated terrain only fills columns lower than all their neighbours
+        for x in inner.clone() {
+            for z in inner.clone() {
+                let column = top(&data, x, z);
+                let hole = [(1, 0), (-1, 0), (0, 1), (0, -1)].iter()
+                    .all(|(dx, dz)|top(&data, x + dx, z + dz).y() > column.y());
+                assert_eq!(rain(&data, column), if hole { Some((column.with_y(1), WATER)) } else { None });
+            }
+        }
+
+        //a dug hole in a column not lower than its neighbours
+        let (x, z) = inner.clone().flat_map(|x|inner.clone().map(move|z|(x, z)))
+            .find(|(x, z)|{
+                let column = top(&data, *x, *z);
+                column.y() > 0 && [(1, 0), (-1, 0), (0, 1), (0, -1)].iter().all(|(dx, dz)|top(&data, x + dx, z + dz).y() >= column.y())
+            })
+            .unwrap();
+        let column = top(&data, x, z);
+        data[column.chunk_relative()] = AIR;
+        assert_eq!(rain(&data, column.with_y(-1)), Some((column, WATER)));
+        //the border of the loaded world isnt a wall
+        assert_eq!(rain(&data, top(&data, 0, 0)).map(|(_, block)|block), None);
+    }
+}